@@ -0,0 +1,3 @@
+#!/bin/bash
+
+./run_lib/target/release/run_lib parse --backend chunks --split-lines --file /Users/rluvaton/dev/personal/ansi-viewer/examples/fixtures/huge.ans
@@ -2,3 +2,20 @@ pub mod definition;
 pub mod mapping_file_command;
 pub mod parse_command;
 pub mod parse_in_memory_command;
+pub mod render_command;
+
+use ansi_parser_extended::error::Error;
+
+// Passing `-` as the file reads from stdin (e.g. `cargo test --color=always | ansi-parser parse -f -`)
+pub const STDIN_FILE_PATH: &str = "-";
+
+// Print the error to stderr (stdout is used for the output) and exit
+pub fn exit_on_error<T>(result: Result<T, Error>) -> T {
+    return match result {
+        Ok(value) => value,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+}
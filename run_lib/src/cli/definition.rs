@@ -12,7 +12,7 @@ pub fn get_cli() -> Command {
 
             .required(true)
             .value_hint(ValueHint::FilePath)
-            .help("file to read"))
+            .help("file to read, use - to read from stdin"))
 
 
         .arg(Arg::new("in-memory")
@@ -23,6 +23,14 @@ pub fn get_cli() -> Command {
             .conflicts_with_all(["from-line", "to-line", "mapping-file"]))
 
 
+        .arg(Arg::new("backend")
+            .long("backend")
+            .required(false)
+            .value_parser(["chunks", "mmap"])
+            .help("how to read the file, in chunks when not set, mmap must not be used when the file can be truncated while reading it")
+            .conflicts_with("in-memory"))
+
+
         .arg(Arg::new("split-lines")
             .short('s')
             .long("split-lines")
@@ -73,17 +81,131 @@ pub fn get_cli() -> Command {
                 .required(true)
                 .value_hint(ValueHint::FilePath)
                 .help("mapping file to output"),
+        )
+        .arg(
+            Arg::new("compact")
+                .long("compact")
+                .required(false)
+                .help("store each distinct line style once, smaller mapping file for big files")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("every")
+                .long("every")
+                .required(false)
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                .help("store only every N lines, the lines between are parsed from the line before them when read"),
+        )
+        .arg(
+            Arg::new("parallel")
+                .long("parallel")
+                .required(false)
+                .help("parse the input file in multiple threads, the mapping file is the same")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("threads")
+                .long("threads")
+                .required(false)
+                .requires("parallel")
+                .value_parser(clap::value_parser!(usize))
+                .help("number of threads when parsing in parallel, the number of CPU cores when not set"),
+        );
+
+    let update_mapping_command = Command::new("update")
+        .about("Update mapping file of input file that was appended to")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .short_alias('f')
+                .alias("file")
+                .required(true)
+                .value_hint(ValueHint::FilePath)
+                .help("file to read"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .required(true)
+                .value_hint(ValueHint::FilePath)
+                .help("mapping file to update, created when missing or when the input file was rewritten"),
+        );
+
+    let render_command = Command::new("render")
+        .about("Render ANSI text to another format")
+        .arg(
+            Arg::new("file")
+                .short('f')
+                .long("file")
+                .short_alias('i')
+                .alias("input")
+                .required(true)
+                .value_hint(ValueHint::FilePath)
+                .help("file to read, use - to read from stdin"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .required(true)
+                .value_parser(["html"])
+                .help("output format"),
+        )
+        .arg(
+            Arg::new("styles")
+                .long("styles")
+                .required(false)
+                .value_parser(["inline", "classes"])
+                .help("style attribute on each element or CSS classes, inline when not set"),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .alias("palette")
+                .required(false)
+                .help("xterm, vscode, solarized or 16 comma separated hex colors (e.g. #000000,#cd0000,...) optionally followed by the foreground and background, xterm when not set"),
+        )
+        .arg(
+            Arg::new("line-numbers")
+                .long("line-numbers")
+                .required(false)
+                .help("add line numbers that can be linked to")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("document")
+                .long("document")
+                .required(false)
+                .help("output a full HTML document with the stylesheet instead of a fragment")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("stylesheet")
+                .long("stylesheet")
+                .required(false)
+                .conflicts_with("document")
+                .value_hint(ValueHint::FilePath)
+                .help("file to write the stylesheet of the fragment to, needed for the classes, line numbers and blink"),
+        )
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .required(false)
+                .requires("document")
+                .help("title of the HTML document"),
         );
 
     let mapping_command = Command::new("mapping")
         .about("Mapping file for easy access")
         .subcommand(create_mapping_command)
+        .subcommand(update_mapping_command)
         .subcommand_required(true);
 
     return Command::new("Ansi Parser CLI")
         .version("1.0.0")
         .author("Raz Luvaton")
         .about("Parse ANSI text")
-        .subcommands([parse_command, mapping_command])
+        .subcommands([parse_command, mapping_command, render_command])
         .subcommand_required(true);
 }
@@ -1,7 +1,12 @@
 use std::ffi::OsString;
 
 use std::path::PathBuf;
-use ansi_parser_extended::mapping_file::create::create_mapping_file_from_input_path;
+use ansi_parser_extended::mapping_file::create::create_sparse_mapping_file;
+use ansi_parser_extended::mapping_file::header::MappingLayout;
+use ansi_parser_extended::mapping_file::parallel::{create_mapping_file_in_parallel, ParallelOptions};
+use ansi_parser_extended::mapping_file::update::{update_mapping_file_from_input_path, MappingFileUpdate};
+
+use crate::cli::exit_on_error;
 
 pub fn run_create_mapping_file_command(matches: &clap::ArgMatches) {
     let input_path = matches
@@ -10,11 +15,51 @@ pub fn run_create_mapping_file_command(matches: &clap::ArgMatches) {
     let output_path = matches
         .get_one::<String>("output")
         .expect("Should have been able to get the output file path");
+    let layout = if matches.get_flag("compact") {
+        MappingLayout::Compact
+    } else {
+        MappingLayout::Fixed
+    };
+    let checkpoint_interval = matches.get_one::<usize>("every").copied().unwrap_or(1);
+
+    if matches.get_flag("parallel") {
+        exit_on_error(create_mapping_file_in_parallel(
+            PathBuf::from(OsString::from(output_path)),
+            PathBuf::from(OsString::from(input_path)),
+            layout,
+            checkpoint_interval,
+            ParallelOptions {
+                threads: matches.get_one::<usize>("threads").copied(),
+                chunk_size_in_bytes: None,
+            },
+        ));
+    } else {
+        exit_on_error(create_sparse_mapping_file(
+            PathBuf::from(OsString::from(output_path)),
+            PathBuf::from(OsString::from(input_path)),
+            layout,
+            checkpoint_interval,
+        ));
+    }
 
-    create_mapping_file_from_input_path(
+    println!("Done");
+}
+
+pub fn run_update_mapping_file_command(matches: &clap::ArgMatches) {
+    let input_path = matches
+        .get_one::<String>("input")
+        .expect("Should have been able to get the input file path");
+    let output_path = matches
+        .get_one::<String>("output")
+        .expect("Should have been able to get the output file path");
+
+    let update = exit_on_error(update_mapping_file_from_input_path(
         PathBuf::from(OsString::from(output_path)),
         PathBuf::from(OsString::from(input_path)),
-    );
+    ));
 
-    println!("Done");
+    match update {
+        MappingFileUpdate::Appended { new_lines } => println!("Done, added {} lines", new_lines),
+        MappingFileUpdate::Recreated => println!("Done, the mapping file could not be updated so it was created again"),
+    }
 }
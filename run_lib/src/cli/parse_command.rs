@@ -1,14 +1,16 @@
 use std::ffi::OsString;
+use std::io;
 use std::path::PathBuf;
 
-use ansi_parser_extended::files::file_reader::FileReaderOptions;
+use ansi_parser_extended::files::file_reader::{FileReaderBackend, FileReaderOptions};
 use ansi_parser_extended::parse_ansi_text::ansi::types::Span;
 use ansi_parser_extended::parse_ansi_text::parse_options::ParseOptions;
-use ansi_parser_extended::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
-use ansi_parser_extended::parse_file::file_to_spans::read_ansi_file_to_spans;
+use ansi_parser_extended::parse_file::file_to_lines_of_spans::{read_ansi_file_to_lines, read_ansi_to_lines};
+use ansi_parser_extended::parse_file::file_to_spans::{read_ansi_file_to_spans, read_ansi_to_spans};
 use ansi_parser_extended::parse_file::from_middle_of_file::get_from_middle_of_the_file_info;
 use ansi_parser_extended::parse_file::types::ReadAnsiFileOptions;
 
+use crate::cli::{exit_on_error, STDIN_FILE_PATH};
 use crate::cli::parse_in_memory_command::run_parse_command_in_memory;
 
 pub fn run_parse_command(matches: &clap::ArgMatches) {
@@ -24,21 +26,41 @@ pub fn run_parse_command(matches: &clap::ArgMatches) {
     let from_line = matches.get_one::<usize>("from-line");
     let to_line = matches.get_one::<usize>("to-line");
     let mapping_file = matches.get_one::<String>("mapping-file");
+    let backend = matches.get_one::<String>("backend").map(|backend| match backend.as_str() {
+        "mmap" => FileReaderBackend::Mmap,
+        _ => FileReaderBackend::Chunks,
+    });
 
     let file_path = matches
         .get_one::<String>("file")
         .expect("Should have been able to get the file path");
 
+    if file_path == STDIN_FILE_PATH {
+        if from_line.is_some() || to_line.is_some() || mapping_file.is_some() {
+            eprintln!("Reading from line to line is not supported when reading from stdin");
+            std::process::exit(1);
+        }
+
+        run_parse_command_on_stdin(split_by_lines);
+        return;
+    }
+
     let input_file_path = PathBuf::from(OsString::from(file_path));
 
-    let middle_of_file_info =
-        get_from_middle_of_the_file_info(input_file_path, from_line.copied(), to_line.copied(), mapping_file.cloned());
+    let middle_of_file_info = exit_on_error(get_from_middle_of_the_file_info(
+        input_file_path,
+        from_line.copied(),
+        to_line.copied(),
+        mapping_file.cloned(),
+    ));
 
     let file_reader_options = FileReaderOptions {
         file_path: file_path.clone(),
         chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
         from_bytes: middle_of_file_info.from_bytes,
         to_bytes: middle_of_file_info.to_bytes,
+        seek_point: middle_of_file_info.seek_point,
+        backend,
     };
     let parse_options = ParseOptions::default()
         .with_initial_span(middle_of_file_info.initial_span.unwrap_or(Span::empty()));
@@ -49,16 +71,30 @@ pub fn run_parse_command(matches: &clap::ArgMatches) {
     };
 
     if !split_by_lines {
-        let spans_iterator = read_ansi_file_to_spans(options);
+        let spans_iterator = exit_on_error(read_ansi_file_to_spans(options));
 
-        spans_iterator.into_iter().for_each(|_| {
-            // Noop
+        spans_iterator.into_iter().for_each(|item| {
+            exit_on_error(item);
         });
     } else {
-        let lines_iterator = read_ansi_file_to_lines(options);
+        let lines_iterator = exit_on_error(read_ansi_file_to_lines(options));
 
-        lines_iterator.into_iter().for_each(|_| {
-            // Noop
+        lines_iterator.into_iter().for_each(|item| {
+            exit_on_error(item);
+        });
+    }
+}
+
+fn run_parse_command_on_stdin(split_by_lines: bool) {
+    let stdin = io::stdin().lock();
+
+    if !split_by_lines {
+        read_ansi_to_spans(stdin, ParseOptions::default()).for_each(|item| {
+            exit_on_error(item);
+        });
+    } else {
+        read_ansi_to_lines(stdin, ParseOptions::default()).for_each(|item| {
+            exit_on_error(item);
         });
     }
 }
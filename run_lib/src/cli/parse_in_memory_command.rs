@@ -1,9 +1,13 @@
 use std::ffi::OsString;
+use std::io::Read;
 use std::path::PathBuf;
 
+use ansi_parser_extended::parse_ansi_text::parse_options::ParseOptions;
 use ansi_parser_extended::parse_file::text_to_lines_of_spans::buffer_to_lines;
 use ansi_parser_extended::parse_file::text_to_spans::buffer_to_spans;
 
+use crate::cli::{exit_on_error, STDIN_FILE_PATH};
+
 pub fn run_parse_command_in_memory(matches: &clap::ArgMatches) {
     let split_by_lines = *matches.get_one::<bool>("split-lines").unwrap();
 
@@ -11,22 +15,28 @@ pub fn run_parse_command_in_memory(matches: &clap::ArgMatches) {
         .get_one::<String>("file")
         .expect("Should have been able to get the file path");
 
-    let input_file_path = PathBuf::from(OsString::from(file_path));
+    let file_content = if file_path == STDIN_FILE_PATH {
+        let mut content = vec![];
+        exit_on_error(std::io::stdin().lock().read_to_end(&mut content).map_err(Into::into));
 
-    let file_content = std::fs::read(&input_file_path).expect("Failed to read file");
+        content
+    } else {
+        let input_file_path = PathBuf::from(OsString::from(file_path));
 
+        exit_on_error(std::fs::read(&input_file_path).map_err(Into::into))
+    };
 
     if !split_by_lines {
-        let spans_iterator = buffer_to_spans(file_content.as_slice());
+        let spans_iterator = buffer_to_spans(file_content.as_slice(), ParseOptions::default());
 
-        spans_iterator.into_iter().for_each(|_| {
-            // Noop
+        spans_iterator.into_iter().for_each(|item| {
+            exit_on_error(item);
         });
     } else {
-        let lines_iterator = buffer_to_lines(file_content.as_slice());
+        let lines_iterator = buffer_to_lines(file_content.as_slice(), ParseOptions::default());
 
-        lines_iterator.into_iter().for_each(|_| {
-            // Noop
+        lines_iterator.into_iter().for_each(|item| {
+            exit_on_error(item);
         });
     }
 }
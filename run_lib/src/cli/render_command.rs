@@ -0,0 +1,82 @@
+use std::io;
+
+use ansi_parser_extended::error::Error;
+use ansi_parser_extended::files::file_reader::FileReaderOptions;
+use ansi_parser_extended::output::html::{get_html_stylesheet, lines_to_html, HtmlMode, HtmlOptions, HtmlStyles};
+use ansi_parser_extended::parse_ansi_text::ansi::theme::Theme;
+use ansi_parser_extended::parse_ansi_text::parse_options::ParseOptions;
+use ansi_parser_extended::parse_file::file_to_lines_of_spans::{read_ansi_file_to_lines, read_ansi_to_lines};
+use ansi_parser_extended::parse_file::types::ReadAnsiFileOptions;
+use ansi_parser_extended::types::Line;
+
+use crate::cli::{exit_on_error, STDIN_FILE_PATH};
+
+pub fn run_render_command(matches: &clap::ArgMatches) {
+    let file_path = matches
+        .get_one::<String>("file")
+        .expect("Should have been able to get the file path");
+
+    // Only html is supported for now
+    let options = get_html_options(matches);
+
+    // The fragment has no stylesheet, so it is written to a separate file
+    if let Some(stylesheet_path) = matches.get_one::<String>("stylesheet") {
+        exit_on_error(std::fs::write(stylesheet_path, get_html_stylesheet(&options)).map_err(Into::into));
+    }
+
+    if file_path == STDIN_FILE_PATH {
+        print_html(read_ansi_to_lines(io::stdin().lock(), ParseOptions::default()), options);
+        return;
+    }
+
+    let lines = exit_on_error(read_ansi_file_to_lines(ReadAnsiFileOptions {
+        file_options: FileReaderOptions {
+            file_path: file_path.clone(),
+            chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
+            from_bytes: None,
+            to_bytes: None,
+            seek_point: None,
+            backend: None,
+        },
+        parse_options: ParseOptions::default(),
+    }));
+
+    print_html(lines, options);
+}
+
+fn get_html_options(matches: &clap::ArgMatches) -> HtmlOptions {
+    let theme = matches.get_one::<String>("theme").map(|theme| match Theme::from_name(theme).or_else(|| Theme::parse(theme)) {
+        Some(theme) => theme,
+        None => {
+            eprintln!("Invalid theme, expected xterm, vscode, solarized or 16 comma separated hex colors (e.g. #000000,#cd0000,...)");
+            std::process::exit(1);
+        }
+    });
+
+    let styles = matches.get_one::<String>("styles").map(|styles| match styles.as_str() {
+        "classes" => HtmlStyles::Classes,
+        _ => HtmlStyles::Inline,
+    });
+
+    let mode = if matches.get_flag("document") {
+        HtmlMode::Document {
+            title: matches.get_one::<String>("title").cloned().unwrap_or("ANSI output".to_string()),
+        }
+    } else {
+        HtmlMode::Fragment
+    };
+
+    return HtmlOptions {
+        styles,
+        theme,
+        line_numbers: matches.get_flag("line-numbers"),
+        first_line_number: None,
+        mode: Some(mode),
+    };
+}
+
+fn print_html(lines: impl Iterator<Item=Result<Line, Error>>, options: HtmlOptions) {
+    lines_to_html(lines, options).for_each(|html| {
+        print!("{}", exit_on_error(html));
+    });
+}
@@ -8,8 +8,9 @@ extern crate core;
 mod cli;
 
 use crate::cli::definition::get_cli;
-use crate::cli::mapping_file_command::run_create_mapping_file_command;
+use crate::cli::mapping_file_command::{run_create_mapping_file_command, run_update_mapping_file_command};
 use crate::cli::parse_command::run_parse_command;
+use crate::cli::render_command::run_render_command;
 
 
 fn run_cli() {
@@ -28,6 +29,15 @@ fn run_cli() {
         return;
     }
 
+    if command == "render" {
+        run_render_command(
+            matches
+                .subcommand_matches("render")
+                .expect("Should have been able to get the render subcommand"),
+        );
+        return;
+    }
+
     if command == "mapping" {
         let matches = matches
             .subcommand_matches("mapping")
@@ -43,6 +53,12 @@ fn run_cli() {
                     .subcommand_matches("create")
                     .expect("Should have been able to get the create subcommand"),
             );
+        } else if command == "update" {
+            run_update_mapping_file_command(
+                matches
+                    .subcommand_matches("update")
+                    .expect("Should have been able to get the update subcommand"),
+            );
         } else {
             panic!("Unknown mapping subcommand: {}", command);
         }
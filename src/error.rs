@@ -0,0 +1,112 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    // Opening, reading or writing the input, output or mapping file failed
+    Io(std::io::Error),
+
+    // Path that can't be converted to a string
+    InvalidPath(PathBuf),
+
+    // Valid ANSI sequence that has no effect on the style (e.g. cursor movement `\x1b[2A`)
+    UnsupportedSequence(String),
+
+    // Graphics mode parameter that is not known (e.g. `\x1b[1000m`)
+    UnsupportedGraphicsMode(String),
+
+    // Color that is missing arguments or has unknown color space (e.g. `\x1b[38;7m` or `\x1b[38;2;1m`)
+    InvalidColor(String),
+
+    // Escape character that is not followed by a valid sequence (e.g. `\x1b[1\x07`)
+    MalformedEscape,
+
+    // Escape sequence that is not finished when the text ends (e.g. `\x1b[1`)
+    UnterminatedEscape,
+
+    // The first malformed sequence when parsing in strict mode
+    MalformedSequence {
+        // Byte offset from the start of the parsed text
+        location: usize,
+        error: Box<Error>,
+    },
+
+    InvalidMappingFile(String),
+
+    // Mapping file that was created with an older format (e.g. version 1 that is not portable between architectures)
+    UnsupportedMappingFileVersion(u32),
+
+    // Mapping file that was created for a different input file or before the input file was changed
+    StaleMappingFile(String),
+
+    // Lines in the mapping file start from 1
+    InvalidLineNumber(usize),
+
+    // The requested line is after the end of the file
+    LineNotFound(usize),
+
+    // A thread that parsed part of the input panicked, with the panic message
+    ParsingThreadPanicked(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::Io(error) => write!(formatter, "IO error: {}", error),
+            Error::InvalidPath(path) => write!(formatter, "Invalid path: {:?}", path),
+            Error::UnsupportedSequence(sequence) => write!(formatter, "Unsupported ANSI sequence: {}", sequence),
+            Error::UnsupportedGraphicsMode(parameters) => {
+                write!(formatter, "Unsupported graphics mode: {}", parameters)
+            }
+            Error::InvalidColor(parameters) => write!(formatter, "Invalid color: {}", parameters),
+            Error::MalformedEscape => write!(formatter, "Escape character is not followed by a valid sequence"),
+            Error::UnterminatedEscape => write!(formatter, "Escape sequence is not terminated"),
+            Error::MalformedSequence { location, error } => write!(formatter, "{} at byte {}", error, location),
+            Error::InvalidMappingFile(reason) => write!(formatter, "Invalid mapping file: {}", reason),
+            Error::UnsupportedMappingFileVersion(version) => write!(
+                formatter,
+                "Unsupported mapping file version {}, create the mapping file again",
+                version
+            ),
+            Error::StaleMappingFile(reason) => write!(
+                formatter,
+                "Mapping file does not match the input file ({}), create the mapping file again",
+                reason
+            ),
+            Error::InvalidLineNumber(line_number) => {
+                write!(formatter, "Invalid line number {}, line number must be at least 1", line_number)
+            }
+            Error::LineNotFound(line_number) => write!(formatter, "Line {} does not exist", line_number),
+            Error::ParsingThreadPanicked(message) => write!(formatter, "Parsing thread panicked: {}", message),
+        };
+    }
+}
+
+impl Error {
+    // Unsupported sequences are valid, they just have no effect on the style
+    pub fn is_malformed(&self) -> bool {
+        return matches!(
+            self,
+            Error::InvalidColor(_)
+                | Error::MalformedEscape
+                | Error::UnterminatedEscape
+                | Error::MalformedSequence { .. }
+        );
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            Error::Io(error) => Some(error),
+            Error::MalformedSequence { error, .. } => Some(error.as_ref()),
+            _ => None,
+        };
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Error {
+        return Error::Io(error);
+    }
+}
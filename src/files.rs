@@ -1,2 +1,7 @@
+pub mod chunk_reader;
+pub mod compression;
 pub mod file_reader;
 pub mod file_size;
+pub mod follow_reader;
+pub mod gzip;
+pub mod mapped_file;
@@ -0,0 +1,157 @@
+use std::io::{ErrorKind, Read};
+
+use crate::error::Error;
+
+pub const DEFAULT_CHUNK_SIZE_IN_BYTES: usize = 64 * 1024; // 64KB
+
+// Same as `FileReader` but for any reader (stdin, pipes, sockets, decompressors, ...),
+// `BufRead` implementations (e.g. `StdinLock`) can be used as well as they implement `Read`
+//
+// Chunks are returned as soon as the reader return data (chunks can be smaller than the chunk size)
+// so piped output is parsed while it's written and not only when the writer is done
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    chunk_size_in_bytes: usize,
+    done: bool,
+}
+
+impl<R: Read> ChunkReader<R> {
+    pub fn new(reader: R) -> ChunkReader<R> {
+        ChunkReader {
+            reader,
+            chunk_size_in_bytes: DEFAULT_CHUNK_SIZE_IN_BYTES,
+            done: false,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size_in_bytes: usize) -> ChunkReader<R> {
+        // Empty chunk means end of the reader so the size must not be 0
+        self.chunk_size_in_bytes = chunk_size_in_bytes.max(1);
+        self
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut chunk = vec![0; self.chunk_size_in_bytes];
+
+        loop {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(read_bytes) => {
+                    chunk.truncate(read_bytes);
+                    return Some(Ok(chunk));
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {
+                    // Retry, nothing was read
+                }
+                Err(error) => {
+                    // Can't know where the reader is at after an error
+                    self.done = true;
+                    return Some(Err(Error::Io(error)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    // Return at most `max_read` bytes on every read and fail with the given error kinds before reading
+    struct ShortReader {
+        data: Vec<u8>,
+        position: usize,
+        max_read: usize,
+        errors: Vec<ErrorKind>,
+    }
+
+    impl Read for ShortReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if let Some(kind) = self.errors.pop() {
+                return Err(std::io::Error::new(kind, "test error"));
+            }
+
+            let end = (self.position + self.max_read.min(buf.len())).min(self.data.len());
+            let read_bytes = end - self.position;
+
+            buf[..read_bytes].copy_from_slice(&self.data[self.position..end]);
+            self.position = end;
+
+            return Ok(read_bytes);
+        }
+    }
+
+    fn read_all_chunks<R: Read>(reader: ChunkReader<R>) -> Vec<Vec<u8>> {
+        return reader.map(|chunk| chunk.expect("read chunk")).collect();
+    }
+
+    #[test]
+    fn should_read_the_whole_reader_in_chunks() {
+        let reader = ChunkReader::new(Cursor::new(b"Hello, World!".to_vec())).with_chunk_size(5);
+
+        let chunks = read_all_chunks(reader);
+
+        assert_eq!(chunks, vec![b"Hello".to_vec(), b", Wor".to_vec(), b"ld!".to_vec()]);
+    }
+
+    #[test]
+    fn empty_reader_should_have_no_chunks() {
+        let reader = ChunkReader::new(Cursor::new(vec![]));
+
+        assert_eq!(read_all_chunks(reader), Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    fn should_return_chunk_as_soon_as_reader_return_data() {
+        let reader = ChunkReader::new(ShortReader {
+            data: b"Hello, World!".to_vec(),
+            position: 0,
+            max_read: 4,
+            errors: vec![],
+        });
+
+        let chunks = read_all_chunks(reader);
+
+        assert_eq!(chunks, vec![b"Hell".to_vec(), b"o, W".to_vec(), b"orld".to_vec(), b"!".to_vec()]);
+    }
+
+    #[test]
+    fn should_retry_when_read_is_interrupted() {
+        let reader = ChunkReader::new(ShortReader {
+            data: b"Hello".to_vec(),
+            position: 0,
+            max_read: 10,
+            errors: vec![ErrorKind::Interrupted, ErrorKind::Interrupted],
+        });
+
+        assert_eq!(read_all_chunks(reader), vec![b"Hello".to_vec()]);
+    }
+
+    #[test]
+    fn should_stop_after_read_error() {
+        let mut reader = ChunkReader::new(ShortReader {
+            data: b"Hello".to_vec(),
+            position: 0,
+            max_read: 10,
+            errors: vec![ErrorKind::BrokenPipe],
+        });
+
+        assert!(matches!(reader.next(), Some(Err(Error::Io(_)))));
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,491 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+use crate::error::Error;
+use crate::files::gzip::{GzipMemberDecoder, DEFAULT_CHECKPOINT_SPAN};
+
+const GZIP_MAGIC_BYTES: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC_BYTES: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC_BYTES: &[u8] = &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+
+const LONGEST_MAGIC_BYTES_LENGTH: usize = XZ_MAGIC_BYTES.len();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    // Detect by the magic bytes and not by the file extension, as logs are not always named correctly
+    pub fn detect(header: &[u8]) -> Compression {
+        if header.starts_with(GZIP_MAGIC_BYTES) {
+            return Compression::Gzip;
+        }
+
+        if header.starts_with(ZSTD_MAGIC_BYTES) {
+            return Compression::Zstd;
+        }
+
+        if header.starts_with(XZ_MAGIC_BYTES) {
+            return Compression::Xz;
+        }
+
+        return Compression::None;
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Compression> {
+        return match name {
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        };
+    }
+}
+
+// Detect the compression of the file, the file position is restored to the start
+pub fn detect_file_compression(file: &mut File) -> Result<Compression, Error> {
+    let mut header = Vec::with_capacity(LONGEST_MAGIC_BYTES_LENGTH);
+
+    file.take(LONGEST_MAGIC_BYTES_LENGTH as u64).read_to_end(&mut header)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    return Ok(Compression::detect(&header));
+}
+
+// Position in the compressed file where decompression can start from without the data before it
+//
+// Every gzip member, zstd frame and xz stream can be decompressed on its own,
+// so the seek points are at the start of each of them (e.g. zstd seekable format, `pigz --independent` or concatenated logs)
+//
+// Gzip also has checkpoints in the middle of a member, at the start of a deflate block,
+// which need the bit the block starts at and the data before it that the block can refer to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekPoint {
+    pub compressed_offset: usize,
+    pub uncompressed_offset: usize,
+
+    // The bit in the byte at the compressed offset where the deflate block starts
+    pub bit_offset: u8,
+
+    // The last 32KB before the checkpoint, None when it is the start of a member
+    pub window: Option<Arc<[u8]>>,
+}
+
+impl SeekPoint {
+    pub fn start() -> SeekPoint {
+        return SeekPoint::member_start(0, 0);
+    }
+
+    pub fn member_start(compressed_offset: usize, uncompressed_offset: usize) -> SeekPoint {
+        return SeekPoint {
+            compressed_offset,
+            uncompressed_offset,
+            bit_offset: 0,
+            window: None,
+        };
+    }
+}
+
+// Get the closest seek point that is before the uncompressed location
+pub fn find_seek_point(seek_points: &[SeekPoint], uncompressed_location: usize) -> SeekPoint {
+    return seek_points
+        .iter()
+        .take_while(|seek_point| seek_point.uncompressed_offset <= uncompressed_location)
+        .last()
+        .cloned()
+        .unwrap_or(SeekPoint::start());
+}
+
+// Count the bytes that the decoder consumed, so we know where each member ends in the compressed file
+struct CountingReader<R: BufRead> {
+    reader: R,
+    position: usize,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read_bytes = self.reader.read(buf)?;
+        self.position += read_bytes;
+
+        return Ok(read_bytes);
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        return self.reader.fill_buf();
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt;
+        self.reader.consume(amt);
+    }
+}
+
+// Decoder for a single gzip member, zstd frame or xz stream,
+// the buffered decoders only consume the member bytes so the next member starts right after
+enum MemberDecoder<R: BufRead> {
+    Gzip(GzipMemberDecoder<R>),
+    Zstd(zstd::stream::read::Decoder<'static, R>),
+    Xz(XzStreamDecoder<R>),
+}
+
+// `xz2::bufread::XzDecoder` fails when another stream is right after the current one instead of stopping,
+// so the stream end is tracked here
+struct XzStreamDecoder<R: BufRead> {
+    reader: R,
+    stream: xz2::stream::Stream,
+    ended: bool,
+}
+
+impl<R: BufRead> XzStreamDecoder<R> {
+    fn new(reader: R) -> std::io::Result<XzStreamDecoder<R>> {
+        let stream = xz2::stream::Stream::new_stream_decoder(u64::MAX, 0)?;
+
+        return Ok(XzStreamDecoder {
+            reader,
+            stream,
+            ended: false,
+        });
+    }
+}
+
+impl<R: BufRead> Read for XzStreamDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while !self.ended {
+            let input = self.reader.fill_buf()?;
+            let eof = input.is_empty();
+
+            let before_out = self.stream.total_out();
+            let before_in = self.stream.total_in();
+
+            let action = if eof { xz2::stream::Action::Finish } else { xz2::stream::Action::Run };
+            let status = self.stream.process(input, buf, action)?;
+
+            let read_bytes = (self.stream.total_out() - before_out) as usize;
+            let consumed = (self.stream.total_in() - before_in) as usize;
+
+            self.reader.consume(consumed);
+
+            if status == xz2::stream::Status::StreamEnd {
+                self.ended = true;
+            } else if eof && read_bytes == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "xz stream is not complete"));
+            }
+
+            if read_bytes > 0 {
+                return Ok(read_bytes);
+            }
+        }
+
+        return Ok(0);
+    }
+}
+
+impl<R: BufRead> MemberDecoder<R> {
+    // Only gzip can start from a checkpoint in the middle of a member
+    fn new(compression: Compression, reader: R, start: SeekPoint, checkpoint_span: usize) -> std::io::Result<MemberDecoder<R>> {
+        return match compression {
+            Compression::Gzip => Ok(MemberDecoder::Gzip(GzipMemberDecoder::new(reader, start, checkpoint_span)?)),
+            Compression::Zstd => Ok(MemberDecoder::Zstd(
+                zstd::stream::read::Decoder::with_buffer(reader)?.single_frame(),
+            )),
+            Compression::Xz => Ok(MemberDecoder::Xz(XzStreamDecoder::new(reader)?)),
+            Compression::None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "file is not compressed",
+            )),
+        };
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        return match self {
+            MemberDecoder::Gzip(decoder) => decoder.read(buf),
+            MemberDecoder::Zstd(decoder) => decoder.read(buf),
+            MemberDecoder::Xz(decoder) => decoder.read(buf),
+        };
+    }
+
+    fn take_checkpoints(&mut self) -> Vec<SeekPoint> {
+        return match self {
+            MemberDecoder::Gzip(decoder) => decoder.take_checkpoints(),
+            MemberDecoder::Zstd(_) | MemberDecoder::Xz(_) => vec![],
+        };
+    }
+
+    fn into_inner(self) -> R {
+        return match self {
+            MemberDecoder::Gzip(decoder) => decoder.into_inner(),
+            MemberDecoder::Zstd(decoder) => decoder.finish(),
+            MemberDecoder::Xz(decoder) => decoder.reader,
+        };
+    }
+}
+
+// The seek points a decompressed reader passed, shared so they are available after the reader was moved into a parser
+pub type SeekPointCollector = Arc<Mutex<Vec<SeekPoint>>>;
+
+// Stream decompress all the members in the reader and record the seek points between and inside them
+pub struct DecompressedReader<R: BufRead> {
+    compression: Compression,
+    checkpoint_span: usize,
+
+    // Only None while switching to the next member
+    decoder: Option<MemberDecoder<CountingReader<R>>>,
+
+    // The offsets are relative to the seek point the reader started from
+    start_compressed_offset: usize,
+    uncompressed_position: usize,
+    seek_points: SeekPointCollector,
+}
+
+impl<R: BufRead> DecompressedReader<R> {
+    // The reader must be at the seek point
+    pub fn new(compression: Compression, reader: R, start: SeekPoint) -> Result<DecompressedReader<R>, Error> {
+        return DecompressedReader::with_checkpoint_span(compression, reader, start, DEFAULT_CHECKPOINT_SPAN);
+    }
+
+    // The minimum uncompressed distance between gzip checkpoints inside a member
+    pub fn with_checkpoint_span(
+        compression: Compression,
+        reader: R,
+        start: SeekPoint,
+        checkpoint_span: usize,
+    ) -> Result<DecompressedReader<R>, Error> {
+        let reader = CountingReader { reader, position: 0 };
+
+        return Ok(DecompressedReader {
+            compression,
+            checkpoint_span,
+            decoder: Some(MemberDecoder::new(compression, reader, start.clone(), checkpoint_span)?),
+            start_compressed_offset: start.compressed_offset,
+            uncompressed_position: start.uncompressed_offset,
+            seek_points: Arc::new(Mutex::new(vec![start])),
+        });
+    }
+
+    // The seek points that were passed so far, all of them are available after reading until the end
+    pub fn seek_points(&self) -> Vec<SeekPoint> {
+        return self.seek_points.lock().expect("seek points lock").clone();
+    }
+
+    // Keep getting the seek points while the reader is read by something else
+    pub fn seek_point_collector(&self) -> SeekPointCollector {
+        return self.seek_points.clone();
+    }
+}
+
+impl<R: BufRead> Read for DecompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let decoder = match self.decoder.as_mut() {
+                Some(decoder) => decoder,
+                None => return Ok(0),
+            };
+
+            let read_bytes = decoder.read(buf)?;
+            let checkpoints = decoder.take_checkpoints();
+
+            if !checkpoints.is_empty() {
+                self.seek_points.lock().expect("seek points lock").extend(checkpoints);
+            }
+
+            if read_bytes > 0 {
+                self.uncompressed_position += read_bytes;
+                return Ok(read_bytes);
+            }
+
+            // The current member ended, check if there is another one after it
+            let mut reader = self.decoder.take().unwrap().into_inner();
+
+            if reader.fill_buf()?.is_empty() {
+                return Ok(0);
+            }
+
+            let member_start = SeekPoint::member_start(self.start_compressed_offset + reader.position, self.uncompressed_position);
+
+            self.seek_points.lock().expect("seek points lock").push(member_start.clone());
+
+            self.decoder = Some(MemberDecoder::new(self.compression, reader, member_start, self.checkpoint_span)?);
+        }
+    }
+}
+
+// Open the compressed file and start decompressing from the seek point
+pub fn open_decompressed_file(
+    mut file: File,
+    compression: Compression,
+    seek_point: SeekPoint,
+) -> Result<DecompressedReader<BufReader<File>>, Error> {
+    file.seek(SeekFrom::Start(seek_point.compressed_offset as u64))?;
+
+    return DecompressedReader::new(compression, BufReader::new(file), seek_point);
+}
+
+// Compress the data in memory for the tests of the compressed files
+#[cfg(test)]
+pub(crate) fn compress(compression: Compression, data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    return match compression {
+        Compression::None => data.to_vec(),
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(data).expect("compress");
+            encoder.finish().expect("compress")
+        }
+        Compression::Zstd => zstd::encode_all(data, 0).expect("compress"),
+        Compression::Xz => {
+            let mut encoder = xz2::write::XzEncoder::new(vec![], 6);
+            encoder.write_all(data).expect("compress");
+            encoder.finish().expect("compress")
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn decompress(compression: Compression, compressed: &[u8], seek_point: SeekPoint) -> (Vec<u8>, Vec<SeekPoint>) {
+        let mut reader = DecompressedReader::new(
+            compression,
+            Cursor::new(&compressed[seek_point.compressed_offset..]),
+            seek_point,
+        )
+        .expect("create reader");
+
+        let mut output = vec![];
+        reader.read_to_end(&mut output).expect("decompress");
+
+        return (output, reader.seek_points());
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn should_detect_compression_by_magic_bytes(compression: Compression) {
+        assert_eq!(Compression::detect(&compress(compression, b"Hello")), compression);
+    }
+
+    #[test_case(b""; "empty")]
+    #[test_case(b"\x1b[31mHello"; "text")]
+    #[test_case(b"\x1f"; "partial magic bytes")]
+    fn should_not_detect_compression_for_text(header: &[u8]) {
+        assert_eq!(Compression::detect(header), Compression::None);
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn should_decompress_single_member(compression: Compression) {
+        let (output, seek_points) = decompress(compression, &compress(compression, b"Hello, World!"), SeekPoint::start());
+
+        assert_eq!(output, b"Hello, World!");
+        assert_eq!(seek_points, vec![SeekPoint::start()]);
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn should_have_seek_point_for_every_member(compression: Compression) {
+        let first = compress(compression, b"Hello, ");
+        let second = compress(compression, b"World");
+        let compressed = [first.clone(), second.clone(), compress(compression, b"!")].concat();
+
+        let (output, seek_points) = decompress(compression, &compressed, SeekPoint::start());
+
+        assert_eq!(output, b"Hello, World!");
+        assert_eq!(
+            seek_points,
+            vec![
+                SeekPoint::start(),
+                SeekPoint::member_start(first.len(), 7),
+                SeekPoint::member_start(first.len() + second.len(), 12),
+            ]
+        );
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn should_decompress_from_seek_point(compression: Compression) {
+        let first = compress(compression, b"Hello, ");
+        let compressed = [first.clone(), compress(compression, b"World!")].concat();
+        let seek_point = SeekPoint::member_start(first.len(), 7);
+
+        let (output, seek_points) = decompress(compression, &compressed, seek_point.clone());
+
+        assert_eq!(output, b"World!");
+        assert_eq!(seek_points, vec![seek_point]);
+    }
+
+    #[test]
+    fn gzip_should_have_seek_points_inside_members_and_decompress_from_them() {
+        let first = compress(Compression::Gzip, b"Hello, World!\n");
+        let text = (0..20000u32)
+            .map(|index| format!("\x1b[3{}mline {:x}\n", index % 8, index.wrapping_mul(2654435761)))
+            .collect::<String>();
+        let compressed = [first.clone(), compress(Compression::Gzip, text.as_bytes())].concat();
+        let full_text = "Hello, World!\n".to_string() + &text;
+
+        let mut reader = DecompressedReader::with_checkpoint_span(Compression::Gzip, Cursor::new(&compressed), SeekPoint::start(), 1024 * 16)
+            .expect("create reader");
+
+        let mut output = vec![];
+        reader.read_to_end(&mut output).expect("decompress");
+
+        let seek_points = reader.seek_points();
+
+        assert_eq!(output, full_text.as_bytes());
+        assert_eq!(seek_points[..2], [SeekPoint::start(), SeekPoint::member_start(first.len(), 14)]);
+        assert!(seek_points.len() > 3, "expected checkpoints inside the member, got {:?}", seek_points.len());
+
+        for seek_point in &seek_points[2..] {
+            assert!(seek_point.window.is_some());
+
+            let (output, _) = decompress(Compression::Gzip, &compressed, seek_point.clone());
+
+            assert_eq!(output, full_text.as_bytes()[seek_point.uncompressed_offset..]);
+        }
+    }
+
+    #[test]
+    fn find_seek_point_should_return_the_closest_seek_point_before_the_location() {
+        let seek_points = vec![
+            SeekPoint::start(),
+            SeekPoint::member_start(10, 100),
+            SeekPoint::member_start(20, 200),
+        ];
+
+        assert_eq!(find_seek_point(&seek_points, 0), seek_points[0]);
+        assert_eq!(find_seek_point(&seek_points, 99), seek_points[0]);
+        assert_eq!(find_seek_point(&seek_points, 100), seek_points[1]);
+        assert_eq!(find_seek_point(&seek_points, 1000), seek_points[2]);
+        assert_eq!(find_seek_point(&[], 1000), SeekPoint::start());
+    }
+}
@@ -2,82 +2,154 @@ use const_typed_builder::Builder;
 use get_chunk::iterator::FileIter;
 use get_chunk::ChunkSize;
 use std::fs::File;
+use std::io::{BufReader, Read};
+
+use crate::error::Error;
+use crate::files::chunk_reader::{ChunkReader, DEFAULT_CHUNK_SIZE_IN_BYTES};
+use crate::files::compression::{detect_file_compression, open_decompressed_file, Compression, DecompressedReader, SeekPoint};
+
+enum FileSource {
+    Plain(Box<FileIter<File>>),
+
+    // Compressed files are decompressed while reading, the positions are in the decompressed data
+    Compressed(Box<ChunkReader<DecompressedReader<BufReader<File>>>>),
+}
 
 pub struct FileReader {
-    file_iter: FileIter<File>,
+    source: FileSource,
     to_bytes: Option<usize>,
     current_position: usize,
 }
 
 impl Iterator for FileReader {
-    type Item = Vec<u8>;
+    type Item = Result<Vec<u8>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.to_bytes.is_some() && self.current_position >= self.to_bytes.unwrap() {
             return None;
         }
 
-        let chunk = self
-            .file_iter
-            .next()
-            .map(|item| item.expect("Failed to get file chunk"));
-
-        if chunk.is_none() {
-            return None;
-        }
-
-        let chunk = chunk.unwrap();
+        let mut chunk = match &mut self.source {
+            FileSource::Plain(file_iter) => match file_iter.next()? {
+                Ok(chunk) => chunk,
+                Err(error) => return Some(Err(Error::Io(error))),
+            },
+            FileSource::Compressed(chunk_reader) => match chunk_reader.next()? {
+                Ok(chunk) => chunk,
+                Err(error) => return Some(Err(error)),
+            },
+        };
 
         if let Some(to_bytes) = self.to_bytes {
+            // Last chunk in the range, keep only the bytes before to_bytes
             if self.current_position + chunk.len() > to_bytes {
+                chunk.truncate(to_bytes - self.current_position);
                 self.current_position = to_bytes;
-                return Some(chunk[..self.current_position - to_bytes].to_vec());
+
+                return Some(Ok(chunk));
             }
         }
 
         self.current_position += chunk.len();
 
-        return Some(chunk);
+        return Some(Ok(chunk));
     }
 }
 
 impl FileReader {
-    pub fn new(options: FileReaderOptions) -> FileReader {
-        let file = File::open(options.file_path).expect("opening input file path failed");
+    pub fn new(options: FileReaderOptions) -> Result<FileReader, Error> {
+        let mut file = File::open(&options.file_path)?;
+
+        let compression = detect_file_compression(&mut file)?;
+
+        if compression != Compression::None {
+            return FileReader::new_compressed(file, compression, options);
+        }
 
-        let mut file_iter = FileIter::try_from(file).expect("create input file iterator failed");
+        let mut file_iter = FileIter::try_from(file)?;
 
         if let Some(chunk_size) = options.chunk_size_in_bytes {
             file_iter = file_iter.set_mode(ChunkSize::Bytes(chunk_size));
         }
 
         if let Some(from_bytes) = options.from_bytes {
-            file_iter = file_iter
-                .set_start_position_bytes(from_bytes)
-                .expect("Failed to set start position");
+            file_iter = file_iter.set_start_position_bytes(from_bytes)?;
         }
 
-        FileReader {
-            file_iter,
+        Ok(FileReader {
+            source: FileSource::Plain(Box::new(file_iter)),
             to_bytes: options.to_bytes,
             current_position: options.from_bytes.unwrap_or(0),
-        }
+        })
+    }
+
+    fn new_compressed(file: File, compression: Compression, options: FileReaderOptions) -> Result<FileReader, Error> {
+        let from_bytes = options.from_bytes.unwrap_or(0);
+
+        // Can't seek to the exact position in compressed file, so start from the closest seek point and skip the rest
+        let seek_point = options
+            .seek_point
+            .filter(|seek_point| seek_point.uncompressed_offset <= from_bytes)
+            .unwrap_or(SeekPoint::start());
+
+        let start_offset = seek_point.uncompressed_offset;
+        let mut reader = open_decompressed_file(file, compression, seek_point)?;
+
+        let bytes_to_skip = (from_bytes - start_offset) as u64;
+        let skipped_bytes = std::io::copy(&mut (&mut reader).take(bytes_to_skip), &mut std::io::sink())?;
+
+        let chunk_reader = ChunkReader::new(reader)
+            .with_chunk_size(options.chunk_size_in_bytes.unwrap_or(DEFAULT_CHUNK_SIZE_IN_BYTES));
+
+        Ok(FileReader {
+            source: FileSource::Compressed(Box::new(chunk_reader)),
+            to_bytes: options.to_bytes,
+            current_position: start_offset + skipped_bytes as usize,
+        })
     }
 }
 
+// The bytes that are read are `from_bytes..to_bytes` (from is inclusive and to is exclusive),
+// reading from the start of the file when from_bytes is not set and until the end of the file when to_bytes is not set
 #[derive(Debug, Builder)]
 pub struct FileReaderOptions {
     pub chunk_size_in_bytes: Option<usize>,
     pub file_path: String,
+
+    // The first byte to read (inclusive)
     pub from_bytes: Option<usize>,
+
+    // The byte to stop before (exclusive), nothing is read when it's not after from_bytes
     pub to_bytes: Option<usize>,
+
+    // Where to start decompressing from when the file is compressed (from the mapping file),
+    // decompression starts from the beginning of the file when not set
+    pub seek_point: Option<SeekPoint>,
+
+    // How to read the file, in chunks when not set
+    pub backend: Option<FileReaderBackend>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileReaderBackend {
+    // Read the file in chunks that are copied to memory
+    Chunks,
+
+    // Map the file to memory and parse it in place without copying,
+    // compressed files are always read in chunks as they need to be decompressed
+    //
+    // The file must not be truncated while it's mapped (e.g. log rotation), as it kills the process instead of failing
+    Mmap,
 }
 
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
     use tempfile::NamedTempFile;
 
+    use crate::files::compression::compress;
+
     use super::*;
 
     fn get_tmp_file_path() -> String {
@@ -100,7 +172,7 @@ mod tests {
     fn read_all_file_from_iterator(mut reader: FileReader) -> Vec<u8> {
         let mut result = Vec::new();
         while let Some(chunk) = reader.next() {
-            result.extend(chunk);
+            result.extend(chunk.expect("read chunk"));
         }
 
         return result;
@@ -115,7 +187,7 @@ mod tests {
             //
             .file_path(tmp_file_path.clone())
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -132,7 +204,7 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .chunk_size_in_bytes(Some(1))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -149,7 +221,7 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .from_bytes(expected_file_string.find("World!"))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -166,7 +238,7 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .to_bytes(expected_file_string.find(","))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -184,7 +256,7 @@ mod tests {
             .from_bytes(expected_file_string.find("World"))
             .to_bytes(expected_file_string.find("!"))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -202,7 +274,7 @@ mod tests {
             .from_bytes(expected_file_string.find("World"))
             .chunk_size_in_bytes(Some(1))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -220,7 +292,7 @@ mod tests {
             .to_bytes(expected_file_string.find(" "))
             .chunk_size_in_bytes(Some(1))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -239,7 +311,7 @@ mod tests {
             .to_bytes(expected_file_string.find("!"))
             .chunk_size_in_bytes(Some(1))
             .build();
-        let file_reader = FileReader::new(options);
+        let file_reader = FileReader::new(options).expect("create file reader");
 
         let result = read_all_file_from_iterator(file_reader);
 
@@ -256,10 +328,11 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .chunk_size_in_bytes(Some(1))
             .build();
-        let mut file_reader = FileReader::new(options);
+        let mut file_reader = FileReader::new(options).expect("create file reader");
 
         let mut result = Vec::new();
         while let Some(chunk) = file_reader.next() {
+            let chunk = chunk.expect("read chunk");
             assert_eq!(chunk.len(), 1);
             result.extend(chunk);
         }
@@ -277,11 +350,11 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .chunk_size_in_bytes(Some(3))
             .build();
-        let mut file_reader = FileReader::new(options);
+        let mut file_reader = FileReader::new(options).expect("create file reader");
 
         let mut result = Vec::new();
         while let Some(chunk) = file_reader.next() {
-            result.push(chunk);
+            result.push(chunk.expect("read chunk"));
         }
 
         let sizes: Vec<usize> = result
@@ -315,11 +388,11 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .chunk_size_in_bytes(Some(expected_file_string.len()))
             .build();
-        let mut file_reader = FileReader::new(options);
+        let mut file_reader = FileReader::new(options).expect("create file reader");
 
         let mut result = Vec::new();
         while let Some(chunk) = file_reader.next() {
-            result.push(chunk);
+            result.push(chunk.expect("read chunk"));
         }
 
         assert_eq!(result, vec![expected_file_string]);
@@ -335,13 +408,127 @@ mod tests {
             .file_path(tmp_file_path.clone())
             .chunk_size_in_bytes(Some(expected_file_string.len() + 10))
             .build();
-        let mut file_reader = FileReader::new(options);
+        let mut file_reader = FileReader::new(options).expect("create file reader");
 
         let mut result = Vec::new();
         while let Some(chunk) = file_reader.next() {
-            result.push(chunk);
+            result.push(chunk.expect("read chunk"));
         }
 
         assert_eq!(result, vec![expected_file_string]);
     }
+
+    #[test]
+    fn test_read_compressed_file_should_return_decompressed_content() {
+        let expected_file_string = b"Hello, World!";
+        let tmp_file_path = create_tmp_file(&compress(Compression::Gzip, expected_file_string));
+
+        let options = FileReaderOptions::builder()
+            //
+            .file_path(tmp_file_path.clone())
+            .chunk_size_in_bytes(Some(3))
+            .build();
+        let file_reader = FileReader::new(options).expect("create file reader");
+
+        let result = read_all_file_from_iterator(file_reader);
+
+        assert_eq!(result, expected_file_string);
+    }
+
+    #[test]
+    fn test_read_compressed_file_from_seek_point_start_and_ends_in_ending_position() {
+        let first_member = compress(Compression::Gzip, b"Hello, ");
+        let tmp_file_path = create_tmp_file(&[first_member.clone(), compress(Compression::Gzip, b"World!")].concat());
+
+        let options = FileReaderOptions::builder()
+            //
+            .file_path(tmp_file_path.clone())
+            .seek_point(Some(SeekPoint::member_start(first_member.len(), 7)))
+            .from_bytes(Some(8))
+            .to_bytes(Some(12))
+            .chunk_size_in_bytes(Some(1))
+            .build();
+        let file_reader = FileReader::new(options).expect("create file reader");
+
+        let result = read_all_file_from_iterator(file_reader);
+
+        assert_eq!(result, b"orld");
+    }
+
+    #[test]
+    fn test_read_file_ends_in_ending_position_in_the_middle_of_chunk() {
+        let expected_file_string = "Hello, World!";
+        let tmp_file_path = create_tmp_file(expected_file_string.as_bytes());
+
+        let options = FileReaderOptions::builder()
+            //
+            .file_path(tmp_file_path.clone())
+            .from_bytes(expected_file_string.find("World"))
+            .to_bytes(expected_file_string.find("!"))
+            .chunk_size_in_bytes(Some(4))
+            .build();
+        let file_reader = FileReader::new(options).expect("create file reader");
+
+        let result = read_all_file_from_iterator(file_reader);
+
+        assert_eq!(result, b"World");
+    }
+
+    #[test]
+    fn test_read_file_with_ending_position_before_starting_position_should_be_empty() {
+        let expected_file_string = "Hello, World!";
+        let tmp_file_path = create_tmp_file(expected_file_string.as_bytes());
+
+        let options = FileReaderOptions::builder()
+            //
+            .file_path(tmp_file_path.clone())
+            .from_bytes(Some(5))
+            .to_bytes(Some(3))
+            .build();
+        let file_reader = FileReader::new(options).expect("create file reader");
+
+        let result = read_all_file_from_iterator(file_reader);
+
+        assert_eq!(result, b"");
+    }
+
+    fn expected_range(content: &[u8], from_bytes: Option<usize>, to_bytes: Option<usize>) -> Vec<u8> {
+        let from = from_bytes.unwrap_or(0).min(content.len());
+        let to = to_bytes.unwrap_or(content.len()).min(content.len());
+
+        if from >= to {
+            return vec![];
+        }
+
+        return content[from..to].to_vec();
+    }
+
+    proptest! {
+        #[test]
+        fn test_read_file_range_should_return_exactly_the_requested_bytes(
+            content in proptest::collection::vec(any::<u8>(), 0..200),
+            chunk_size in 1usize..300,
+            from_bytes in proptest::option::of(0usize..220),
+            to_bytes in proptest::option::of(0usize..220),
+            compressed in any::<bool>(),
+        ) {
+            let file_content = if compressed { compress(Compression::Gzip, &content) } else { content.clone() };
+            let tmp_file_path = create_tmp_file(&file_content);
+
+            let options = FileReaderOptions::builder()
+                //
+                .file_path(tmp_file_path.clone())
+                .chunk_size_in_bytes(Some(chunk_size))
+                .from_bytes(from_bytes)
+                .to_bytes(to_bytes)
+                .build();
+            let file_reader = FileReader::new(options).expect("create file reader");
+
+            let result = read_all_file_from_iterator(file_reader);
+
+            std::fs::remove_file(tmp_file_path).expect("remove temp file");
+
+            prop_assert_eq!(result, expected_range(&content, from_bytes, to_bytes));
+        }
+    }
 }
@@ -1,7 +1,9 @@
 use std::fs;
 use std::path::PathBuf;
 
-pub fn get_file_size(path: PathBuf) -> usize {
-    let metadata = fs::metadata(path).expect("Failed to get metadata");
-    metadata.len() as usize
+use crate::error::Error;
+
+pub fn get_file_size(path: PathBuf) -> Result<usize, Error> {
+    let metadata = fs::metadata(path)?;
+    Ok(metadata.len() as usize)
 }
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::fs::{File, Metadata};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use const_typed_builder::Builder;
+
+use crate::error::Error;
+use crate::files::chunk_reader::DEFAULT_CHUNK_SIZE_IN_BYTES;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Called with the poll interval at the end of the file, returns false to stop following
+pub type FollowWait = Arc<dyn Fn(Duration) -> bool + Send + Sync>;
+
+#[derive(Clone, Builder)]
+pub struct FollowOptions {
+    // How long to wait before checking again for new bytes when reaching the end of the file
+    pub poll_interval: Option<Duration>,
+
+    // Stop following when no new bytes were written for this long, follow forever when not set
+    pub stop_when_idle_for: Option<Duration>,
+
+    // Used instead of sleeping for the poll interval (e.g. to wait for a file watcher event or to stop when the writing process exited)
+    pub wait: Option<FollowWait>,
+}
+
+impl fmt::Debug for FollowOptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("FollowOptions")
+            .field("poll_interval", &self.poll_interval)
+            .field("stop_when_idle_for", &self.stop_when_idle_for)
+            .field("wait", &self.wait.is_some())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowEnd {
+    // The file was truncated or replaced (e.g. log rotation), it should be read again from the start
+    Restart,
+
+    // Nothing was written for `stop_when_idle_for`, the wait function stopped following or reading failed
+    Stop,
+}
+
+// Same as `FileReader` but like `tail -f`, at the end of the file it waits for more bytes instead of ending,
+// the iteration ends when the file is truncated or replaced so the parser can start again from a known state
+pub struct FollowReader {
+    file: File,
+    file_path: String,
+    options: FollowOptions,
+    chunk_size_in_bytes: usize,
+    position: usize,
+    last_read_at: Instant,
+    end: Option<FollowEnd>,
+}
+
+impl FollowReader {
+    pub fn new(
+        file_path: String,
+        from_bytes: usize,
+        chunk_size_in_bytes: Option<usize>,
+        options: FollowOptions,
+    ) -> Result<FollowReader, Error> {
+        let mut file = File::open(&file_path)?;
+
+        // Starting after the end of a truncated file is the same as starting from the end
+        let position = from_bytes.min(file.metadata()?.len() as usize);
+        file.seek(SeekFrom::Start(position as u64))?;
+
+        return Ok(FollowReader {
+            file,
+            file_path,
+            options,
+            // Empty chunk is never returned so the size must not be 0
+            chunk_size_in_bytes: chunk_size_in_bytes.unwrap_or(DEFAULT_CHUNK_SIZE_IN_BYTES).max(1),
+            position,
+            last_read_at: Instant::now(),
+            end: None,
+        });
+    }
+
+    // Why the iteration ended, None while following
+    pub fn end(&self) -> Option<FollowEnd> {
+        return self.end;
+    }
+
+    fn was_truncated_or_replaced(&self) -> Result<bool, Error> {
+        let current_metadata = self.file.metadata()?;
+
+        if (current_metadata.len() as usize) < self.position {
+            return Ok(true);
+        }
+
+        let metadata_at_path = match std::fs::metadata(&self.file_path) {
+            Ok(metadata) => metadata,
+
+            // Moved away and not created yet, keep waiting on the current file
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(Error::Io(error)),
+        };
+
+        return Ok(!is_same_file(&current_metadata, &metadata_at_path));
+    }
+
+    fn is_idle(&self) -> bool {
+        return match self.options.stop_when_idle_for {
+            Some(stop_when_idle_for) => self.last_read_at.elapsed() >= stop_when_idle_for,
+            None => false,
+        };
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    return a.dev() == b.dev() && a.ino() == b.ino();
+}
+
+// Without file identity only truncation can be detected
+#[cfg(not(unix))]
+fn is_same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    return true;
+}
+
+impl Iterator for FollowReader {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.end.is_some() {
+            return None;
+        }
+
+        let mut chunk = vec![0; self.chunk_size_in_bytes];
+
+        loop {
+            let read_result = self.file.read(&mut chunk);
+
+            match read_result {
+                Ok(0) => {}
+                Ok(read_bytes) => {
+                    chunk.truncate(read_bytes);
+                    self.position += read_bytes;
+                    self.last_read_at = Instant::now();
+
+                    return Some(Ok(chunk));
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    self.end = Some(FollowEnd::Stop);
+                    return Some(Err(Error::Io(error)));
+                }
+            }
+
+            // At the end of the file, the rest of the current file is already read when it's replaced
+            match self.was_truncated_or_replaced() {
+                Ok(true) => {
+                    self.end = Some(FollowEnd::Restart);
+                    return None;
+                }
+                Ok(false) => {}
+                Err(error) => {
+                    self.end = Some(FollowEnd::Stop);
+                    return Some(Err(error));
+                }
+            }
+
+            if self.is_idle() {
+                self.end = Some(FollowEnd::Stop);
+                return None;
+            }
+
+            let poll_interval = self.options.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+
+            let keep_following = match &self.options.wait {
+                Some(wait) => wait(poll_interval),
+                None => {
+                    std::thread::sleep(poll_interval);
+                    true
+                }
+            };
+
+            if !keep_following {
+                self.end = Some(FollowEnd::Stop);
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    // Each wait at the end of the file runs the next write instead of sleeping, and following stops when there are no more writes
+    fn follow_options(writes: Vec<Box<dyn FnOnce() + Send>>) -> FollowOptions {
+        let writes = Mutex::new(VecDeque::from(writes));
+
+        return FollowOptions {
+            poll_interval: None,
+            stop_when_idle_for: None,
+            wait: Some(Arc::new(move |_| match writes.lock().unwrap().pop_front() {
+                Some(write) => {
+                    write();
+                    true
+                }
+                None => false,
+            })),
+        };
+    }
+
+    fn create_follow_reader(file: &NamedTempFile, from_bytes: usize, options: FollowOptions) -> FollowReader {
+        let file_path = file.path().to_str().expect("convert to string").to_string();
+
+        return FollowReader::new(file_path, from_bytes, None, options).expect("create follow reader");
+    }
+
+    #[test]
+    fn should_stop_when_idle_after_reading_the_whole_file() {
+        let mut file = NamedTempFile::new().expect("create temp file");
+        file.write_all(b"Hello").expect("write");
+
+        let options = FollowOptions {
+            poll_interval: None,
+            stop_when_idle_for: Some(Duration::ZERO),
+            wait: None,
+        };
+        let mut reader = create_follow_reader(&file, 1, options);
+
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b"ello".to_vec()));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.end(), Some(FollowEnd::Stop));
+    }
+
+    #[test]
+    fn should_return_bytes_written_after_reaching_the_end() {
+        let mut file = NamedTempFile::new().expect("create temp file");
+        file.write_all(b"Hello").expect("write");
+
+        let mut writer = std::fs::OpenOptions::new().append(true).open(file.path()).expect("open file");
+        let mut reader = create_follow_reader(
+            &file,
+            0,
+            follow_options(vec![Box::new(move || writer.write_all(b", World!").expect("write"))]),
+        );
+
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b"Hello".to_vec()));
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b", World!".to_vec()));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.end(), Some(FollowEnd::Stop));
+    }
+
+    #[test]
+    fn should_restart_when_the_file_is_truncated() {
+        let mut file = NamedTempFile::new().expect("create temp file");
+        file.write_all(b"Hello, World!").expect("write");
+
+        let writer = file.reopen().expect("reopen file");
+        let mut reader = create_follow_reader(
+            &file,
+            0,
+            follow_options(vec![Box::new(move || writer.set_len(2).expect("truncate"))]),
+        );
+
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b"Hello, World!".to_vec()));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.end(), Some(FollowEnd::Restart));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_restart_when_the_file_is_replaced() {
+        let directory = tempfile::tempdir().expect("create temp dir");
+        let file_path = directory.path().join("build.log");
+        std::fs::write(&file_path, b"old").expect("write");
+
+        let rotated_file_path = file_path.clone();
+        let rotated_directory_path = directory.path().to_path_buf();
+
+        let options = follow_options(vec![Box::new(move || {
+            // Log rotation
+            std::fs::rename(&rotated_file_path, rotated_directory_path.join("build.log.1")).expect("rename");
+            std::fs::write(&rotated_file_path, b"new file").expect("write");
+        })]);
+
+        let mut reader = FollowReader::new(file_path.to_str().expect("convert to string").to_string(), 0, None, options)
+            .expect("create follow reader");
+
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b"old".to_vec()));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.end(), Some(FollowEnd::Restart));
+    }
+
+    #[test]
+    fn should_stop_when_the_wait_function_stops_following() {
+        let mut file = NamedTempFile::new().expect("create temp file");
+        file.write_all(b"Hello").expect("write");
+
+        let mut reader = create_follow_reader(&file, 0, follow_options(vec![]));
+
+        assert_eq!(reader.next().map(|chunk| chunk.expect("read chunk")), Some(b"Hello".to_vec()));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.end(), Some(FollowEnd::Stop));
+    }
+}
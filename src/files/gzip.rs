@@ -0,0 +1,769 @@
+use std::io::{BufRead, Error, ErrorKind, Read, Result};
+use std::sync::Arc;
+
+use crate::files::compression::SeekPoint;
+
+// Gzip decoder that records checkpoints in the middle of a member and can start decompressing from them (like zlib's zran),
+// so a file that is a single gzip member can still be read from the middle
+//
+// A checkpoint is at the start of a deflate block, which can start at any bit and refer to the 32KB before it,
+// so the checkpoint has the bit the block starts at and the window that was before it
+
+// Deflate can refer to at most the last 32KB of the output
+pub const WINDOW_SIZE: usize = 32 * 1024;
+
+// Each checkpoint keeps a window, so they are far apart to keep the mapping file small
+pub const DEFAULT_CHECKPOINT_SPAN: usize = 1024 * 1024 * 4; // 4MB
+
+const GZIP_ID: [u8; 2] = [0x1f, 0x8b];
+const DEFLATE_METHOD: u8 = 8;
+
+const FLAG_HEADER_CRC: u8 = 1 << 1;
+const FLAG_EXTRA: u8 = 1 << 2;
+const FLAG_NAME: u8 = 1 << 3;
+const FLAG_COMMENT: u8 = 1 << 4;
+
+const END_OF_BLOCK: usize = 256;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// The order the code lengths of the code length alphabet are in the dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn invalid_data(message: &str) -> Error {
+    return Error::new(ErrorKind::InvalidData, format!("invalid gzip data: {}", message));
+}
+
+// Deflate packs the bits from the least significant bit of each byte
+struct BitReader<R: BufRead> {
+    reader: R,
+    bit_buffer: u64,
+    bit_count: u32,
+
+    // Bytes taken from the reader, including the ones that are still in the bit buffer
+    consumed: usize,
+}
+
+impl<R: BufRead> BitReader<R> {
+    // Take whole bytes only, so at most 7 bytes after the deflate stream are taken before it ends (less than the gzip trailer)
+    fn refill(&mut self) -> Result<()> {
+        while self.bit_count <= 56 {
+            let input = self.reader.fill_buf()?;
+
+            if input.is_empty() {
+                break;
+            }
+
+            let count = input.len().min(((64 - self.bit_count) / 8) as usize);
+
+            for byte in &input[..count] {
+                self.bit_buffer |= (*byte as u64) << self.bit_count;
+                self.bit_count += 8;
+            }
+
+            self.reader.consume(count);
+            self.consumed += count;
+        }
+
+        return Ok(());
+    }
+
+    // The missing bits at the end of the input are zeros
+    fn peek(&mut self, count: u32) -> Result<u64> {
+        if self.bit_count < count {
+            self.refill()?;
+        }
+
+        return Ok(self.bit_buffer & ((1u64 << count) - 1));
+    }
+
+    fn skip(&mut self, count: u32) -> Result<()> {
+        if self.bit_count < count {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "gzip stream is not complete"));
+        }
+
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+
+        return Ok(());
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u64> {
+        let bits = self.peek(count)?;
+        self.skip(count)?;
+
+        return Ok(bits);
+    }
+
+    fn align_to_byte(&mut self) {
+        let partial_bits = self.bit_count % 8;
+
+        self.bit_buffer >>= partial_bits;
+        self.bit_count -= partial_bits;
+    }
+
+    // Must be aligned to a byte, reads from the bit buffer first and then exactly the needed bytes from the reader
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+
+        while filled < buf.len() && self.bit_count >= 8 {
+            buf[filled] = self.bit_buffer as u8;
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+            filled += 1;
+        }
+
+        self.reader.read_exact(&mut buf[filled..])?;
+        self.consumed += buf.len() - filled;
+
+        return Ok(());
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut byte = [0u8; 1];
+        self.read_bytes(&mut byte)?;
+
+        return Ok(byte[0]);
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.read_bytes(&mut bytes)?;
+
+        return Ok(u16::from_le_bytes(bytes));
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.read_bytes(&mut bytes)?;
+
+        return Ok(u32::from_le_bytes(bytes));
+    }
+
+    // The position of the next bit from where the reader started
+    fn bit_position(&self) -> usize {
+        return self.consumed * 8 - self.bit_count as usize;
+    }
+}
+
+struct HuffmanTable {
+    // Indexed by the next `max_length` bits, each entry is the symbol and the code length (in the lowest 4 bits),
+    // 0 when the bits are not a code
+    entries: Vec<u16>,
+    max_length: u32,
+}
+
+impl HuffmanTable {
+    // The canonical Huffman code of the code lengths of the symbols
+    fn new(lengths: &[u8]) -> Result<HuffmanTable> {
+        let mut length_count = [0u32; 16];
+
+        for length in lengths {
+            length_count[*length as usize] += 1;
+        }
+
+        length_count[0] = 0;
+
+        // Incomplete codes are allowed (e.g. a single distance code) but not codes with too many symbols
+        let mut available_codes = 1i64;
+
+        for count in &length_count[1..] {
+            available_codes = (available_codes << 1) - *count as i64;
+
+            if available_codes < 0 {
+                return Err(invalid_data("oversubscribed Huffman code"));
+            }
+        }
+
+        let mut next_code = [0u32; 16];
+
+        for length in 1..16 {
+            next_code[length] = (next_code[length - 1] + length_count[length - 1]) << 1;
+        }
+
+        let max_length = lengths.iter().max().copied().unwrap_or(0) as u32;
+        let mut entries = vec![0u16; 1 << max_length];
+
+        for (symbol, length) in lengths.iter().enumerate() {
+            let length = *length as u32;
+
+            if length == 0 {
+                continue;
+            }
+
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+
+            // The codes are packed from the most significant bit so they are reversed
+            let mut index = (code.reverse_bits() >> (32 - length)) as usize;
+
+            while index < entries.len() {
+                entries[index] = ((symbol as u16) << 4) | length as u16;
+                index += 1 << length;
+            }
+        }
+
+        return Ok(HuffmanTable { entries, max_length });
+    }
+
+    fn fixed_literals() -> HuffmanTable {
+        let lengths = (0..288)
+            .map(|symbol| match symbol {
+                0..=143 => 8,
+                144..=255 => 9,
+                256..=279 => 7,
+                _ => 8,
+            })
+            .collect::<Vec<u8>>();
+
+        return HuffmanTable::new(&lengths).expect("fixed code should be valid");
+    }
+
+    fn fixed_distances() -> HuffmanTable {
+        return HuffmanTable::new(&[5; 30]).expect("fixed code should be valid");
+    }
+
+    fn decode<R: BufRead>(&self, bits: &mut BitReader<R>) -> Result<usize> {
+        let entry = self.entries[bits.peek(self.max_length)? as usize];
+        let length = (entry & 0xf) as u32;
+
+        if length == 0 {
+            return Err(invalid_data("invalid Huffman code"));
+        }
+
+        bits.skip(length)?;
+
+        return Ok((entry >> 4) as usize);
+    }
+}
+
+enum BlockState {
+    // Before the header of the next block
+    Header,
+    Stored { remaining: usize },
+    Huffman { literals: HuffmanTable, distances: HuffmanTable },
+
+    // The last block ended
+    End,
+}
+
+// Decoder for a single gzip member, the reader is left right after the member
+pub struct GzipMemberDecoder<R: BufRead> {
+    bits: BitReader<R>,
+    state: BlockState,
+    last_block: bool,
+
+    // The window the next blocks can refer to followed by the output that was not read yet
+    output: Vec<u8>,
+    read_position: usize,
+
+    // Where the decoder started, the positions of the checkpoints are from it
+    start: SeekPoint,
+    decompressed_bytes: usize,
+
+    // The output before it was already counted in the decompressed bytes
+    counted_length: usize,
+
+    // Only when decompressing from the start of the member, as the checksum is of the whole member
+    crc: Option<crc32fast::Hasher>,
+
+    checkpoint_span: usize,
+    last_checkpoint_offset: usize,
+    checkpoints: Vec<SeekPoint>,
+}
+
+impl<R: BufRead> GzipMemberDecoder<R> {
+    // The reader must be at the seek point, which is either the start of a member or a checkpoint
+    pub fn new(reader: R, start: SeekPoint, checkpoint_span: usize) -> Result<GzipMemberDecoder<R>> {
+        let mut bits = BitReader {
+            reader,
+            bit_buffer: 0,
+            bit_count: 0,
+            consumed: 0,
+        };
+
+        let (output, crc) = match &start.window {
+            Some(window) => {
+                // The block starts in the middle of the byte
+                bits.bits(start.bit_offset as u32)?;
+
+                (window.to_vec(), None)
+            }
+            None => {
+                read_header(&mut bits)?;
+
+                (vec![], Some(crc32fast::Hasher::new()))
+            }
+        };
+
+        return Ok(GzipMemberDecoder {
+            bits,
+            state: BlockState::Header,
+            last_block: false,
+            read_position: output.len(),
+            counted_length: output.len(),
+            output,
+            last_checkpoint_offset: start.uncompressed_offset,
+            start,
+            decompressed_bytes: 0,
+            crc,
+            checkpoint_span,
+            checkpoints: vec![],
+        });
+    }
+
+    // The checkpoints that were passed since the last call
+    pub fn take_checkpoints(&mut self) -> Vec<SeekPoint> {
+        return std::mem::take(&mut self.checkpoints);
+    }
+
+    pub fn into_inner(self) -> R {
+        return self.bits.reader;
+    }
+
+    fn pending(&self) -> usize {
+        return self.output.len() - self.read_position;
+    }
+
+    // Decompress until there are at least `wanted` bytes that were not read or the member ended
+    fn decompress(&mut self, wanted: usize) -> Result<()> {
+        self.discard_read_output();
+
+        while self.pending() < wanted {
+            let pending = self.pending();
+
+            match &mut self.state {
+                BlockState::Header => self.read_block_header()?,
+                BlockState::Stored { remaining } => {
+                    let length = (*remaining).min(wanted - pending);
+                    let start = self.output.len();
+
+                    self.output.resize(start + length, 0);
+                    self.bits.read_bytes(&mut self.output[start..])?;
+
+                    *remaining -= length;
+
+                    if *remaining == 0 {
+                        self.end_block()?;
+                    }
+                }
+                BlockState::Huffman { literals, distances } => {
+                    let block_ended = decompress_huffman_block(&mut self.bits, literals, distances, &mut self.output, self.read_position + wanted)?;
+
+                    if block_ended {
+                        self.end_block()?;
+                    }
+                }
+                BlockState::End => return Ok(()),
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Keep only the window of the output that was read
+    fn discard_read_output(&mut self) {
+        if self.read_position < WINDOW_SIZE * 4 {
+            return;
+        }
+
+        self.count_output();
+
+        let discarded = self.read_position - WINDOW_SIZE;
+
+        self.output.drain(..discarded);
+        self.read_position -= discarded;
+        self.counted_length -= discarded;
+    }
+
+    // The checksum is of the output as it is decompressed, as the trailer can be read before all the output was read
+    fn count_output(&mut self) {
+        let new_output = &self.output[self.counted_length..];
+
+        if let Some(crc) = self.crc.as_mut() {
+            crc.update(new_output);
+        }
+
+        self.decompressed_bytes += new_output.len();
+        self.counted_length = self.output.len();
+    }
+
+    fn read_block_header(&mut self) -> Result<()> {
+        if self.last_block {
+            return self.read_trailer();
+        }
+
+        self.last_block = self.bits.bits(1)? == 1;
+
+        self.state = match self.bits.bits(2)? {
+            0 => {
+                self.bits.align_to_byte();
+
+                let length = self.bits.read_u16()?;
+                let length_complement = self.bits.read_u16()?;
+
+                if length != !length_complement {
+                    return Err(invalid_data("stored block length does not match its complement"));
+                }
+
+                BlockState::Stored { remaining: length as usize }
+            }
+            1 => BlockState::Huffman {
+                literals: HuffmanTable::fixed_literals(),
+                distances: HuffmanTable::fixed_distances(),
+            },
+            2 => read_dynamic_tables(&mut self.bits)?,
+            _ => return Err(invalid_data("invalid block type")),
+        };
+
+        // An empty stored block has no data
+        if matches!(self.state, BlockState::Stored { remaining: 0 }) {
+            self.end_block()?;
+        }
+
+        return Ok(());
+    }
+
+    fn end_block(&mut self) -> Result<()> {
+        self.count_output();
+
+        self.state = BlockState::Header;
+
+        if self.last_block {
+            return Ok(());
+        }
+
+        let uncompressed_offset = self.start.uncompressed_offset + self.decompressed_bytes;
+
+        if uncompressed_offset - self.last_checkpoint_offset < self.checkpoint_span {
+            return Ok(());
+        }
+
+        // The bit position includes the bits that were skipped to get to the start
+        let bit_position = self.start.compressed_offset * 8 + self.bits.bit_position();
+        let window_start = self.output.len().saturating_sub(WINDOW_SIZE);
+
+        self.checkpoints.push(SeekPoint {
+            compressed_offset: bit_position / 8,
+            uncompressed_offset,
+            bit_offset: (bit_position % 8) as u8,
+            window: Some(Arc::from(&self.output[window_start..])),
+        });
+        self.last_checkpoint_offset = uncompressed_offset;
+
+        return Ok(());
+    }
+
+    fn read_trailer(&mut self) -> Result<()> {
+        self.count_output();
+        self.bits.align_to_byte();
+
+        let crc = self.bits.read_u32()?;
+        let size = self.bits.read_u32()?;
+
+        if let Some(hasher) = self.crc.take() {
+            if hasher.finalize() != crc {
+                return Err(invalid_data("checksum does not match"));
+            }
+
+            if self.decompressed_bytes as u32 != size {
+                return Err(invalid_data("size does not match"));
+            }
+        }
+
+        self.state = BlockState::End;
+
+        return Ok(());
+    }
+}
+
+impl<R: BufRead> Read for GzipMemberDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pending() == 0 {
+            self.decompress(buf.len())?;
+        }
+
+        let read_bytes = self.pending().min(buf.len());
+        let read_end = self.read_position + read_bytes;
+
+        buf[..read_bytes].copy_from_slice(&self.output[self.read_position..read_end]);
+        self.read_position = read_end;
+
+        return Ok(read_bytes);
+    }
+}
+
+fn read_header<R: BufRead>(bits: &mut BitReader<R>) -> Result<()> {
+    let mut header = [0u8; 10];
+    bits.read_bytes(&mut header)?;
+
+    if header[..2] != GZIP_ID || header[2] != DEFLATE_METHOD {
+        return Err(invalid_data("invalid header"));
+    }
+
+    let flags = header[3];
+
+    if flags & FLAG_EXTRA != 0 {
+        let extra_length = bits.read_u16()?;
+        bits.read_bytes(&mut vec![0u8; extra_length as usize])?;
+    }
+
+    // Zero terminated file name and comment
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            while bits.read_byte()? != 0 {}
+        }
+    }
+
+    if flags & FLAG_HEADER_CRC != 0 {
+        bits.read_u16()?;
+    }
+
+    return Ok(());
+}
+
+fn read_dynamic_tables<R: BufRead>(bits: &mut BitReader<R>) -> Result<BlockState> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+
+    for index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[*index] = bits.bits(3)? as u8;
+    }
+
+    let code_lengths = HuffmanTable::new(&code_length_lengths)?;
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(bits)?;
+
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or_else(|| invalid_data("repeated code length without a previous one"))?;
+
+                (previous, bits.bits(2)? as usize + 3)
+            }
+            17 => (0, bits.bits(3)? as usize + 3),
+            _ => (0, bits.bits(7)? as usize + 11),
+        };
+
+        if lengths.len() + repeat > literal_count + distance_count {
+            return Err(invalid_data("too many code lengths"));
+        }
+
+        lengths.extend(std::iter::repeat_n(length, repeat));
+    }
+
+    if lengths[END_OF_BLOCK] == 0 {
+        return Err(invalid_data("missing end of block code"));
+    }
+
+    return Ok(BlockState::Huffman {
+        literals: HuffmanTable::new(&lengths[..literal_count])?,
+        distances: HuffmanTable::new(&lengths[literal_count..])?,
+    });
+}
+
+// Returns true when the block ended, otherwise stops once the output reached the wanted length
+fn decompress_huffman_block<R: BufRead>(
+    bits: &mut BitReader<R>,
+    literals: &HuffmanTable,
+    distances: &HuffmanTable,
+    output: &mut Vec<u8>,
+    wanted_length: usize,
+) -> Result<bool> {
+    while output.len() < wanted_length {
+        let symbol = literals.decode(bits)?;
+
+        if symbol < END_OF_BLOCK {
+            output.push(symbol as u8);
+            continue;
+        }
+
+        if symbol == END_OF_BLOCK {
+            return Ok(true);
+        }
+
+        let length_index = symbol - END_OF_BLOCK - 1;
+
+        if length_index >= LENGTH_BASE.len() {
+            return Err(invalid_data("invalid length code"));
+        }
+
+        let length = LENGTH_BASE[length_index] as usize + bits.bits(LENGTH_EXTRA_BITS[length_index] as u32)? as usize;
+
+        let distance_index = distances.decode(bits)?;
+
+        if distance_index >= DISTANCE_BASE.len() {
+            return Err(invalid_data("invalid distance code"));
+        }
+
+        let distance = DISTANCE_BASE[distance_index] as usize + bits.bits(DISTANCE_EXTRA_BITS[distance_index] as u32)? as usize;
+
+        if distance > output.len() {
+            return Err(invalid_data("distance is before the start of the output"));
+        }
+
+        let copy_start = output.len() - distance;
+
+        // The copy can overlap the bytes it adds
+        if distance >= length {
+            output.extend_from_within(copy_start..copy_start + length);
+        } else {
+            for index in copy_start..copy_start + length {
+                output.push(output[index]);
+            }
+        }
+    }
+
+    return Ok(false);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn gzip(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::new(level));
+        encoder.write_all(data).expect("compress");
+
+        return encoder.finish().expect("compress");
+    }
+
+    fn decompress(compressed: &[u8], start: SeekPoint, checkpoint_span: usize) -> Result<(Vec<u8>, Vec<SeekPoint>)> {
+        let mut decoder = GzipMemberDecoder::new(Cursor::new(&compressed[start.compressed_offset..]), start, checkpoint_span)?;
+
+        let mut output = vec![];
+        decoder.read_to_end(&mut output)?;
+
+        return Ok((output, decoder.take_checkpoints()));
+    }
+
+    // Lines of words and numbers so the compressed data has both literals and matches
+    fn create_text(length: usize) -> Vec<u8> {
+        let words = ["\x1b[31merror\x1b[0m", "warning", "info", "request", "id=", "12", "345", "\n", "\x1b[1m", " "];
+        let mut seed = 42u64;
+        let mut text = vec![];
+
+        while text.len() < length {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            text.extend_from_slice(words[(seed >> 33) as usize % words.len()].as_bytes());
+            text.extend_from_slice(format!("{:x}", seed >> 48).as_bytes());
+        }
+
+        return text;
+    }
+
+    #[test]
+    fn should_have_checkpoints_inside_a_single_member() {
+        let text = create_text(1024 * 512);
+        let compressed = gzip(&text, 6);
+
+        let (output, checkpoints) = decompress(&compressed, SeekPoint::start(), 1024 * 32).expect("decompress");
+
+        assert_eq!(output, text);
+        assert!(checkpoints.len() > 2, "expected multiple checkpoints, got {}", checkpoints.len());
+
+        for checkpoint in &checkpoints {
+            let window = checkpoint.window.as_ref().expect("checkpoint should have a window");
+            let start = checkpoint.uncompressed_offset;
+
+            assert_eq!(**window, text[start - window.len()..start]);
+        }
+
+        for (index, checkpoint) in checkpoints.iter().enumerate() {
+            let (output, next_checkpoints) = decompress(&compressed, checkpoint.clone(), 1024 * 32).expect("decompress from checkpoint");
+
+            assert_eq!(output, text[checkpoint.uncompressed_offset..]);
+            assert_eq!(next_checkpoints, checkpoints[index + 1..]);
+        }
+    }
+
+    #[test]
+    fn should_fail_when_the_checksum_does_not_match() {
+        let mut compressed = gzip(b"Hello, World!", 6);
+        let crc_offset = compressed.len() - 8;
+        compressed[crc_offset] ^= 0xff;
+
+        let error = decompress(&compressed, SeekPoint::start(), DEFAULT_CHECKPOINT_SPAN).expect_err("should fail");
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn should_fail_when_the_member_is_not_complete() {
+        let compressed = gzip(&create_text(1024 * 64), 6);
+
+        let error = decompress(&compressed[..compressed.len() / 2], SeekPoint::start(), DEFAULT_CHECKPOINT_SPAN).expect_err("should fail");
+
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn should_leave_the_reader_after_the_member() {
+        let compressed = [gzip(b"Hello", 6), b"next".to_vec()].concat();
+        let mut decoder = GzipMemberDecoder::new(Cursor::new(&compressed), SeekPoint::start(), DEFAULT_CHECKPOINT_SPAN).expect("create decoder");
+
+        let mut output = vec![];
+        decoder.read_to_end(&mut output).expect("decompress");
+
+        let mut rest = vec![];
+        decoder.into_inner().read_to_end(&mut rest).expect("read rest");
+
+        assert_eq!(output, b"Hello");
+        assert_eq!(rest, b"next");
+    }
+
+    proptest! {
+        #[test]
+        fn should_decompress_the_same_as_flate2(
+            seed in any::<u64>(),
+            length in 0usize..200000,
+            level in 0u32..10,
+            buffer_size in 1usize..5000,
+        ) {
+            // Mostly a few bytes so there are matches, generated from the seed as big vectors are slow to generate
+            let data = (0..length as u64)
+                .map(|index| {
+                    let random = seed.wrapping_add(index).wrapping_mul(6364136223846793005) >> 56;
+
+                    if random < 200 { b"ab\n"[random as usize % 3] } else { random as u8 }
+                })
+                .collect::<Vec<u8>>();
+            let compressed = gzip(&data, level);
+            let mut decoder = GzipMemberDecoder::new(Cursor::new(&compressed), SeekPoint::start(), DEFAULT_CHECKPOINT_SPAN).expect("create decoder");
+
+            let mut output = vec![];
+            let mut buffer = vec![0u8; buffer_size];
+
+            loop {
+                let read_bytes = decoder.read(&mut buffer).expect("decompress");
+
+                if read_bytes == 0 {
+                    break;
+                }
+
+                output.extend_from_slice(&buffer[..read_bytes]);
+            }
+
+            prop_assert_eq!(output, data);
+        }
+    }
+}
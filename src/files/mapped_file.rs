@@ -0,0 +1,188 @@
+use std::fs::File;
+
+use memmap2::Mmap;
+
+use crate::error::Error;
+use crate::files::compression::{detect_file_compression, Compression};
+use crate::files::file_reader::{FileReader, FileReaderBackend, FileReaderOptions};
+
+// The whole file is mapped so it must fit in the address space
+#[cfg(not(target_pointer_width = "64"))]
+const MMAP_MAX_FILE_SIZE_IN_BYTES: u64 = 1024 * 1024 * 1024; // 1GB
+
+#[cfg(target_pointer_width = "64")]
+fn fits_in_address_space(_file_size: u64) -> bool {
+    return true;
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn fits_in_address_space(file_size: u64) -> bool {
+    return file_size <= MMAP_MAX_FILE_SIZE_IN_BYTES;
+}
+
+// File that is mapped to memory, the bytes are the same as the bytes `FileReader` returns for the same options
+// so they can be passed to `buffer_to_spans`, `buffer_to_lines` or the span refs API without copying
+pub struct MappedFile {
+    // Empty files can't be mapped
+    mmap: Option<Mmap>,
+    from_bytes: usize,
+    to_bytes: usize,
+}
+
+impl MappedFile {
+    pub fn open(options: &FileReaderOptions) -> Result<MappedFile, Error> {
+        let file = File::open(&options.file_path)?;
+
+        return MappedFile::from_file(&file, options);
+    }
+
+    fn from_file(file: &File, options: &FileReaderOptions) -> Result<MappedFile, Error> {
+        let file_size = file.metadata()?.len() as usize;
+
+        let mmap = if file_size == 0 {
+            None
+        } else {
+            // The file must not be truncated or changed by another process while it's mapped,
+            // reading a page that was truncated kills the process (SIGBUS) instead of returning an error like reading in chunks does,
+            // so the file is mapped only when the mmap backend is requested
+            let mmap = unsafe { Mmap::map(file)? };
+
+            // The file is parsed from start to end
+            #[cfg(unix)]
+            mmap.advise(memmap2::Advice::Sequential)?;
+
+            Some(mmap)
+        };
+
+        let to_bytes = options.to_bytes.unwrap_or(file_size).min(file_size);
+        let from_bytes = options.from_bytes.unwrap_or(0).min(to_bytes);
+
+        return Ok(MappedFile {
+            mmap,
+            from_bytes,
+            to_bytes,
+        });
+    }
+
+    // The `from_bytes..to_bytes` range of the file
+    pub fn as_bytes(&self) -> &[u8] {
+        return match &self.mmap {
+            Some(mmap) => &mmap[self.from_bytes..self.to_bytes],
+            None => &[],
+        };
+    }
+}
+
+pub enum FileInput {
+    Chunks(FileReader),
+    Mapped(MappedFile),
+}
+
+// Open the file with the backend from the options, the file is read in chunks when the backend is not set
+pub fn open_file_input(options: FileReaderOptions) -> Result<FileInput, Error> {
+    if options.backend != Some(FileReaderBackend::Mmap) {
+        return Ok(FileInput::Chunks(FileReader::new(options)?));
+    }
+
+    let mut file = File::open(&options.file_path)?;
+
+    // Compressed files must be decompressed so they can't be parsed in place
+    if detect_file_compression(&mut file)? != Compression::None || !fits_in_address_space(file.metadata()?.len()) {
+        return Ok(FileInput::Chunks(FileReader::new(options)?));
+    }
+
+    return Ok(FileInput::Mapped(MappedFile::from_file(&file, &options)?));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn create_tmp_file(input: &[u8]) -> NamedTempFile {
+        let file = NamedTempFile::new().expect("create temp file");
+
+        std::fs::write(file.path(), input).expect("Failed to write to file");
+
+        return file;
+    }
+
+    fn create_options(file: &NamedTempFile, from_bytes: Option<usize>, to_bytes: Option<usize>) -> FileReaderOptions {
+        return FileReaderOptions {
+            file_path: file.path().to_str().expect("convert to string").to_string(),
+            chunk_size_in_bytes: Some(3),
+            from_bytes,
+            to_bytes,
+            seek_point: None,
+            backend: None,
+        };
+    }
+
+    #[test_case(None, None ; "whole file")]
+    #[test_case(Some(7), None ; "from the middle")]
+    #[test_case(None, Some(5) ; "until the middle")]
+    #[test_case(Some(2), Some(9) ; "range")]
+    #[test_case(Some(9), Some(2) ; "to before from")]
+    #[test_case(Some(100), Some(200) ; "after the end")]
+    fn mapped_file_should_have_the_same_bytes_as_file_reader(from_bytes: Option<usize>, to_bytes: Option<usize>) {
+        let file = create_tmp_file(b"Hello, World!");
+
+        let mapped_file = MappedFile::open(&create_options(&file, from_bytes, to_bytes)).expect("map file");
+        let file_reader = FileReader::new(create_options(&file, from_bytes, to_bytes)).expect("create file reader");
+
+        let expected: Vec<u8> = file_reader.flat_map(|chunk| chunk.expect("read chunk")).collect();
+
+        assert_eq!(mapped_file.as_bytes(), expected.as_slice());
+    }
+
+    #[test]
+    fn empty_file_should_be_mapped_to_empty_bytes() {
+        let file = create_tmp_file(b"");
+
+        let mapped_file = MappedFile::open(&create_options(&file, None, None)).expect("map file");
+
+        assert_eq!(mapped_file.as_bytes(), b"");
+    }
+
+    #[test]
+    fn file_should_be_read_in_chunks_when_backend_is_not_set() {
+        let file = create_tmp_file(b"Hello, World!");
+
+        let input = open_file_input(create_options(&file, None, None)).expect("open file");
+
+        assert!(matches!(input, FileInput::Chunks(_)));
+    }
+
+    #[test]
+    fn file_should_be_mapped_when_mmap_backend_is_set() {
+        let file = create_tmp_file(b"Hello, World!");
+
+        let options = FileReaderOptions {
+            backend: Some(FileReaderBackend::Mmap),
+            ..create_options(&file, None, None)
+        };
+
+        let input = open_file_input(options).expect("open file");
+
+        assert!(matches!(input, FileInput::Mapped(_)));
+    }
+
+    #[test]
+    fn compressed_file_should_be_read_in_chunks_even_when_mmap_backend_is_set() {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"Hello, World!").expect("compress");
+        let file = create_tmp_file(&encoder.finish().expect("compress"));
+
+        let options = FileReaderOptions {
+            backend: Some(FileReaderBackend::Mmap),
+            ..create_options(&file, None, None)
+        };
+
+        let input = open_file_input(options).expect("open file");
+
+        assert!(matches!(input, FileInput::Chunks(_)));
+    }
+}
@@ -1,5 +1,6 @@
 // TODO - don't make everything public, make it private and expose only what is needed
 
+pub mod error;
 pub mod files;
 pub mod iterators;
 pub mod mapping_file;
@@ -1,4 +1,13 @@
+mod compact;
 mod constants;
 pub mod create;
+pub mod fingerprint;
+pub mod header;
+pub mod line_index;
+mod links;
+pub mod parallel;
 pub mod read;
+mod seek_table;
+mod sparse;
+pub mod update;
 mod tests;
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::Error;
+use crate::mapping_file::constants::*;
+use crate::mapping_file::create::get_initial_span_for_line;
+use crate::mapping_file::links::{get_link, LinkTable};
+use crate::mapping_file::read::MappingItem;
+use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
+use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_ansi_text::parse_text_matching_single_span::parse_text_matching_single_span;
+use crate::types::Line;
+
+// The compact layout of the mapping file is
+// <header>
+// <style-id-for-line-0><link-id><location-in-input-file>
+// <style-id-for-line-1><link-id><location-in-input-file>
+// ...
+// <style-id-for-line-n><link-id><location-in-input-file>
+// <style-0>
+// ...
+// <style-m>
+// <link-table>
+//
+// The link id and the link table are the same as in the fixed layout (see `links`),
+// The style id is a little-endian u32 index in the style table and the location is a little-endian u64,
+// there is no delimiter between the lines, and each style in the style table ends with the delimiter
+//
+// Most lines start with one of a handful of styles so each line takes COMPACT_LINE_LENGTH bytes instead of FULL_LINE_LENGTH
+
+// The distinct initial styles of the lines in the order they were first seen
+#[derive(Debug, Default)]
+pub(crate) struct StyleTable {
+    ids: HashMap<Vec<u8>, u32>,
+    styles: Vec<Vec<u8>>,
+}
+
+impl StyleTable {
+    pub(crate) fn create_line_map(&mut self, line: Line, link_table: &mut LinkTable) -> Result<Vec<u8>, Error> {
+        let initial_span = get_initial_span_for_line(&line);
+
+        let link_id = link_table.get_link_id(&initial_span.link)?;
+        let style = initial_span.with_link(None).serialize_to_ansi_string();
+
+        let style_id = match self.ids.get(&style) {
+            Some(style_id) => *style_id,
+            None => {
+                let style_id = u32::try_from(self.styles.len())
+                    .map_err(|_| Error::InvalidMappingFile("too many styles for compact layout".to_string()))?;
+
+                self.ids.insert(style.clone(), style_id);
+                self.styles.push(style);
+
+                style_id
+            }
+        };
+
+        return Ok([
+            style_id.to_le_bytes().to_vec(),
+            link_id.to_le_bytes().to_vec(),
+            (line.location_in_file as u64).to_le_bytes().to_vec(),
+        ]
+            .concat());
+    }
+
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        return self
+            .styles
+            .iter()
+            .flat_map(|style| [style.as_slice(), DELIMITER.as_bytes()].concat())
+            .collect();
+    }
+}
+
+// Read the style table that is after the lines
+pub(crate) fn read_style_table(file: &mut File, style_table_offset: usize, style_table_size: usize) -> Result<Vec<Span>, Error> {
+    let mut style_table = vec![0u8; style_table_size];
+
+    file.seek(SeekFrom::Start(style_table_offset as u64))?;
+    file.read_exact(&mut style_table)?;
+
+    let style_table = String::from_utf8(style_table)
+        .map_err(|_| Error::InvalidMappingFile("style table is not valid UTF-8".to_string()))?;
+
+    return Ok(style_table
+        .split_terminator(DELIMITER)
+        .map(|style| parse_text_matching_single_span(style).with_text(vec![]))
+        .collect());
+}
+
+// Same as `get_line_metadata_from_file` for the compact layout
+pub(crate) fn get_line_metadata_from_compact_file(
+    file: &mut File,
+    line_number: usize,
+    content_start_offset: usize,
+    line_count: usize,
+    styles: &[Span],
+    links: &[Hyperlink],
+) -> Result<Option<MappingItem>, Error> {
+    if line_number < 1 {
+        return Err(Error::InvalidLineNumber(line_number));
+    }
+
+    // The style table is after the lines so reaching the end of the file can't be used
+    if line_number > line_count {
+        return Ok(None);
+    }
+
+    let mut line = [0u8; COMPACT_LINE_LENGTH];
+
+    file.seek(SeekFrom::Start((content_start_offset + DELIMITER.len() + (line_number - 1) * COMPACT_LINE_LENGTH) as u64))?;
+    file.read_exact(&mut line)?;
+
+    let (style_id, rest) = line.split_at(STYLE_ID_LENGTH);
+    let (link_id, location_in_original_file) = rest.split_at(LINK_ID_LENGTH);
+
+    let style_id = u32::from_le_bytes(style_id.try_into().expect("style id should have STYLE_ID_LENGTH bytes"));
+    let link_id = u32::from_le_bytes(link_id.try_into().expect("link id should have LINK_ID_LENGTH bytes"));
+    let location_in_original_file = u64::from_le_bytes(
+        location_in_original_file
+            .try_into()
+            .expect("location should have SECOND_PART_LINE_LENGTH bytes"),
+    );
+
+    let initial_span = styles
+        .get(style_id as usize)
+        .ok_or_else(|| Error::InvalidMappingFile(format!("missing style {} in style table", style_id)))?;
+
+    return Ok(Some(MappingItem {
+        initial_span: initial_span.clone().with_link(get_link(links, link_id)?),
+        location_in_original_file: location_in_original_file as usize,
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::parse_ansi_text::ansi::colors::Color;
+
+    use super::*;
+
+    fn create_line(style: Span, location_in_file: usize) -> Line {
+        return Line {
+            spans: vec![style.with_text(b"Hello".to_vec())],
+            location_in_file,
+        };
+    }
+
+    #[test]
+    fn lines_with_the_same_style_should_have_the_same_style_id() {
+        let red = Span::empty().with_color(Color::Red);
+        let mut style_table = StyleTable::default();
+
+        let lines_map = [
+            create_line(red.clone(), 0),
+            create_line(Span::empty(), 6),
+            create_line(red.clone(), 12),
+        ]
+            .map(|line| style_table.create_line_map(line, &mut LinkTable::default()).expect("create line map"));
+
+        assert_eq!(lines_map.map(|line_map| line_map[..STYLE_ID_LENGTH].to_vec()), [
+            0u32.to_le_bytes().to_vec(),
+            1u32.to_le_bytes().to_vec(),
+            0u32.to_le_bytes().to_vec(),
+        ]);
+        assert_eq!(style_table.serialize(), [red.serialize_to_ansi_string(), b"\n".to_vec(), b"\n".to_vec()].concat());
+    }
+}
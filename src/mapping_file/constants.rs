@@ -3,16 +3,37 @@ use crate::parse_ansi_text::ansi::style::*;
 
 pub const DELIMITER: &str = "\n";
 
+// The header starts with the magic and the version so other files and older formats are not misread
+pub const MAGIC: &str = "ANSIMAP";
+pub const FORMAT_VERSION: u32 = 2;
+
 // LINE LENGTH IS THE MAXIMUM LENGTH THAT IS REQUIRED TO HAVE ALL SUPPORTED STYLES
 pub const FIRST_PART_LINE_LENGTH: usize = BOLD_CODE.len()
     + ITALIC_CODE.len()
     + INVERSE_CODE.len()
-    + UNDERLINE_CODE.len()
+    // Only one underline style is serialized, and the curly underline has the longest code
+    + CURLY_UNDERLINE_CODE.len()
     + STRIKETHROUGH_CODE.len()
+    // Only one blink speed is serialized, both codes have the same length
+    + SLOW_BLINK_CODE.len()
+    + HIDDEN_CODE.len()
+    + OVERLINE_CODE.len()
+    // Superscript and subscript can't be combined, both codes have the same length
+    + SUPERSCRIPT_CODE.len()
     + LARGEST_RGB_FOREGROUND_CODE.len()
-    + LARGEST_RGB_BACKGROUND_CODE.len();
+    + LARGEST_RGB_BACKGROUND_CODE.len()
+    + LARGEST_RGB_UNDERLINE_COLOR_CODE.len();
+
+// The location is always little-endian so the mapping file can be read on any architecture
+pub const SECOND_PART_LINE_LENGTH: usize = u64::MAX.to_le_bytes().len();
 
-pub const SECOND_PART_LINE_LENGTH: usize = u64::MAX.to_ne_bytes().len();
+// The id of the hyperlink the line starts in, the hyperlinks are in the link table (see `links`)
+pub const LINK_ID_LENGTH: usize = u32::MAX.to_le_bytes().len();
 
 pub const FULL_LINE_LENGTH: usize =
-    FIRST_PART_LINE_LENGTH + SECOND_PART_LINE_LENGTH + DELIMITER.len();
+    FIRST_PART_LINE_LENGTH + LINK_ID_LENGTH + SECOND_PART_LINE_LENGTH + DELIMITER.len();
+
+// The compact layout has the index of the line style in the style table instead of the style
+pub const STYLE_ID_LENGTH: usize = u32::MAX.to_le_bytes().len();
+
+pub const COMPACT_LINE_LENGTH: usize = STYLE_ID_LENGTH + LINK_ID_LENGTH + SECOND_PART_LINE_LENGTH;
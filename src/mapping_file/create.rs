@@ -1,76 +1,212 @@
-use itertools::Itertools;
+use itertools::{Either, Itertools};
 use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
+use crate::error::Error;
+use crate::files::compression::{detect_file_compression, open_decompressed_file, Compression, SeekPoint, SeekPointCollector};
 use crate::files::file_reader::FileReaderOptions;
+use crate::mapping_file::compact::StyleTable;
 use crate::mapping_file::constants::*;
+use crate::mapping_file::fingerprint::SourceFingerprint;
+use crate::mapping_file::header::{MappingHeader, MappingLayout};
+use crate::mapping_file::links::LinkTable;
+use crate::mapping_file::seek_table::serialize_seek_table;
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::parse_options::ParseOptions;
-use crate::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
+use crate::parse_file::file_to_lines_of_spans::{read_ansi_file_to_lines, read_ansi_to_lines};
 use crate::parse_file::types::ReadAnsiFileOptions;
 use crate::types::Line;
 
 // The format for the mapping is
-// <line-length>
-// <initial-style-for-line-0><padding until reach line-length?>
-// <initial-style-for-line-1><padding until reach line-length?>
+// <header>
+// <initial-style-for-line-0><padding until reach line-length?><link-id><location-in-input-file>
+// <initial-style-for-line-1><padding until reach line-length?><link-id><location-in-input-file>
 // ...
-// <initial-style-for-line-n><padding until reach line-length?>
+// <initial-style-for-line-n><padding until reach line-length?><link-id><location-in-input-file>
+// <link-table>
+// <seek-table>
+//
+// The header is described in `MappingHeader`, the link id and the link table are described in `links`, the location is a little-endian u64,
+// the locations of the lines are in the decompressed data when the input file is compressed,
+// and the seek table (described in `seek_table`) is empty when it is not
+//
+// This is the fixed layout, the compact layout is described in `compact`,
+// and only every Nth line is in the mapping file when it's sparse (see `sparse`)
 
 pub fn create_mapping_file_from_input_path(
     output_mapping_file_path: PathBuf,
     input_file_path: PathBuf,
-) {
-    let mut file = File::create(output_mapping_file_path).expect("create mapping file failed");
+) -> Result<(), Error> {
+    return create_mapping_file_with_layout(output_mapping_file_path, input_file_path, MappingLayout::Fixed);
+}
+
+pub fn create_mapping_file_with_layout(
+    output_mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
+    layout: MappingLayout,
+) -> Result<(), Error> {
+    return create_sparse_mapping_file(output_mapping_file_path, input_file_path, layout, 1);
+}
+
+// Only every `checkpoint_interval` line is in the mapping file, the lines between are found by parsing from the line before them
+pub fn create_sparse_mapping_file(
+    output_mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
+    layout: MappingLayout,
+    checkpoint_interval: usize,
+) -> Result<(), Error> {
+    let header = create_header(&input_file_path, layout, checkpoint_interval)?;
+
+    let (lines, seek_points) = read_input_file_lines(&input_file_path, &header)?;
 
-    let header = FULL_LINE_LENGTH.to_string() + DELIMITER;
+    return write_mapping_file(output_mapping_file_path, header, lines, seek_points);
+}
+
+// The lines of the input file as it was when the header was created,
+// the seek points of a compressed input file are collected while the lines are read so it is decompressed only once
+pub(crate) fn read_input_file_lines(
+    input_file_path: &Path,
+    header: &MappingHeader,
+) -> Result<(impl Iterator<Item=Result<Line, Error>>, SeekPointCollector), Error> {
+    let file_path = match input_file_path.to_str() {
+        Some(file_path) => file_path.to_string(),
+        None => return Err(Error::InvalidPath(input_file_path.to_path_buf())),
+    };
 
-    file.write_all(header.as_bytes())
-        .expect("write header to file failed");
+    if header.compression != Compression::None {
+        let reader = open_decompressed_file(File::open(input_file_path)?, header.compression, SeekPoint::start())?;
+        let seek_points = reader.seek_point_collector();
 
-    let output = read_ansi_file_to_lines(ReadAnsiFileOptions {
+        return Ok((Either::Left(read_ansi_to_lines(reader, get_mapping_parse_options())), seek_points));
+    }
+
+    let lines = read_ansi_file_to_lines(ReadAnsiFileOptions {
         file_options: FileReaderOptions {
-            file_path: input_file_path
-                .to_str()
-                .expect("input file path is not valid")
-                .to_string(),
+            file_path,
             chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
             from_bytes: None,
-            to_bytes: None,
+            // Bytes that are appended while parsing are not part of the fingerprint
+            to_bytes: Some(header.source.size as usize),
+            seek_point: None,
+            backend: None,
         },
-        parse_options: ParseOptions::default(),
-    })
-    .map(create_line_map)
-    .into_iter()
-    .chunks(1024 * 1024 * 10); // 10MB
+        parse_options: get_mapping_parse_options(),
+    })?;
+
+    return Ok((Either::Right(lines), SeekPointCollector::default()));
+}
+
+// Parsing from a line continues with the style the line starts with, so empty lines must keep their style as well
+pub(crate) fn get_mapping_parse_options() -> ParseOptions {
+    return ParseOptions::default().with_empty_line_style(true);
+}
+
+// Write the mapping of the lines of the whole input file the header was created for,
+// the seek points are taken after all the lines were read as they can be collected while reading them
+pub(crate) fn write_mapping_file(
+    output_mapping_file_path: PathBuf,
+    mut header: MappingHeader,
+    lines: impl Iterator<Item=Result<Line, Error>>,
+    seek_points: SeekPointCollector,
+) -> Result<(), Error> {
+    let layout = header.layout;
+    let checkpoint_interval = header.checkpoint_interval;
+
+    let mut file = File::create(output_mapping_file_path)?;
+
+    file.write_all((header.serialize() + DELIMITER).as_bytes())?;
+
+    let mut style_table = StyleTable::default();
+    let mut link_table = LinkTable::default();
+
+    let output = lines
+        .enumerate()
+        // Errors are kept even when they are in lines that are not in the mapping file
+        .filter(|(index, line)| line.is_err() || index % checkpoint_interval == 0)
+        .map(|(_, line)| line)
+        .map(|line| match layout {
+            MappingLayout::Fixed => line.and_then(|line| create_line_map(line, &mut link_table)),
+            MappingLayout::Compact => line.and_then(|line| style_table.create_line_map(line, &mut link_table)),
+        })
+        .chunks(1024 * 1024 * 10); // 10MB
 
     for chunk in &output {
-        let merged = chunk.concat();
-        file.write_all(&merged).expect("write line to file failed");
+        let lines = chunk.collect::<Result<Vec<Vec<u8>>, Error>>()?;
+        header.line_count += lines.len();
+
+        file.write_all(&lines.concat())?;
+    }
+
+    drop(output);
+
+    // The style table is empty for the fixed layout
+    let style_table = style_table.serialize();
+    header.style_table_size = style_table.len();
+
+    file.write_all(&style_table)?;
+
+    let link_table = link_table.serialize();
+    header.link_table_size = link_table.len();
+
+    file.write_all(&link_table)?;
+
+    header.seek_points = seek_points.lock().expect("seek points lock").clone();
+
+    let seek_table = serialize_seek_table(&header.seek_points);
+    header.seek_table_size = seek_table.len();
+
+    file.write_all(&seek_table)?;
+
+    // The line count is known only after parsing, the header length does not change so it can be replaced in place
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(header.serialize().as_bytes())?;
+
+    return Ok(());
+}
+
+pub(crate) fn create_header(
+    input_file_path: &PathBuf,
+    layout: MappingLayout,
+    checkpoint_interval: usize,
+) -> Result<MappingHeader, Error> {
+    let mut input_file = File::open(input_file_path)?;
+
+    let source = SourceFingerprint::from_file(&mut input_file)?;
+    let compression = detect_file_compression(&mut input_file)?;
+
+    // The seek points are found while reading the lines
+    return Ok(MappingHeader::new(layout, checkpoint_interval, source, compression, vec![]));
+}
+
+pub(crate) fn get_initial_span_for_line(line: &Line) -> Span {
+    if line.spans.is_empty() {
+        return Span::empty();
     }
+
+    return line.spans[0].clone().with_text(vec![]);
 }
 
-fn create_line_map(line: Line) -> Vec<u8> {
-    let initial_span_for_line = if line.spans.is_empty() {
-        Span::empty()
-    } else {
-        line.spans[0].clone().with_text(vec![])
-    };
+pub(crate) fn create_line_map(line: Line, link_table: &mut LinkTable) -> Result<Vec<u8>, Error> {
+    let initial_span = get_initial_span_for_line(&line);
+
+    let link_id = link_table.get_link_id(&initial_span.link)?;
 
-    let initial_style_for_line_ansi_string = initial_span_for_line.serialize_to_ansi_string();
+    // Hyperlinks have no length limit so they can't fit in the fixed line length, they are in the link table instead
+    let initial_style_for_line_ansi_string = initial_span.with_link(None).serialize_to_ansi_string();
 
     let ansi_len = initial_style_for_line_ansi_string.len();
 
     let first_part_padding = b" ".repeat(FIRST_PART_LINE_LENGTH - ansi_len).to_vec();
 
-    let location_in_file = line.location_in_file.to_ne_bytes();
+    let location_in_file = line.location_in_file.to_le_bytes();
 
-    return [
+    return Ok([
         initial_style_for_line_ansi_string,
         first_part_padding,
+        link_id.to_le_bytes().to_vec(),
         location_in_file.to_vec(),
         DELIMITER.as_bytes().to_vec(),
     ]
-        .concat();
+        .concat());
 }
@@ -0,0 +1,266 @@
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::time::UNIX_EPOCH;
+
+use crate::error::Error;
+
+// Only the start, the end and samples spread across the middle of the file are hashed,
+// hashing the whole file would be as slow as parsing it
+const HASHED_SAMPLE_SIZE_IN_BYTES: u64 = 64 * 1024; // 64KB
+
+const MIDDLE_SAMPLES: u64 = 64;
+const MIDDLE_SAMPLE_SIZE_IN_BYTES: u64 = 4 * 1024; // 4KB
+
+// FNV-1a, the hash must be the same on every platform and Rust version so the std hasher can't be used
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Identify the input file the mapping file was created for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceFingerprint {
+    pub size: u64,
+
+    // Nanoseconds since the unix epoch, 0 when not available (e.g. parsed from memory)
+    pub modified_at: u64,
+
+    pub hash: u64,
+}
+
+impl SourceFingerprint {
+    pub fn from_file(file: &mut File) -> Result<SourceFingerprint, Error> {
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+
+        return Ok(SourceFingerprint {
+            size,
+            modified_at: get_modified_at(&metadata),
+            hash: hash_file_sample(file, size)?,
+        });
+    }
+
+    // Same as `from_file` for content that is not in a file (e.g. parsed from memory),
+    // a file with the same content has the same fingerprint except for the modified time
+    pub fn from_bytes(bytes: &[u8]) -> SourceFingerprint {
+        let size = bytes.len() as u64;
+
+        let hash = get_hashed_samples(size)
+            .into_iter()
+            .fold(FNV_OFFSET_BASIS, |hash, (start, end)| hash_sample(hash, &bytes[start as usize..end as usize]));
+
+        return SourceFingerprint {
+            size,
+            modified_at: 0,
+            hash,
+        };
+    }
+
+    // Describe why the file does not match, None when it matches
+    pub fn mismatch(&self, file: &mut File) -> Result<Option<String>, Error> {
+        let metadata = file.metadata()?;
+
+        if metadata.len() != self.size {
+            return Ok(Some(format!("input file has {} bytes instead of {}", metadata.len(), self.size)));
+        }
+
+        // Any write changes the modified time, so a change in the middle of the file that is not in the samples is found as well,
+        // copying the file changes it as well (unless it's preserved, e.g. `cp -p`) so a copied file must be mapped again
+        let modified_at = get_modified_at(&metadata);
+
+        if self.modified_at != 0 && modified_at != 0 && modified_at != self.modified_at {
+            return Ok(Some("input file was modified".to_string()));
+        }
+
+        // The modified time has low resolution on some file systems, so the content is compared as well
+        if hash_file_sample(file, self.size)? != self.hash {
+            return Ok(Some("input file content is different".to_string()));
+        }
+
+        return Ok(None);
+    }
+
+    // The file starts with the content the fingerprint was created for (e.g. a log that was only appended to),
+    // the modified time is not compared as appending changes it
+    pub fn is_prefix_of(&self, file: &mut File) -> Result<bool, Error> {
+        if file.metadata()?.len() < self.size {
+            return Ok(false);
+        }
+
+        return Ok(hash_file_sample(file, self.size)? == self.hash);
+    }
+}
+
+fn get_modified_at(metadata: &Metadata) -> u64 {
+    return metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+}
+
+// Hash the samples of the first `size` bytes of the file,
+// the bytes after `size` are ignored so the hash of a file that was only appended to is the same for the old size
+pub fn hash_file_sample(file: &mut File, size: u64) -> Result<u64, Error> {
+    let mut hash = FNV_OFFSET_BASIS;
+
+    for (start, end) in get_hashed_samples(size) {
+        let mut sample = vec![];
+
+        file.seek(SeekFrom::Start(start))?;
+        file.take(end - start).read_to_end(&mut sample)?;
+
+        if sample.len() as u64 != end - start {
+            return Err(Error::StaleMappingFile(format!("input file has less than {} bytes", size)));
+        }
+
+        hash = hash_sample(hash, &sample);
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+
+    return Ok(hash);
+}
+
+// The start and end of each sample in order, the samples don't overlap
+// and the whole file is hashed when it's smaller than all the samples together
+fn get_hashed_samples(size: u64) -> Vec<(u64, u64)> {
+    let head_end = size.min(HASHED_SAMPLE_SIZE_IN_BYTES);
+    let tail_start = size.saturating_sub(HASHED_SAMPLE_SIZE_IN_BYTES).max(head_end);
+    let middle_size = tail_start - head_end;
+
+    if middle_size <= MIDDLE_SAMPLES * MIDDLE_SAMPLE_SIZE_IN_BYTES {
+        return vec![(0, size)];
+    }
+
+    let middle_samples = (0..MIDDLE_SAMPLES).map(|index| {
+        let start = head_end + middle_size * index / MIDDLE_SAMPLES;
+
+        (start, start + MIDDLE_SAMPLE_SIZE_IN_BYTES)
+    });
+
+    return [(0, head_end)].into_iter().chain(middle_samples).chain([(tail_start, size)]).collect();
+}
+
+fn hash_sample(mut hash: u64, sample: &[u8]) -> u64 {
+    for byte in sample {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    return hash;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::tempfile;
+
+    use super::*;
+
+    fn create_tmp_file(input: &[u8]) -> File {
+        let mut file = tempfile().expect("create temp file");
+        file.write_all(input).expect("write to file");
+
+        return file;
+    }
+
+    #[test]
+    fn hash_should_be_stable() {
+        // Known FNV-1a values, the hash is part of the mapping file format
+        assert_eq!(hash_file_sample(&mut create_tmp_file(b""), 0).expect("hash"), 0xcbf29ce484222325);
+        assert_eq!(hash_file_sample(&mut create_tmp_file(b"a"), 1).expect("hash"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn hash_of_appended_file_should_be_the_same_for_the_old_size() {
+        let text = "Hello, world!\n".repeat(10_000);
+        let mut file = create_tmp_file(text.as_bytes());
+        let fingerprint = SourceFingerprint::from_file(&mut file).expect("fingerprint");
+
+        file.seek(SeekFrom::End(0)).expect("seek to end");
+        file.write_all(b"appended").expect("write to file");
+
+        assert_eq!(hash_file_sample(&mut file, fingerprint.size).expect("hash"), fingerprint.hash);
+    }
+
+    #[test]
+    fn fingerprint_should_be_prefix_only_of_appended_file() {
+        let mut file = create_tmp_file(b"Hello, world!");
+        let fingerprint = SourceFingerprint::from_file(&mut file).expect("fingerprint");
+
+        file.seek(SeekFrom::End(0)).expect("seek to end");
+        file.write_all(b"\nappended").expect("write to file");
+
+        assert!(fingerprint.is_prefix_of(&mut file).expect("compare"));
+
+        assert!(!fingerprint.is_prefix_of(&mut create_tmp_file(b"Hello, World!\nappended")).expect("compare"));
+        assert!(!fingerprint.is_prefix_of(&mut create_tmp_file(b"Hello")).expect("compare"));
+    }
+
+    #[test]
+    fn fingerprint_of_bytes_should_be_the_same_as_of_file_with_the_same_content() {
+        for text in ["".to_string(), "Hello, world!".to_string(), "Hello, world!\n".repeat(10_000)] {
+            let file_fingerprint = SourceFingerprint::from_file(&mut create_tmp_file(text.as_bytes())).expect("fingerprint");
+
+            assert_eq!(
+                SourceFingerprint::from_bytes(text.as_bytes()),
+                SourceFingerprint {
+                    modified_at: 0,
+                    ..file_fingerprint
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn changed_file_should_not_match() {
+        let mut file = create_tmp_file(b"Hello, world!");
+        let fingerprint = SourceFingerprint::from_file(&mut file).expect("fingerprint");
+
+        assert_eq!(fingerprint.mismatch(&mut file).expect("compare"), None);
+
+        let modified = SourceFingerprint {
+            modified_at: fingerprint.modified_at + 1,
+            ..fingerprint
+        };
+        assert_eq!(modified.mismatch(&mut file).expect("compare"), Some("input file was modified".to_string()));
+
+        let without_modified_time = SourceFingerprint {
+            modified_at: 0,
+            ..fingerprint
+        };
+        assert_eq!(without_modified_time.mismatch(&mut file).expect("compare"), None);
+
+        let mut other_file = create_tmp_file(b"Hello, World!");
+        assert!(without_modified_time.mismatch(&mut other_file).expect("compare").is_some());
+
+        file.seek(SeekFrom::End(0)).expect("seek to end");
+        file.write_all(b"!").expect("write to file");
+        assert!(fingerprint.mismatch(&mut file).expect("compare").is_some());
+    }
+
+    #[test]
+    fn change_in_the_middle_of_big_file_should_change_the_hash() {
+        let text = "Hello, world!\n".repeat(100_000);
+        let mut changed_text = text.clone().into_bytes();
+        changed_text[text.len() / 2] = b'!';
+
+        assert_ne!(SourceFingerprint::from_bytes(text.as_bytes()).hash, SourceFingerprint::from_bytes(&changed_text).hash);
+    }
+
+    #[test]
+    fn samples_should_cover_the_whole_small_file_and_be_in_order() {
+        assert_eq!(get_hashed_samples(0), vec![(0, 0)]);
+        assert_eq!(get_hashed_samples(1000), vec![(0, 1000)]);
+
+        let size = 1024 * 1024 * 100;
+        let samples = get_hashed_samples(size);
+
+        assert_eq!(samples.len() as u64, MIDDLE_SAMPLES + 2);
+        assert_eq!(samples.first(), Some(&(0, HASHED_SAMPLE_SIZE_IN_BYTES)));
+        assert_eq!(samples.last(), Some(&(size - HASHED_SAMPLE_SIZE_IN_BYTES, size)));
+        assert!(samples.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+    }
+}
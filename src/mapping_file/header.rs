@@ -0,0 +1,288 @@
+use crate::error::Error;
+use crate::files::compression::{Compression, SeekPoint};
+use crate::mapping_file::constants::*;
+use crate::mapping_file::fingerprint::SourceFingerprint;
+
+// The header is the first line of the mapping file:
+// <magic> <version> <layout> <checkpoint-interval> <line-length> <line-count> <style-table-size> <link-table-size> <input-size> <input-modified-at> <input-hash>[ <compression> <seek-table-size>]
+//
+// The numbers are padded to a fixed width so the header can be rewritten in place once the lines are written,
+// the compression and the seek table size are only added when the input file is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingLayout {
+    // Each line has the initial style padded to the same length
+    Fixed,
+
+    // Each line has the id of the initial style in the style table that is after the lines
+    Compact,
+}
+
+impl MappingLayout {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            MappingLayout::Fixed => "fixed",
+            MappingLayout::Compact => "compact",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<MappingLayout> {
+        return match name {
+            "fixed" => Some(MappingLayout::Fixed),
+            "compact" => Some(MappingLayout::Compact),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappingHeader {
+    pub layout: MappingLayout,
+
+    // Only every Nth line of the input file is in the mapping file, 1 when every line is
+    pub checkpoint_interval: usize,
+
+    pub line_length: usize,
+
+    // Number of lines in the mapping file, not in the input file when not every line is in it
+    pub line_count: usize,
+
+    // Always 0 for the fixed layout
+    pub style_table_size: usize,
+
+    // The link table is after the style table in both layouts
+    pub link_table_size: usize,
+
+    pub source: SourceFingerprint,
+    pub compression: Compression,
+
+    // The seek table is after the link table, only when the input file is compressed
+    pub seek_table_size: usize,
+
+    // Not part of the header line, read from the seek table
+    pub seek_points: Vec<SeekPoint>,
+}
+
+impl MappingHeader {
+    // Header of a mapping file without lines yet
+    pub fn new(
+        layout: MappingLayout,
+        checkpoint_interval: usize,
+        source: SourceFingerprint,
+        compression: Compression,
+        seek_points: Vec<SeekPoint>,
+    ) -> MappingHeader {
+        return MappingHeader {
+            layout,
+            checkpoint_interval: checkpoint_interval.max(1),
+            line_length: match layout {
+                MappingLayout::Fixed => FULL_LINE_LENGTH,
+                MappingLayout::Compact => COMPACT_LINE_LENGTH,
+            },
+            line_count: 0,
+            style_table_size: 0,
+            link_table_size: 0,
+            source,
+            compression,
+            seek_table_size: 0,
+            seek_points,
+        };
+    }
+
+    pub fn serialize(&self) -> String {
+        let header = format!(
+            "{} {} {} {} {} {:020} {:020} {:020} {:020} {:020} {:016x}",
+            MAGIC,
+            FORMAT_VERSION,
+            self.layout.name(),
+            self.checkpoint_interval,
+            self.line_length,
+            self.line_count,
+            self.style_table_size,
+            self.link_table_size,
+            self.source.size,
+            self.source.modified_at,
+            self.source.hash,
+        );
+
+        if self.compression == Compression::None {
+            return header;
+        }
+
+        return format!("{} {} {:020}", header, self.compression.name(), self.seek_table_size);
+    }
+
+    pub fn parse(header: &str) -> Result<MappingHeader, Error> {
+        let mut parts = header.split(' ');
+
+        match parts.next() {
+            Some(MAGIC) => {}
+
+            // Version 1 had only the line length in the header
+            Some(first_part) if first_part.parse::<usize>().is_ok() => {
+                return Err(Error::UnsupportedMappingFileVersion(1));
+            }
+            _ => return Err(Error::InvalidMappingFile("missing magic bytes, not a mapping file".to_string())),
+        }
+
+        let version = parse_number(parts.next(), "version")?;
+
+        if version != FORMAT_VERSION as u64 {
+            return Err(Error::UnsupportedMappingFileVersion(version as u32));
+        }
+
+        let layout = parts
+            .next()
+            .and_then(MappingLayout::from_name)
+            .ok_or_else(|| Error::InvalidMappingFile("unknown layout".to_string()))?;
+
+        let checkpoint_interval = parse_number(parts.next(), "checkpoint interval")? as usize;
+
+        if checkpoint_interval == 0 {
+            return Err(Error::InvalidMappingFile("invalid checkpoint interval".to_string()));
+        }
+
+        let line_length = parse_number(parts.next(), "line length")? as usize;
+        let line_count = parse_number(parts.next(), "line count")? as usize;
+        let style_table_size = parse_number(parts.next(), "style table size")? as usize;
+        let link_table_size = parse_number(parts.next(), "link table size")? as usize;
+
+        let source = SourceFingerprint {
+            size: parse_number(parts.next(), "input file size")?,
+            modified_at: parse_number(parts.next(), "input file modified time")?,
+            hash: parts
+                .next()
+                .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                .ok_or_else(|| Error::InvalidMappingFile("invalid input file hash".to_string()))?,
+        };
+
+        let compression = match parts.next() {
+            Some(name) => Compression::from_name(name)
+                .ok_or_else(|| Error::InvalidMappingFile(format!("unknown compression {}", name)))?,
+            None => Compression::None,
+        };
+
+        let seek_table_size = match compression {
+            Compression::None => 0,
+            _ => parse_number(parts.next(), "seek table size")? as usize,
+        };
+
+        // Decompressing always starts from a seek point, the start of the file is the first one
+        if compression != Compression::None && seek_table_size == 0 {
+            return Err(Error::InvalidMappingFile("missing seek points for compressed file".to_string()));
+        }
+
+        return Ok(MappingHeader {
+            layout,
+            checkpoint_interval,
+            line_length,
+            line_count,
+            style_table_size,
+            link_table_size,
+            source,
+            compression,
+            seek_table_size,
+            seek_points: vec![],
+        });
+    }
+}
+
+fn parse_number(part: Option<&str>, name: &str) -> Result<u64, Error> {
+    return part
+        .and_then(|part| part.parse::<u64>().ok())
+        .ok_or_else(|| Error::InvalidMappingFile(format!("invalid {}", name)));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn create_header(line_count: usize, size: u64) -> MappingHeader {
+        return MappingHeader {
+            layout: MappingLayout::Fixed,
+            checkpoint_interval: 1,
+            line_length: FULL_LINE_LENGTH,
+            line_count,
+            style_table_size: 0,
+            link_table_size: 0,
+            source: SourceFingerprint {
+                size,
+                modified_at: 1718000000123456789,
+                hash: 0xaf63dc4c8601ec8c,
+            },
+            compression: Compression::None,
+            seek_table_size: 0,
+            seek_points: vec![],
+        };
+    }
+
+    #[test]
+    fn header_should_be_the_same_after_parsing() {
+        let header = create_header(3, 100);
+
+        assert_eq!(MappingHeader::parse(&header.serialize()).expect("parse header"), header);
+    }
+
+    #[test]
+    fn compressed_file_header_should_be_the_same_after_parsing() {
+        let header = MappingHeader {
+            compression: Compression::Zstd,
+            seek_table_size: 42,
+            ..create_header(3, 100)
+        };
+
+        assert_eq!(MappingHeader::parse(&header.serialize()).expect("parse header"), header);
+    }
+
+    #[test]
+    fn compact_layout_header_should_be_the_same_after_parsing() {
+        let header = MappingHeader {
+            layout: MappingLayout::Compact,
+            line_length: COMPACT_LINE_LENGTH,
+            style_table_size: 42,
+            link_table_size: 27,
+            ..create_header(3, 100)
+        };
+
+        assert_eq!(MappingHeader::parse(&header.serialize()).expect("parse header"), header);
+    }
+
+    #[test]
+    fn sparse_header_should_be_the_same_after_parsing() {
+        let header = MappingHeader {
+            checkpoint_interval: 100,
+            ..create_header(3, 100)
+        };
+
+        assert_eq!(MappingHeader::parse(&header.serialize()).expect("parse header"), header);
+    }
+
+    #[test]
+    fn header_length_should_not_depend_on_the_numbers() {
+        assert_eq!(create_header(0, 0).serialize().len(), create_header(123456, 987654321).serialize().len());
+
+        let compressed_header = |seek_table_size| MappingHeader {
+            compression: Compression::Gzip,
+            seek_table_size,
+            ..create_header(3, 100)
+        };
+
+        assert_eq!(compressed_header(21).serialize().len(), compressed_header(123456789).serialize().len());
+    }
+
+    #[test_case("115", Error::UnsupportedMappingFileVersion(1); "version 1")]
+    #[test_case("ANSIMAP 3 fixed 1 115", Error::UnsupportedMappingFileVersion(3); "newer version")]
+    #[test_case("Hello, world!", Error::InvalidMappingFile("missing magic bytes, not a mapping file".to_string()); "not a mapping file")]
+    #[test_case("ANSIMAP 2 other 1 115", Error::InvalidMappingFile("unknown layout".to_string()); "unknown layout")]
+    #[test_case("ANSIMAP 2 fixed 0 115", Error::InvalidMappingFile("invalid checkpoint interval".to_string()); "no checkpoint interval")]
+    #[test_case("ANSIMAP 2 fixed 1 115 3 0 0", Error::InvalidMappingFile("invalid input file size".to_string()); "missing fingerprint")]
+    #[test_case("ANSIMAP 2 fixed 1 115 3 0 0 100 1 af63dc4c8601ec8c gzip", Error::InvalidMappingFile("invalid seek table size".to_string()); "missing seek table size")]
+    #[test_case("ANSIMAP 2 fixed 1 115 3 0 0 100 1 af63dc4c8601ec8c gzip 0", Error::InvalidMappingFile("missing seek points for compressed file".to_string()); "empty seek table")]
+    fn invalid_header_should_return_error(header: &str, expected: Error) {
+        let error = MappingHeader::parse(header).expect_err("should fail");
+
+        assert_eq!(error.to_string(), expected.to_string());
+    }
+}
@@ -0,0 +1,222 @@
+use std::iter;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use itertools::Either;
+
+use crate::error::Error;
+use crate::files::compression::{find_seek_point, Compression};
+use crate::files::file_reader::FileReaderOptions;
+use crate::mapping_file::create::{
+    create_header, get_initial_span_for_line, get_mapping_parse_options, read_input_file_lines, write_mapping_file,
+};
+use crate::mapping_file::fingerprint::SourceFingerprint;
+use crate::mapping_file::header::{MappingHeader, MappingLayout};
+use crate::mapping_file::read::{MappingFileReader, MappingItem};
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
+use crate::parse_file::text_to_lines_of_spans::buffer_to_lines;
+use crate::parse_file::types::ReadAnsiFileOptions;
+use crate::types::Line;
+
+// Same random access to lines as the mapping file but kept in memory,
+// it can be written as a mapping file and read from one so both can be used interchangeably
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    input: LineIndexInput,
+
+    // The input file fingerprint, compression and seek points for writing the mapping file
+    header: MappingHeader,
+
+    // The item of line N is at index N - 1
+    items: Vec<MappingItem>,
+}
+
+#[derive(Debug, Clone)]
+enum LineIndexInput {
+    File(PathBuf),
+    Buffer(Vec<u8>),
+}
+
+impl LineIndex {
+    pub fn from_file(input_file_path: PathBuf) -> Result<LineIndex, Error> {
+        let mut header = create_header(&input_file_path, MappingLayout::Fixed, 1)?;
+
+        let (lines, seek_points) = read_input_file_lines(&input_file_path, &header)?;
+
+        let items = lines
+            .map(|line| line.map(|line| create_mapping_item(&line)))
+            .collect::<Result<Vec<MappingItem>, Error>>()?;
+
+        header.seek_points = seek_points.lock().expect("seek points lock").clone();
+
+        return Ok(LineIndex {
+            input: LineIndexInput::File(input_file_path),
+            header,
+            items,
+        });
+    }
+
+    // The buffer is kept to parse the lines from it
+    pub fn from_buffer(buffer: Vec<u8>) -> Result<LineIndex, Error> {
+        let items = buffer_to_lines(&buffer, get_mapping_parse_options())
+            .map(|line| line.map(|line| create_mapping_item(&line)))
+            .collect::<Result<Vec<MappingItem>, Error>>()?;
+
+        let header = MappingHeader::new(
+            MappingLayout::Fixed,
+            1,
+            SourceFingerprint::from_bytes(&buffer),
+            Compression::None,
+            vec![],
+        );
+
+        return Ok(LineIndex {
+            input: LineIndexInput::Buffer(buffer),
+            header,
+            items,
+        });
+    }
+
+    // Read the index from a mapping file of any layout after making sure it was created for the input file as it is now
+    pub fn from_mapping_file(mapping_file_path: PathBuf, input_file_path: PathBuf) -> Result<LineIndex, Error> {
+        let mut reader = MappingFileReader::open(mapping_file_path, input_file_path.clone())?;
+
+        // Finding each line of a sparse mapping file parses from its checkpoint, parsing the whole file once is faster
+        if reader.header().checkpoint_interval != 1 {
+            return LineIndex::from_file(input_file_path);
+        }
+
+        let header = reader.header().clone();
+
+        let items = (1..=header.line_count)
+            .map(|line_number| {
+                reader
+                    .get_line_metadata(line_number)?
+                    .ok_or_else(|| Error::InvalidMappingFile("missing line".to_string()))
+            })
+            .collect::<Result<Vec<MappingItem>, Error>>()?;
+
+        return Ok(LineIndex {
+            input: LineIndexInput::File(input_file_path),
+            header,
+            items,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.items.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.items.is_empty();
+    }
+
+    // Same as `MappingFileReader::get_line_metadata`, lines start from 1
+    pub fn get(&self, line_number: usize) -> Result<Option<MappingItem>, Error> {
+        if line_number < 1 {
+            return Err(Error::InvalidLineNumber(line_number));
+        }
+
+        return Ok(self.items.get(line_number - 1).cloned());
+    }
+
+    // Parse the lines in the range of line numbers (the end is excluded), lines after the last line are ignored
+    pub fn lines(&self, line_numbers: Range<usize>) -> Result<impl Iterator<Item=Result<Line, Error>> + '_, Error> {
+        if line_numbers.start < 1 {
+            return Err(Error::InvalidLineNumber(line_numbers.start));
+        }
+
+        let first_line = match self.items.get(line_numbers.start - 1) {
+            Some(first_line) if line_numbers.start < line_numbers.end => first_line,
+            _ => return Ok(Either::Left(iter::empty())),
+        };
+
+        let from_bytes = first_line.location_in_original_file;
+
+        // Until the start of the line after the last line, or the end of the input when it's the last line
+        let to_bytes = self
+            .items
+            .get(line_numbers.end - 1)
+            .map(|next_line| next_line.location_in_original_file);
+
+        let parse_options = ParseOptions::default().with_initial_span(first_line.initial_span.clone());
+
+        let lines = match &self.input {
+            LineIndexInput::File(input_file_path) => {
+                let file_path = match input_file_path.to_str() {
+                    Some(file_path) => file_path.to_string(),
+                    None => return Err(Error::InvalidPath(input_file_path.clone())),
+                };
+
+                Either::Left(read_ansi_file_to_lines(ReadAnsiFileOptions {
+                    file_options: FileReaderOptions {
+                        file_path,
+                        chunk_size_in_bytes: Some(1024 * 1024), // 1MB
+                        from_bytes: Some(from_bytes),
+                        // Bytes that were appended after the index was created are not part of it
+                        to_bytes: match to_bytes {
+                            Some(to_bytes) => Some(to_bytes),
+                            None if self.header.compression == Compression::None => Some(self.header.source.size as usize),
+                            None => None,
+                        },
+                        seek_point: if self.header.seek_points.is_empty() {
+                            None
+                        } else {
+                            Some(find_seek_point(&self.header.seek_points, from_bytes))
+                        },
+                        backend: None,
+                    },
+                    parse_options,
+                })?)
+            }
+            LineIndexInput::Buffer(buffer) => {
+                Either::Right(buffer_to_lines(&buffer[from_bytes..to_bytes.unwrap_or(buffer.len())], parse_options))
+            }
+        };
+
+        let line_count = line_numbers.end.min(self.items.len() + 1) - line_numbers.start;
+
+        return Ok(Either::Right(lines.take(line_count).map(move |line| {
+            line.map(|mut line| {
+                // The locations are from where the parsing started
+                line.location_in_file += from_bytes;
+
+                line
+            })
+        })));
+    }
+
+    // The mapping file is the same as the one created from the input file with `create_mapping_file_with_layout`
+    pub fn write_mapping_file(&self, output_mapping_file_path: PathBuf, layout: MappingLayout) -> Result<(), Error> {
+        let header = MappingHeader::new(
+            layout,
+            1,
+            self.header.source,
+            self.header.compression,
+            vec![],
+        );
+
+        let lines = self.items.iter().map(|item| {
+            Ok(Line {
+                spans: vec![item.initial_span.clone()],
+                location_in_file: item.location_in_original_file,
+            })
+        });
+
+        return write_mapping_file(
+            output_mapping_file_path,
+            header,
+            lines,
+            Arc::new(Mutex::new(self.header.seek_points.clone())),
+        );
+    }
+}
+
+fn create_mapping_item(line: &Line) -> MappingItem {
+    return MappingItem {
+        initial_span: get_initial_span_for_line(line),
+        location_in_original_file: line.location_in_file,
+    };
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::Error;
+use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
+
+// Hyperlinks have no length limit so they can't be part of the line style,
+// each line in either layout has the id of the hyperlink it starts in and the link table is at the end of the mapping file:
+// <params-length-of-link-1><params-of-link-1><uri-length-of-link-1><uri-of-link-1>
+// ...
+// <params-length-of-link-m><params-of-link-m><uri-length-of-link-m><uri-of-link-m>
+//
+// The link id and the lengths are little-endian u32, the link id is 0 when the line does not start in a hyperlink
+// and the position of the hyperlink in the link table (starting from 1) otherwise
+
+const NO_LINK_ID: u32 = 0;
+
+// The distinct hyperlinks the lines start in, in the order they were first seen
+#[derive(Debug, Default)]
+pub(crate) struct LinkTable {
+    ids: HashMap<Hyperlink, u32>,
+    links: Vec<Hyperlink>,
+}
+
+impl LinkTable {
+    // Continue the link table of an existing mapping file so the ids of its lines stay the same
+    pub(crate) fn from_links(links: Vec<Hyperlink>) -> LinkTable {
+        let ids = links
+            .iter()
+            .enumerate()
+            .map(|(index, link)| (link.clone(), index as u32 + 1))
+            .collect();
+
+        return LinkTable { ids, links };
+    }
+
+    pub(crate) fn get_link_id(&mut self, link: &Option<Hyperlink>) -> Result<u32, Error> {
+        let link = match link {
+            Some(link) => link,
+            None => return Ok(NO_LINK_ID),
+        };
+
+        if let Some(link_id) = self.ids.get(link) {
+            return Ok(*link_id);
+        }
+
+        let link_id = u32::try_from(self.links.len() + 1)
+            .map_err(|_| Error::InvalidMappingFile("too many hyperlinks for the link table".to_string()))?;
+
+        self.ids.insert(link.clone(), link_id);
+        self.links.push(link.clone());
+
+        return Ok(link_id);
+    }
+
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        return self
+            .links
+            .iter()
+            .flat_map(|link| [serialize_part(&link.params), serialize_part(&link.uri)].concat())
+            .collect();
+    }
+}
+
+fn serialize_part(part: &[u8]) -> Vec<u8> {
+    return [(part.len() as u32).to_le_bytes().as_slice(), part].concat();
+}
+
+// Read the link table that is at the end of the mapping file
+pub(crate) fn read_link_table(file: &mut File, link_table_offset: usize, link_table_size: usize) -> Result<Vec<Hyperlink>, Error> {
+    let mut link_table = vec![0u8; link_table_size];
+
+    file.seek(SeekFrom::Start(link_table_offset as u64))?;
+    file.read_exact(&mut link_table)?;
+
+    return parse_link_table(&link_table);
+}
+
+pub(crate) fn parse_link_table(mut link_table: &[u8]) -> Result<Vec<Hyperlink>, Error> {
+    let mut links = vec![];
+
+    while !link_table.is_empty() {
+        let params = parse_part(&mut link_table)?;
+        let uri = parse_part(&mut link_table)?;
+
+        links.push(Hyperlink::new(uri.to_vec()).with_params(params.to_vec()));
+    }
+
+    return Ok(links);
+}
+
+fn parse_part<'a>(link_table: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let invalid_link_table = || Error::InvalidMappingFile("invalid link table".to_string());
+
+    let length = link_table
+        .get(..size_of::<u32>())
+        .ok_or_else(invalid_link_table)?;
+    let length = u32::from_le_bytes(length.try_into().expect("length should have 4 bytes")) as usize;
+
+    let part = link_table
+        .get(size_of::<u32>()..size_of::<u32>() + length)
+        .ok_or_else(invalid_link_table)?;
+
+    *link_table = &link_table[size_of::<u32>() + length..];
+
+    return Ok(part);
+}
+
+// The hyperlink of the link id of a line
+pub(crate) fn get_link(links: &[Hyperlink], link_id: u32) -> Result<Option<Hyperlink>, Error> {
+    if link_id == NO_LINK_ID {
+        return Ok(None);
+    }
+
+    return links
+        .get(link_id as usize - 1)
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| Error::InvalidMappingFile(format!("missing hyperlink {} in link table", link_id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn link_table_should_be_the_same_after_parsing() {
+        let first = Hyperlink::new(b"https://example.com".to_vec()).with_params(b"id=1".to_vec());
+        let second = Hyperlink::new(b"file:///tmp/a\nb".to_vec());
+        let mut link_table = LinkTable::default();
+
+        let link_ids = [Some(first.clone()), None, Some(second.clone()), Some(first.clone())]
+            .map(|link| link_table.get_link_id(&link).expect("get link id"));
+
+        let links = parse_link_table(&link_table.serialize()).expect("parse link table");
+
+        assert_eq!(link_ids, [1, 0, 2, 1]);
+        assert_eq!(links, [first.clone(), second.clone()]);
+        assert_eq!(link_ids.map(|link_id| get_link(&links, link_id).expect("get link")), [
+            Some(first.clone()),
+            None,
+            Some(second),
+            Some(first),
+        ]);
+    }
+
+    #[test]
+    fn link_table_from_links_should_keep_the_ids() {
+        let first = Hyperlink::new(b"https://example.com".to_vec());
+        let second = Hyperlink::new(b"https://example.org".to_vec());
+        let mut link_table = LinkTable::from_links(vec![first.clone()]);
+
+        assert_eq!(link_table.get_link_id(&Some(second)).expect("get link id"), 2);
+        assert_eq!(link_table.get_link_id(&Some(first)).expect("get link id"), 1);
+    }
+
+    #[test]
+    fn truncated_link_table_should_return_error() {
+        let mut link_table = LinkTable::default();
+        link_table.get_link_id(&Some(Hyperlink::new(b"https://example.com".to_vec()))).expect("get link id");
+
+        let serialized = link_table.serialize();
+
+        assert!(matches!(parse_link_table(&serialized[..serialized.len() - 1]), Err(Error::InvalidMappingFile(_))));
+        assert!(matches!(get_link(&[], 1), Err(Error::InvalidMappingFile(_))));
+    }
+}
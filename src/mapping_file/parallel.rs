@@ -0,0 +1,535 @@
+use std::any::Any;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use const_typed_builder::Builder;
+use itertools::Itertools;
+use memchr::memchr;
+
+use crate::error::Error;
+use crate::files::compression::{Compression, SeekPointCollector};
+use crate::files::file_reader::FileReaderOptions;
+use crate::files::mapped_file::MappedFile;
+use crate::mapping_file::create::{create_header, get_mapping_parse_options, read_input_file_lines, write_mapping_file};
+use crate::mapping_file::header::MappingLayout;
+use crate::parse_ansi_text::ansi::colors::Color;
+use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
+use crate::parse_ansi_text::ansi::style::{Brightness, TextStyle};
+use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_file::text_to_lines_of_spans::buffer_to_lines;
+use crate::types::Line;
+
+// The mapping file is created in 2 phases:
+// 1. The input file is split to chunks at new lines, and each chunk is parsed in parallel twice,
+//    once from no style and once from a style that has every attribute set (the probe style)
+// 2. The chunks are fixed up in order from the style the previous chunk ended with,
+//    each attribute of the initial style of a line is either set in the chunk (the same in both parses)
+//    or kept from the style at the start of the chunk (the same as the style each parse started with)
+//
+// The chunk ends with a new line so its last line is empty, and empty lines keep the style (see `get_mapping_parse_options`),
+// so the style the chunk ends with is the style of its last line
+//
+// Escape codes can contain new lines (e.g. OSC and DCS), so when a chunk ends in the middle of an escape code
+// the input is parsed from the actual style until a line starts at the start of a later chunk,
+// and the chunks are fixed up again from there, the output is the same as creating the mapping file in a single thread
+
+pub const DEFAULT_PARALLEL_CHUNK_SIZE_IN_BYTES: usize = 1024 * 1024 * 10; // 10MB
+
+#[derive(Debug, Clone, Builder)]
+pub struct ParallelOptions {
+    // Number of threads that parse the chunks, the available parallelism when not set
+    pub threads: Option<usize>,
+
+    // The chunks end at a new line so they can be a bit bigger
+    pub chunk_size_in_bytes: Option<usize>,
+}
+
+// Lines of a chunk with only the initial style of each line, including the empty line at the end of the chunk
+// as it has the style the next chunk starts with
+struct ChunkLines {
+    lines: Vec<Line>,
+
+    // The chunk ends in the middle of an escape code so the next chunk must be parsed with it
+    unterminated: bool,
+}
+
+struct ParsedChunk {
+    from_empty: ChunkLines,
+
+    // Parsed only until a line has the same initial style as in the parse from no style,
+    // the style is the same in both parses from that line so the next lines are the same as well
+    from_probe: Vec<Line>,
+    converged: bool,
+}
+
+// Same as `create_sparse_mapping_file` but the input file is parsed in multiple threads,
+// compressed input files can't be split to chunks so they are parsed in a single thread
+//
+// The chunks are parsed in place so the input file is mapped to memory (see `FileReaderBackend::Mmap`),
+// it must not be truncated while the mapping file is created
+pub fn create_mapping_file_in_parallel(
+    output_mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
+    layout: MappingLayout,
+    checkpoint_interval: usize,
+    options: ParallelOptions,
+) -> Result<(), Error> {
+    let file_path = match input_file_path.to_str() {
+        Some(file_path) => file_path.to_string(),
+        None => return Err(Error::InvalidPath(input_file_path)),
+    };
+
+    let header = create_header(&input_file_path, layout, checkpoint_interval)?;
+
+    let threads = options
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1));
+
+    // Parsing in a single thread is faster than fixing up the chunks
+    if header.compression != Compression::None || threads == 1 {
+        let (lines, seek_points) = read_input_file_lines(&input_file_path, &header)?;
+
+        return write_mapping_file(output_mapping_file_path, header, lines, seek_points);
+    }
+
+    let file_options = FileReaderOptions {
+        file_path,
+        chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
+        from_bytes: None,
+        // Bytes that are appended while parsing are not part of the fingerprint
+        to_bytes: Some(header.source.size as usize),
+        seek_point: None,
+        backend: None,
+    };
+
+    let lines = ParallelLines::new(
+        MappedFile::open(&file_options)?,
+        threads,
+        options.chunk_size_in_bytes.unwrap_or(DEFAULT_PARALLEL_CHUNK_SIZE_IN_BYTES),
+    );
+
+    // The input file is not compressed so it has no seek points
+    return write_mapping_file(output_mapping_file_path, header, lines, SeekPointCollector::default());
+}
+
+// The lines of the whole input, only with the initial style of each line
+struct ParallelLines {
+    mapped_file: MappedFile,
+    threads: usize,
+    chunk_size_in_bytes: usize,
+
+    // Where the next batch of chunks starts and the style it starts with
+    position: usize,
+    current_span: Span,
+
+    lines: std::vec::IntoIter<Line>,
+
+    // Reached the end of the input or failed
+    done: bool,
+}
+
+impl ParallelLines {
+    fn new(mapped_file: MappedFile, threads: usize, chunk_size_in_bytes: usize) -> ParallelLines {
+        return ParallelLines {
+            mapped_file,
+            threads,
+            chunk_size_in_bytes: chunk_size_in_bytes.max(1),
+            position: 0,
+            current_span: Span::empty(),
+            lines: vec![].into_iter(),
+            done: false,
+        };
+    }
+
+    // Parse chunks until the end of the input or until each thread parsed a few chunks, to limit the memory usage
+    fn parse_next_batch(&mut self) -> Result<Vec<Line>, Error> {
+        let input = self.mapped_file.as_bytes();
+
+        let mut chunks = vec![];
+        let mut start = self.position;
+
+        // Empty input has a single empty line, so it is parsed as a single empty chunk
+        while chunks.len() < self.threads * 2 {
+            let end = find_chunk_end(input, start, self.chunk_size_in_bytes);
+            chunks.push((start, end));
+            start = end;
+
+            if end == input.len() {
+                break;
+            }
+        }
+
+        let parsed_chunks = parse_chunks_in_parallel(input, &chunks, self.threads)?;
+
+        let mut lines = vec![];
+        let mut index = 0;
+
+        while index < chunks.len() {
+            let (start, end) = chunks[index];
+
+            let fixed_up_chunk = parsed_chunks[index]
+                .as_ref()
+                .ok()
+                .and_then(|parsed_chunk| fix_up_chunk(parsed_chunk, &self.current_span))
+                .and_then(|lines| split_next_chunk_line(lines, input.len(), end));
+
+            if let Some((chunk_lines, end_span)) = fixed_up_chunk {
+                lines.extend(chunk_lines);
+                self.current_span = end_span;
+                self.position = end;
+                index += 1;
+
+                continue;
+            }
+
+            let (position, span) =
+                parse_until_next_chunk_start(input, start, end, self.current_span.clone(), self.chunk_size_in_bytes, &mut lines)?;
+
+            self.position = position;
+            self.current_span = span;
+
+            // Skip the chunks that were parsed again
+            while index < chunks.len() && chunks[index].0 < self.position {
+                index += 1;
+            }
+        }
+
+        self.done = self.position == input.len();
+
+        return Ok(lines);
+    }
+}
+
+impl Iterator for ParallelLines {
+    type Item = Result<Line, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.lines.next() {
+                return Some(Ok(line));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            match self.parse_next_batch() {
+                Ok(lines) => self.lines = lines.into_iter(),
+                Err(error) => {
+                    // The error stops the parsing
+                    self.done = true;
+
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+// The chunk ends after the first new line after the chunk size
+fn find_chunk_end(input: &[u8], start: usize, chunk_size_in_bytes: usize) -> usize {
+    let position = start + chunk_size_in_bytes;
+
+    if position >= input.len() {
+        return input.len();
+    }
+
+    return match memchr(b'\n', &input[position..]) {
+        Some(new_line_index) => position + new_line_index + 1,
+        None => input.len(),
+    };
+}
+
+// Parse from the actual style until a line starts at the start of a later chunk (so it is not in the middle of an escape code),
+// the input is parsed once so an escape code that spans many chunks is not parsed again for each of them
+//
+// Returns the start of that chunk and the initial style of its first line,
+// parsing continues from it the same as when reading from the middle of the mapping file
+fn parse_until_next_chunk_start(
+    input: &[u8],
+    start: usize,
+    end: usize,
+    initial_span: Span,
+    chunk_size_in_bytes: usize,
+    lines: &mut Vec<Line>,
+) -> Result<(usize, Span), Error> {
+    let mut next_chunk_start = end;
+
+    let (chunk_lines, _) = parse_chunk_lines(input, start, input.len(), initial_span);
+
+    for line in chunk_lines {
+        let line = line?;
+
+        while next_chunk_start < line.location_in_file {
+            next_chunk_start = find_chunk_end(input, next_chunk_start, chunk_size_in_bytes);
+        }
+
+        if line.location_in_file == next_chunk_start && next_chunk_start < input.len() {
+            return Ok((next_chunk_start, line.spans.into_iter().next().unwrap_or_else(Span::empty)));
+        }
+
+        lines.push(line);
+    }
+
+    return Ok((input.len(), Span::empty()));
+}
+
+fn parse_chunks_in_parallel(
+    input: &[u8],
+    chunks: &[(usize, usize)],
+    threads: usize,
+) -> Result<Vec<Result<ParsedChunk, Error>>, Error> {
+    let next_chunk_index = AtomicUsize::new(0);
+
+    let mut parsed_chunks = thread::scope(|scope| {
+        let handles = (0..threads.min(chunks.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parsed_chunks = vec![];
+
+                    loop {
+                        let index = next_chunk_index.fetch_add(1, Ordering::Relaxed);
+
+                        if index >= chunks.len() {
+                            return parsed_chunks;
+                        }
+
+                        let (start, end) = chunks[index];
+
+                        parsed_chunks.push((index, parse_chunk_from_empty_and_probe(input, start, end)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        return handles
+            .into_iter()
+            .map(|handle| handle.join().map_err(|panic| Error::ParsingThreadPanicked(get_panic_message(panic))))
+            .flatten_ok()
+            .collect::<Result<Vec<_>, Error>>();
+    })?;
+
+    parsed_chunks.sort_by_key(|(index, _)| *index);
+
+    return Ok(parsed_chunks.into_iter().map(|(_, parsed_chunk)| parsed_chunk).collect());
+}
+
+fn get_panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+
+    return match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(_) => "unknown panic".to_string(),
+    };
+}
+
+// The second item is set when the chunk ends in the middle of an escape code, after the lines were parsed
+fn parse_chunk_lines(
+    input: &[u8],
+    start: usize,
+    end: usize,
+    initial_span: Span,
+) -> (impl Iterator<Item=Result<Line, Error>> + '_, Arc<AtomicBool>) {
+    let unterminated = Arc::new(AtomicBool::new(false));
+
+    let options = {
+        let unterminated = unterminated.clone();
+
+        get_mapping_parse_options()
+            .with_initial_span(initial_span)
+            .with_diagnostics_sink(move |diagnostic| {
+                if matches!(diagnostic.error, Error::UnterminatedEscape) {
+                    unterminated.store(true, Ordering::Relaxed);
+                }
+            })
+    };
+
+    let lines = buffer_to_lines(&input[start..end], options).map(move |line| {
+        line.map(|line| Line {
+            // Only the initial style is needed for the mapping
+            spans: line.spans.first().map(Span::clone_without_text).into_iter().collect(),
+            location_in_file: start + line.location_in_file,
+        })
+    });
+
+    return (lines, unterminated);
+}
+
+fn parse_chunk_from_empty_and_probe(input: &[u8], start: usize, end: usize) -> Result<ParsedChunk, Error> {
+    let (lines_from_empty, unterminated) = parse_chunk_lines(input, start, end, Span::empty());
+    let (mut lines_from_probe, _) = parse_chunk_lines(input, start, end, get_probe_span());
+
+    let mut from_empty = vec![];
+    let mut from_probe = vec![];
+    let mut converged = false;
+
+    for line_from_empty in lines_from_empty {
+        let line_from_empty = line_from_empty?;
+
+        if !converged {
+            if let Some(line_from_probe) = lines_from_probe.next() {
+                let line_from_probe = line_from_probe?;
+
+                // Lines without text have no style so they can't be compared
+                converged = !line_from_empty.spans.is_empty() && line_from_empty == line_from_probe;
+
+                if !converged {
+                    from_probe.push(line_from_probe);
+                }
+            }
+        }
+
+        from_empty.push(line_from_empty);
+    }
+
+    if !converged {
+        from_probe.extend(lines_from_probe.collect::<Result<Vec<Line>, Error>>()?);
+    }
+
+    return Ok(ParsedChunk {
+        from_empty: ChunkLines {
+            lines: from_empty,
+            unterminated: unterminated.load(Ordering::Relaxed),
+        },
+        from_probe,
+        converged,
+    });
+}
+
+// Split the empty line at the end of the chunk to get the style the next chunk starts with,
+// None when the chunk does not end with that line (e.g. in the middle of escape code)
+fn split_next_chunk_line(mut lines: Vec<Line>, input_length: usize, end: usize) -> Option<(Vec<Line>, Span)> {
+    if end == input_length {
+        return Some((lines, Span::empty()));
+    }
+
+    let next_chunk_line = lines.pop()?;
+
+    if next_chunk_line.location_in_file != end {
+        return None;
+    }
+
+    let end_span = next_chunk_line.spans.into_iter().next()?;
+
+    return Some((lines, end_span));
+}
+
+// Every attribute is different from the empty span
+fn get_probe_span() -> Span {
+    return Span::empty()
+        .with_color(Color::Rgb(1, 2, 3))
+        .with_bg_color(Color::Rgb(1, 2, 3))
+        .with_underline_color(Color::Rgb(1, 2, 3))
+        .with_brightness(Brightness::Bold)
+        .with_text_style(TextStyle::all())
+        .with_link(Some(Hyperlink::new(b"probe".to_vec())));
+}
+
+// None when the chunk can't be fixed up and must be parsed again from the actual style
+fn fix_up_chunk(parsed_chunk: &ParsedChunk, start_span: &Span) -> Option<Vec<Line>> {
+    let from_empty = &parsed_chunk.from_empty.lines;
+    let from_probe = &parsed_chunk.from_probe;
+
+    if parsed_chunk.from_empty.unterminated {
+        return None;
+    }
+
+    // The lines after the probe parse converged are the same from any style
+    if parsed_chunk.converged && from_probe.len() >= from_empty.len()
+        || !parsed_chunk.converged && from_probe.len() != from_empty.len()
+    {
+        return None;
+    }
+
+    let fixed_up_lines = from_empty
+        .iter()
+        .zip(from_probe)
+        .map(|(from_empty, from_probe)| {
+            if from_empty.location_in_file != from_probe.location_in_file {
+                return None;
+            }
+
+            let spans = match (from_empty.spans.first(), from_probe.spans.first()) {
+                (None, None) => vec![],
+                (Some(from_empty), Some(from_probe)) => vec![fix_up_span(from_empty, from_probe, start_span)?],
+                _ => return None,
+            };
+
+            return Some(Line {
+                spans,
+                location_in_file: from_empty.location_in_file,
+            });
+        })
+        .collect::<Option<Vec<Line>>>()?;
+
+    return Some([fixed_up_lines.as_slice(), &from_empty[from_probe.len()..]].concat());
+}
+
+fn fix_up_span(from_empty: &Span, from_probe: &Span, start_span: &Span) -> Option<Span> {
+    let empty = Span::empty();
+    let probe = get_probe_span();
+
+    return Some(
+        Span::empty()
+            .with_color(fix_up_attribute(from_empty.color, from_probe.color, empty.color, probe.color, start_span.color)?)
+            .with_bg_color(fix_up_attribute(
+                from_empty.bg_color,
+                from_probe.bg_color,
+                empty.bg_color,
+                probe.bg_color,
+                start_span.bg_color,
+            )?)
+            .with_underline_color(fix_up_attribute(
+                from_empty.underline_color,
+                from_probe.underline_color,
+                empty.underline_color,
+                probe.underline_color,
+                start_span.underline_color,
+            )?)
+            .with_brightness(fix_up_attribute(
+                from_empty.brightness,
+                from_probe.brightness,
+                empty.brightness,
+                probe.brightness,
+                start_span.brightness,
+            )?)
+            .with_text_style(fix_up_text_style(from_empty.text_style, from_probe.text_style, start_span.text_style)?)
+            .with_link(fix_up_attribute(
+                from_empty.link.clone(),
+                from_probe.link.clone(),
+                empty.link,
+                probe.link,
+                start_span.link.clone(),
+            )?),
+    );
+}
+
+fn fix_up_attribute<T: PartialEq>(from_empty: T, from_probe: T, empty: T, probe: T, start: T) -> Option<T> {
+    // Set in the chunk
+    if from_empty == from_probe {
+        return Some(from_empty);
+    }
+
+    // Kept from the start of the chunk
+    if from_empty == empty && from_probe == probe {
+        return Some(start);
+    }
+
+    return None;
+}
+
+// Each text style is fixed up on its own as the text styles are added and removed separately
+fn fix_up_text_style(from_empty: TextStyle, from_probe: TextStyle, start: TextStyle) -> Option<TextStyle> {
+    let kept = from_empty.symmetric_difference(from_probe);
+
+    // The probe style has all the text styles so the kept text styles can only be added in the parse from the probe
+    if from_empty.intersects(kept) {
+        return None;
+    }
+
+    return Some(from_empty.difference(kept) | start.intersection(kept));
+}
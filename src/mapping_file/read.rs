@@ -1,228 +1,371 @@
 use std::fs::File;
-use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
+use crate::error::Error;
+use crate::files::compression::SeekPoint;
+use crate::mapping_file::compact::{get_line_metadata_from_compact_file, read_style_table};
 use crate::mapping_file::constants::*;
+use crate::mapping_file::header::{MappingHeader, MappingLayout};
+use crate::mapping_file::links::{get_link, parse_link_table, read_link_table};
+use crate::mapping_file::seek_table::read_seek_table;
+use crate::mapping_file::sparse::get_line_metadata_from_checkpoint;
+use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::parse_text_matching_single_span::parse_text_matching_single_span;
 
+// Where each part of the mapping file starts, the sizes come from the header so they are checked for overflow once when it's parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MappingFileOffsets {
+    // The end of the header line, the lines start with the delimiter after it
+    pub content_start: usize,
+
+    // Only the compact layout has a style table
+    pub style_table: usize,
+
+    // The link table is after the style table in both layouts
+    pub link_table: usize,
+
+    // Only when the input file is compressed
+    pub seek_table: usize,
+
+    // The size of a complete mapping file
+    pub end: usize,
+}
+
+impl MappingFileOffsets {
+    fn from_header(content_start: usize, header: &MappingHeader) -> Result<MappingFileOffsets, Error> {
+        let too_large = || Error::InvalidMappingFile("the sizes in the header are too large".to_string());
+
+        let style_table = header
+            .line_count
+            .checked_mul(header.line_length)
+            .and_then(|lines_size| lines_size.checked_add(content_start + DELIMITER.len()))
+            .ok_or_else(too_large)?;
+        let link_table = style_table.checked_add(header.style_table_size).ok_or_else(too_large)?;
+        let seek_table = link_table.checked_add(header.link_table_size).ok_or_else(too_large)?;
+        let end = seek_table.checked_add(header.seek_table_size).ok_or_else(too_large)?;
+
+        return Ok(MappingFileOffsets {
+            content_start,
+            style_table,
+            link_table,
+            seek_table,
+            end,
+        });
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct MappingItem {
     pub initial_span: Span,
     pub location_in_original_file: usize,
 }
 
-pub fn get_initial_style_for_line(mapping_text: String, line_number: usize) -> Option<MappingItem> {
+pub fn get_initial_style_for_line(mapping_text: String, line_number: usize) -> Result<Option<MappingItem>, Error> {
     if line_number < 1 {
-        panic!("Line number must be at least 1");
+        return Err(Error::InvalidLineNumber(line_number));
     }
 
     // TODO - can avoid cloning?
-    let get_mapping_file_metadata_result = get_mapping_metadata(mapping_text.clone());
+    let (offsets, header) = parse_mapping_file_header(mapping_text.clone())?;
 
-    if get_mapping_file_metadata_result.is_none() {
-        println!("Invalid mapping file");
+    ensure_every_line_in_fixed_layout(&header)?;
 
-        // TODO - throw instead of returning None
-        return None;
+    // Line number is missing
+    if line_number > header.line_count {
+        return Ok(None);
     }
 
-    let (content_start_offset, line_length) = get_mapping_file_metadata_result.unwrap();
+    let mapping_bytes = mapping_text.as_bytes();
+    let line_length = header.line_length;
+    let offset_in_text = offsets.content_start + ((line_number - 1) * line_length);
 
-    let offset_in_text = content_start_offset + ((line_number - 1) * line_length);
+    let line = mapping_bytes
+        .get(offset_in_text..offset_in_text + line_length)
+        .ok_or_else(|| Error::InvalidMappingFile("each line is not the same length".to_string()))?;
 
-    if offset_in_text >= mapping_text.len() {
-        println!("Invalid mapping, line number is missing");
+    let links = parse_link_table(
+        mapping_bytes
+            .get(offsets.link_table..offsets.seek_table)
+            .ok_or_else(|| Error::InvalidMappingFile("missing link table".to_string()))?,
+    )?;
 
-        // TODO - throw instead of returning None
-        return None;
-    }
+    return Ok(Some(parse_line(line, &links)?));
+}
+
+// Read lines from a mapping file of any layout
+pub struct MappingFileReader {
+    file: File,
+
+    // Parsed to find the lines between the checkpoints of a sparse mapping file
+    input_file_path: PathBuf,
+
+    offsets: MappingFileOffsets,
+    header: MappingHeader,
+
+    // Empty for the fixed layout
+    styles: Vec<Span>,
 
-    if offset_in_text + line_length > mapping_text.len() {
-        println!("Invalid mapping, each line is not the same length");
+    links: Vec<Hyperlink>,
+}
+
+impl MappingFileReader {
+    // Open the mapping file after making sure it was created for the input file as it is now
+    pub fn open(mapping_file_path: PathBuf, input_file_path: PathBuf) -> Result<MappingFileReader, Error> {
+        let (mut file, offsets, header) = open_mapping_file(mapping_file_path)?;
+
+        if let Some(reason) = header.source.mismatch(&mut File::open(&input_file_path)?)? {
+            return Err(Error::StaleMappingFile(reason));
+        }
 
-        // TODO - throw instead of returning None
-        return None;
+        let styles = match header.layout {
+            MappingLayout::Fixed => vec![],
+            MappingLayout::Compact => read_style_table(&mut file, offsets.style_table, header.style_table_size)?,
+        };
+
+        let links = read_link_table(&mut file, offsets.link_table, header.link_table_size)?;
+
+        return Ok(MappingFileReader {
+            file,
+            input_file_path,
+            offsets,
+            header,
+            styles,
+            links,
+        });
     }
 
-    let line_style = mapping_text
-        [offset_in_text..offset_in_text + line_length - SECOND_PART_LINE_LENGTH]
-        .to_string();
+    pub fn header(&self) -> &MappingHeader {
+        return &self.header;
+    }
 
-    let location_in_original_file = mapping_text
-        [offset_in_text + line_length - SECOND_PART_LINE_LENGTH..offset_in_text + line_length]
-        .as_bytes();
+    // Returns None when the line does not exist in the input file
+    pub fn get_line_metadata(&mut self, line_number: usize) -> Result<Option<MappingItem>, Error> {
+        if line_number < 1 {
+            return Err(Error::InvalidLineNumber(line_number));
+        }
 
-    let reading_of_number = u64::from_ne_bytes(location_in_original_file.try_into().unwrap()); //convert the array to a variable of type usize
+        let checkpoint_interval = self.header.checkpoint_interval;
 
-    // To make sure there is no empty span
-    let span = parse_text_matching_single_span(&line_style);
+        // Line number in the mapping file
+        let checkpoint_line_number = (line_number - 1) / checkpoint_interval + 1;
 
-    return Some(MappingItem {
-        initial_span: span.with_text(vec![]),
-        location_in_original_file: reading_of_number as usize,
-    });
+        let checkpoint = match self.get_mapped_line_metadata(checkpoint_line_number)? {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(None),
+        };
+
+        return get_line_metadata_from_checkpoint(
+            &self.input_file_path,
+            &self.header.seek_points,
+            checkpoint,
+            (line_number - 1) % checkpoint_interval,
+        );
+    }
+
+    // Returns None when the line does not exist in the mapping file
+    fn get_mapped_line_metadata(&mut self, line_number: usize) -> Result<Option<MappingItem>, Error> {
+        return match self.header.layout {
+            MappingLayout::Fixed => get_line_metadata_from_file(
+                &mut self.file,
+                line_number,
+                self.offsets.content_start,
+                &self.header,
+                &self.links,
+            ),
+            MappingLayout::Compact => get_line_metadata_from_compact_file(
+                &mut self.file,
+                line_number,
+                self.offsets.content_start,
+                self.header.line_count,
+                &self.styles,
+                &self.links,
+            ),
+        };
+    }
 }
 
 pub fn get_line_metadata_from_file_path(
-    file_path: PathBuf,
+    mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
     line_number: usize,
-) -> Option<MappingItem> {
+) -> Result<Option<MappingItem>, Error> {
     if line_number < 1 {
-        panic!("Line number must be at least 1");
+        return Err(Error::InvalidLineNumber(line_number));
     }
 
-    get_mapping_file_ready_to_read(file_path).and_then(
-        |(mut file, content_start_offset, line_length)| {
-            return get_line_metadata_from_file(
-                &mut file,
-                line_number,
-                content_start_offset,
-                line_length,
-            );
-        },
-    )
+    return MappingFileReader::open(mapping_file_path, input_file_path)?.get_line_metadata(line_number);
 }
 
 // This is useful when wanting to avoid opening the file multiple times - like reading block of lines
+// Returns None when the line does not exist in the mapping file
+// Only for the fixed layout, use `MappingFileReader` to read any layout
 pub fn get_line_metadata_from_file(
     file: &mut File,
     line_number: usize,
     content_start_offset: usize,
-    line_length: usize,
-) -> Option<MappingItem> {
+    header: &MappingHeader,
+    links: &[Hyperlink],
+) -> Result<Option<MappingItem>, Error> {
     if line_number < 1 {
-        panic!("Line number must be at least 1");
+        return Err(Error::InvalidLineNumber(line_number));
     }
 
-    // Create a buffer to read the line style with the expected length of the line
-    let mut requested_line_initial_style = vec![0u8; line_length - SECOND_PART_LINE_LENGTH];
-    let mut requested_line_original_location = vec![0u8; SECOND_PART_LINE_LENGTH];
+    // The link table is after the lines so reaching the end of the file can't be used
+    if line_number > header.line_count {
+        return Ok(None);
+    }
 
-    let offset_in_text = content_start_offset + ((line_number - 1) * line_length);
+    // Create a buffer to read the line with the expected length of the line
+    let mut line = vec![0u8; header.line_length];
 
-    // Go to the matching line position
-    // TODO - should differentiate between seek problem or index out of bounds
-    let seek_result = file.seek(SeekFrom::Start(offset_in_text as u64));
+    let offset_in_text = content_start_offset + ((line_number - 1) * header.line_length);
 
-    if seek_result.is_err() {
-        let file_size = file.metadata();
+    // Go to the matching line position
+    file.seek(SeekFrom::Start(offset_in_text as u64))?;
 
-        if file_size.is_err() {
-            println!("Seek failed for initial style for line number: {}, tried to seek to {}", line_number, offset_in_text);
-            return None;
+    match file.read_exact(&mut line) {
+        Ok(()) => {}
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+            return Err(Error::InvalidMappingFile("each line is not the same length".to_string()));
         }
-
-        println!("Seek failed for initial style for line number: {}, tried to seek to {} when file size is {}", line_number, offset_in_text, file_size.unwrap().len());
-        return None;
+        Err(error) => return Err(Error::Io(error)),
     }
 
-    let read_exact_result = file.read_exact(&mut requested_line_initial_style);
+    return Ok(Some(parse_line(&line, links)?));
+}
 
-    if read_exact_result.is_err() {
-        let file_size = file.metadata();
+// The line is the initial style (starting with the delimiter of the previous line), the link id and the location
+fn parse_line(line: &[u8], links: &[Hyperlink]) -> Result<MappingItem, Error> {
+    let line_style_length = line
+        .len()
+        .checked_sub(LINK_ID_LENGTH + SECOND_PART_LINE_LENGTH)
+        .ok_or_else(|| Error::InvalidMappingFile("line is too short".to_string()))?;
 
-        if file_size.is_err() {
-            println!("Read requested line for initial style when line number: {}, in {} failed", line_number, offset_in_text);
-            return None;
-        }
+    let (line_style, rest) = line.split_at(line_style_length);
+    let (link_id, location_in_original_file) = rest.split_at(LINK_ID_LENGTH);
 
-        println!("Read requested line for initial style when line number: {}, from bytes {} to bytes {} and file size is {} failed", line_number, offset_in_text, offset_in_text + requested_line_initial_style.len(), file_size.unwrap().len());
-        return None;
-    }
+    let line_style = std::str::from_utf8(line_style)
+        .map_err(|_| Error::InvalidMappingFile("line style is not valid UTF-8".to_string()))?;
 
-    let line_location_in_real_file_seek_position = offset_in_text + line_length - SECOND_PART_LINE_LENGTH;
+    let link_id = u32::from_le_bytes(link_id.try_into().expect("link id should have LINK_ID_LENGTH bytes"));
 
-    // Go to the matching line position
-    let seek_result = file.seek(SeekFrom::Start(line_location_in_real_file_seek_position as u64));
+    // To make sure there is no empty span
+    let initial_span = parse_text_matching_single_span(line_style)
+        .with_text(vec![])
+        .with_link(get_link(links, link_id)?);
 
-    if seek_result.is_err() {
-        let file_size = file.metadata();
+    return Ok(MappingItem {
+        initial_span,
+        location_in_original_file: read_location_in_original_file(location_in_original_file)?,
+    });
+}
 
-        if file_size.is_err() {
-            println!("Seek failed for actual line position when line number: {}, tried to seek to {}", line_number, line_location_in_real_file_seek_position);
-            return None;
-        }
+fn read_location_in_original_file(bytes: &[u8]) -> Result<usize, Error> {
+    let bytes: [u8; SECOND_PART_LINE_LENGTH] = bytes
+        .try_into()
+        .map_err(|_| Error::InvalidMappingFile("invalid line location".to_string()))?;
 
-        println!("Seek failed for actual line position when line number: {}, tried to seek to {} and file size is {}", line_number, line_location_in_real_file_seek_position, file_size.unwrap().len());
-        return None;
-    }
+    return Ok(u64::from_le_bytes(bytes) as usize);
+}
 
-    let read_exact_result = file.read_exact(&mut requested_line_original_location);
+// Open the mapping file after making sure it was created for the input file as it is now,
+// the returned file is read with `get_line_metadata_from_file` so only the fixed layout with every line is supported,
+// the second item is the content_start_offset and the last is the link table
+pub fn get_mapping_file_ready_to_read(
+    mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
+) -> Result<(File, usize, MappingHeader, Vec<Hyperlink>), Error> {
+    let reader = MappingFileReader::open(mapping_file_path, input_file_path)?;
 
-    if read_exact_result.is_err() {
-        let file_size = file.metadata();
+    ensure_every_line_in_fixed_layout(&reader.header)?;
 
-        if file_size.is_err() {
-            println!("Read requested line for line position when line number: {}, in {} failed", line_number, line_location_in_real_file_seek_position);
-            return None;
-        }
+    return Ok((reader.file, reader.offsets.content_start, reader.header, reader.links));
+}
+
+// Open the mapping file without checking it matches the input file,
+// the seek points of the header are read from the seek table
+pub(crate) fn open_mapping_file(mapping_file_path: PathBuf) -> Result<(File, MappingFileOffsets, MappingHeader), Error> {
+    let mut file = File::open(mapping_file_path)?;
 
-        println!("Read requested line for line position when line number: {}, from bytes {} to bytes {} and file size is {} failed", line_number, line_location_in_real_file_seek_position, line_location_in_real_file_seek_position + requested_line_original_location.len(), file_size.unwrap().len());
-        return None;
+    let header_line = read_mapping_file_header(&mut file)?;
+    let (offsets, mut header) = parse_mapping_file_header(header_line)?;
+
+    // Missing lines when creating the mapping file was interrupted
+    if file.metadata()?.len() != offsets.end as u64 {
+        return Err(Error::InvalidMappingFile(format!(
+            "expected {} lines, the mapping file is incomplete",
+            header.line_count
+        )));
     }
 
-    let line_style = String::from_utf8(requested_line_initial_style)
-        .expect("Converting requested line to UTF-8 string failed");
+    header.seek_points = read_seek_table(&mut file, offsets.seek_table, header.seek_table_size)?;
 
-    return Some(MappingItem {
-        initial_span: parse_text_matching_single_span(&line_style)
-            .clone()
-            .with_text(vec![]),
-        location_in_original_file: u64::from_ne_bytes(
-            requested_line_original_location.try_into().unwrap(),
-        ) as usize,
-    });
+    return Ok((file, offsets, header));
 }
 
-pub fn get_mapping_file_ready_to_read(file_path: PathBuf) -> Option<(File, usize, usize)> {
-    // TODO - make sure the file is not closed when the function finish
-    let mut file = File::open(file_path).expect("open mapping file failed");
+fn read_mapping_file_header(f: &mut File) -> Result<String, Error> {
+    let mut header = vec![];
 
-    let get_mapping_file_metadata_result = get_mapping_file_metadata(&mut file);
+    // The position in the file does not matter as lines are read by seeking to them
+    BufReader::new(f).read_until(DELIMITER.as_bytes()[0], &mut header)?;
 
-    if get_mapping_file_metadata_result.is_none() {
-        println!("Invalid mapping file");
-
-        // TODO - throw instead of returning None
-        return None;
+    if header.is_empty() {
+        return Err(Error::InvalidMappingFile("missing header".to_string()));
     }
 
-    let (content_start_offset, line_length) = get_mapping_file_metadata_result.unwrap();
-
-    return Some((file, content_start_offset, line_length));
+    return String::from_utf8(header)
+        .map_err(|_| Error::InvalidMappingFile("header is not valid UTF-8, not a mapping file".to_string()));
 }
 
-// First item in returned tuple is the content_start_offset and the second is the line_length
-fn get_mapping_file_metadata(f: &mut File) -> Option<(usize, usize)> {
-    let mut buf = vec![0u8; 1000];
+// The header of the mapping file without validating it matches the input file, the seek points are not read
+pub fn get_mapping_file_header(file_path: PathBuf) -> Result<MappingHeader, Error> {
+    let mut file = File::open(file_path)?;
 
-    // TODO - make sure that the buffer is read completely and not partially
-    f.read(&mut buf).expect("Try read mapping header failed");
+    let header = read_mapping_file_header(&mut file)?;
 
-    let header = buf
-        .lines()
-        .next()
-        .expect("No lines in mapping file")
-        .expect("No content in mapping file");
+    return Ok(parse_mapping_file_header(header)?.1);
+}
 
-    return get_mapping_metadata(header);
+// Seek points in the input file when it's compressed, empty when the input file is not compressed
+pub fn get_seek_points_from_mapping_file(file_path: PathBuf) -> Result<Vec<SeekPoint>, Error> {
+    return Ok(open_mapping_file(file_path)?.2.seek_points);
 }
 
-// First item in returned tuple is the content_start_offset and the second is the line_length
-fn get_mapping_metadata(header: String) -> Option<(usize, usize)> {
+fn parse_mapping_file_header(header: String) -> Result<(MappingFileOffsets, MappingHeader), Error> {
+    // Only the first line is the header
+    let header = header.lines().next().unwrap_or("");
+
     if header.len() < 1 {
-        println!("Invalid mapping file, should have at least one line");
-        return None;
+        return Err(Error::InvalidMappingFile("should have at least one line".to_string()));
     }
 
-    let line_length_result = header.parse::<usize>();
+    let mapping_header = MappingHeader::parse(header)?;
 
-    if line_length_result.is_err() {
-        panic!("Invalid mapping file, first line should be a number");
+    // Each line must at least contain the delimiter, the link id and the line location
+    if mapping_header.layout == MappingLayout::Fixed
+        && mapping_header.line_length < DELIMITER.len() + LINK_ID_LENGTH + SECOND_PART_LINE_LENGTH
+    {
+        return Err(Error::InvalidMappingFile("line length is too small".to_string()));
     }
 
-    let line_length = line_length_result.unwrap();
+    if mapping_header.layout == MappingLayout::Compact && mapping_header.line_length != COMPACT_LINE_LENGTH {
+        return Err(Error::InvalidMappingFile(format!("compact layout line length must be {}", COMPACT_LINE_LENGTH)));
+    }
+
+    return Ok((MappingFileOffsets::from_header(header.len(), &mapping_header)?, mapping_header));
+}
+
+// The line number is the line in the mapping file only when the mapping file is not sparse
+fn ensure_every_line_in_fixed_layout(header: &MappingHeader) -> Result<(), Error> {
+    if header.layout != MappingLayout::Fixed {
+        return Err(Error::InvalidMappingFile("compact mapping file can only be read with MappingFileReader".to_string()));
+    }
+
+    if header.checkpoint_interval != 1 {
+        return Err(Error::InvalidMappingFile("sparse mapping file can only be read with MappingFileReader".to_string()));
+    }
 
-    return Some((header.len(), line_length));
+    return Ok(());
 }
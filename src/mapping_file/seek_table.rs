@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::files::compression::SeekPoint;
+
+// The seek points of a compressed input file can have the window before them (see `SeekPoint`) so they can't be in the header,
+// the seek table is at the end of the mapping file after the link table:
+// <compressed-offset-1><uncompressed-offset-1><bit-offset-1><window-length-1><window-1>
+// ...
+// <compressed-offset-m><uncompressed-offset-m><bit-offset-m><window-length-m><window-m>
+//
+// The offsets are little-endian u64, the bit offset is a u8 and the window length is a little-endian u32,
+// the window length is 0 when the seek point is the start of a gzip member, zstd frame or xz stream
+
+pub(crate) fn serialize_seek_table(seek_points: &[SeekPoint]) -> Vec<u8> {
+    return seek_points
+        .iter()
+        .flat_map(|seek_point| {
+            let window: &[u8] = seek_point.window.as_deref().unwrap_or(&[]);
+
+            [
+                (seek_point.compressed_offset as u64).to_le_bytes().as_slice(),
+                (seek_point.uncompressed_offset as u64).to_le_bytes().as_slice(),
+                &[seek_point.bit_offset],
+                (window.len() as u32).to_le_bytes().as_slice(),
+                window,
+            ]
+            .concat()
+        })
+        .collect();
+}
+
+// Read the seek table that is at the end of the mapping file
+pub(crate) fn read_seek_table(file: &mut File, seek_table_offset: usize, seek_table_size: usize) -> Result<Vec<SeekPoint>, Error> {
+    let mut seek_table = vec![0u8; seek_table_size];
+
+    file.seek(SeekFrom::Start(seek_table_offset as u64))?;
+    file.read_exact(&mut seek_table)?;
+
+    return parse_seek_table(&seek_table);
+}
+
+pub(crate) fn parse_seek_table(mut seek_table: &[u8]) -> Result<Vec<SeekPoint>, Error> {
+    let mut seek_points = vec![];
+
+    while !seek_table.is_empty() {
+        let compressed_offset = u64::from_le_bytes(take_bytes(&mut seek_table)?) as usize;
+        let uncompressed_offset = u64::from_le_bytes(take_bytes(&mut seek_table)?) as usize;
+        let [bit_offset] = take_bytes(&mut seek_table)?;
+        let window_length = u32::from_le_bytes(take_bytes(&mut seek_table)?) as usize;
+
+        if bit_offset > 7 {
+            return Err(Error::InvalidMappingFile(format!("invalid seek point bit offset {}", bit_offset)));
+        }
+
+        let window = seek_table
+            .get(..window_length)
+            .ok_or_else(|| Error::InvalidMappingFile("invalid seek table".to_string()))?;
+
+        seek_points.push(SeekPoint {
+            compressed_offset,
+            uncompressed_offset,
+            bit_offset,
+            window: if window.is_empty() { None } else { Some(Arc::from(window)) },
+        });
+
+        seek_table = &seek_table[window_length..];
+    }
+
+    return Ok(seek_points);
+}
+
+fn take_bytes<const N: usize>(seek_table: &mut &[u8]) -> Result<[u8; N], Error> {
+    let bytes = seek_table
+        .get(..N)
+        .ok_or_else(|| Error::InvalidMappingFile("invalid seek table".to_string()))?;
+
+    *seek_table = &seek_table[N..];
+
+    return Ok(bytes.try_into().expect("should have N bytes"));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn seek_table_should_be_the_same_after_parsing() {
+        let seek_points = vec![
+            SeekPoint::start(),
+            SeekPoint {
+                compressed_offset: 1234,
+                uncompressed_offset: 98765,
+                bit_offset: 5,
+                window: Some(Arc::from(b"Hello, World!".as_slice())),
+            },
+            SeekPoint::member_start(4321, 123456),
+        ];
+
+        assert_eq!(parse_seek_table(&serialize_seek_table(&seek_points)).expect("parse seek table"), seek_points);
+    }
+
+    #[test]
+    fn truncated_seek_table_should_return_error() {
+        let serialized = serialize_seek_table(&[SeekPoint {
+            compressed_offset: 1234,
+            uncompressed_offset: 98765,
+            bit_offset: 5,
+            window: Some(Arc::from(b"Hello".as_slice())),
+        }]);
+
+        assert!(matches!(parse_seek_table(&serialized[..serialized.len() - 1]), Err(Error::InvalidMappingFile(_))));
+        assert!(matches!(parse_seek_table(&serialized[..3]), Err(Error::InvalidMappingFile(_))));
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use crate::error::Error;
+use crate::files::compression::{find_seek_point, SeekPoint};
+use crate::files::file_reader::FileReaderOptions;
+use crate::mapping_file::create::{get_initial_span_for_line, get_mapping_parse_options};
+use crate::mapping_file::read::MappingItem;
+use crate::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
+use crate::parse_file::types::ReadAnsiFileOptions;
+
+// A sparse mapping file has only every Nth line of the input file (the checkpoints) in either layout,
+// line `(k - 1) * N + 1` of the input file is line `k` in the mapping file
+//
+// The lines between the checkpoints are found by parsing the input file from the checkpoint before them,
+// which is at most N - 1 lines, so the mapping file is N times smaller
+
+// Find the line that is `lines_after_checkpoint` lines after the checkpoint, None when the input file ends before the line
+pub(crate) fn get_line_metadata_from_checkpoint(
+    input_file_path: &Path,
+    seek_points: &[SeekPoint],
+    checkpoint: MappingItem,
+    lines_after_checkpoint: usize,
+) -> Result<Option<MappingItem>, Error> {
+    if lines_after_checkpoint == 0 {
+        return Ok(Some(checkpoint));
+    }
+
+    let file_path = match input_file_path.to_str() {
+        Some(file_path) => file_path.to_string(),
+        None => return Err(Error::InvalidPath(input_file_path.to_path_buf())),
+    };
+
+    let from_bytes = checkpoint.location_in_original_file;
+
+    let mut lines = read_ansi_file_to_lines(ReadAnsiFileOptions {
+        file_options: FileReaderOptions {
+            file_path,
+            chunk_size_in_bytes: Some(1024 * 1024), // 1MB
+            from_bytes: Some(from_bytes),
+            to_bytes: None,
+            seek_point: if seek_points.is_empty() {
+                None
+            } else {
+                Some(find_seek_point(seek_points, from_bytes))
+            },
+            backend: None,
+        },
+        parse_options: get_mapping_parse_options().with_initial_span(checkpoint.initial_span),
+    })?;
+
+    return match lines.nth(lines_after_checkpoint) {
+        Some(line) => {
+            let line = line?;
+
+            Ok(Some(MappingItem {
+                initial_span: get_initial_span_for_line(&line),
+                // The locations are from where the parsing started
+                location_in_original_file: from_bytes + line.location_in_file,
+            }))
+        }
+        None => Ok(None),
+    };
+}
@@ -4,16 +4,28 @@ mod tests {
     use std::path::PathBuf;
 
     use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
     use tempfile::*;
+    use test_case::test_case;
 
+    use crate::error::Error;
+    use crate::files::compression::SeekPoint;
     use crate::mapping_file::constants::*;
     use crate::mapping_file::create::*;
+    use crate::mapping_file::header::{MappingHeader, MappingLayout};
+    use crate::mapping_file::line_index::LineIndex;
+    use crate::mapping_file::parallel::*;
     use crate::mapping_file::read::*;
+    use crate::mapping_file::update::*;
     use crate::parse_ansi_text::ansi::colors::*;
     use crate::parse_ansi_text::ansi::constants::RESET_CODE;
+    use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
     use crate::parse_ansi_text::ansi::style::*;
     use crate::parse_ansi_text::ansi::types::Span;
+    use crate::parse_ansi_text::parse_options::ParseOptions;
     use crate::parse_ansi_text::parse_text_matching_single_span::parse_text_matching_single_span;
+    use crate::parse_file::text_to_lines_of_spans::buffer_to_lines;
+    use crate::types::Line;
 
     fn get_tmp_file_path() -> PathBuf {
         return NamedTempFile::new()
@@ -59,7 +71,8 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mapping_file_content = std::fs::read_to_string(tmp_mapping_file_path.clone()).unwrap();
 
@@ -70,9 +83,9 @@ mod tests {
             )
             .collect::<Vec<&str>>()[0];
 
-        let expected = FULL_LINE_LENGTH.to_string();
+        let expected = format!("{} {} fixed 1 {} ", MAGIC, FORMAT_VERSION, FULL_LINE_LENGTH);
 
-        assert_eq!(first_line, expected);
+        assert!(first_line.starts_with(&expected), "{} should start with {}", first_line, expected);
     }
 
     #[test]
@@ -119,7 +132,8 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mapping_file_content = std::fs::read(tmp_mapping_file_path.clone()).unwrap();
         let number_of_lines_in_mapping = (mapping_file_content.len()
@@ -176,7 +190,8 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mapping_file_content = std::fs::read(tmp_mapping_file_path.clone()).unwrap();
 
@@ -229,13 +244,16 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mapping_file_content = std::fs::read(tmp_mapping_file_path.clone()).unwrap();
 
+        let header = get_mapping_file_header(tmp_mapping_file_path.clone()).expect("read mapping file failed");
+
         assert_eq!(
             mapping_file_content.len(),
-            FIRST_PART_LINE_LENGTH.to_string().len()
+            header.serialize().len()
                 + DELIMITER.len()
                 + input_lines.len() * FULL_LINE_LENGTH
         );
@@ -285,7 +303,8 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mapping_file_content = std::fs::read(tmp_mapping_file_path.clone()).unwrap();
 
@@ -360,9 +379,11 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
-        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), 1);
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), 1)
+                .expect("read mapping file failed");
 
         let expected = Span::empty().with_bg_color(Color::Black);
 
@@ -397,9 +418,11 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
-        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), 2);
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), 2)
+                .expect("read mapping file failed");
 
         let expected = Span::empty()
             .with_bg_color(Color::Cyan)
@@ -458,12 +481,14 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mut all_lines_metadata: Vec<Option<MappingItem>> = vec![];
 
         for i in 0..input_lines.len() {
-            let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), i + 1);
+            let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), i + 1)
+                .expect("read mapping file failed");
 
             all_lines_metadata.push(line_metadata);
         }
@@ -571,12 +596,14 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mut all_lines_metadata: Vec<Option<MappingItem>> = vec![];
 
         for i in (0..input_lines.len()).rev() {
-            let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), i + 1);
+            let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), i + 1)
+                .expect("read mapping file failed");
 
             all_lines_metadata.push(line_metadata.clone());
         }
@@ -691,19 +718,21 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mut initial_style_for_each_line: Vec<Option<MappingItem>> = vec![];
 
-        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_mapping_file_path.clone());
+        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
 
-        assert_eq!(ready_data_for_reading_file.is_none(), false);
+        assert_eq!(ready_data_for_reading_file.is_err(), false);
 
-        let (mut file, content_start_offset, line_length) = ready_data_for_reading_file.unwrap();
+        let (mut file, content_start_offset, header, links) = ready_data_for_reading_file.unwrap();
 
         for i in 0..input_lines.len() {
             let initial_style =
-                get_line_metadata_from_file(&mut file, i + 1, content_start_offset, line_length);
+                get_line_metadata_from_file(&mut file, i + 1, content_start_offset, &header, &links)
+                    .expect("read mapping file failed");
 
             initial_style_for_each_line.push(initial_style);
         }
@@ -811,19 +840,21 @@ mod tests {
         std::fs::write(tmp_input_file_path.clone(), input.to_string())
             .expect("write input file failed");
 
-        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
 
         let mut initial_style_for_each_line: Vec<Option<MappingItem>> = vec![];
 
-        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_mapping_file_path.clone());
+        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_mapping_file_path.clone(), tmp_input_file_path.clone());
 
-        assert_eq!(ready_data_for_reading_file.is_none(), false);
+        assert_eq!(ready_data_for_reading_file.is_err(), false);
 
-        let (mut file, content_start_offset, line_length) = ready_data_for_reading_file.unwrap();
+        let (mut file, content_start_offset, header, links) = ready_data_for_reading_file.unwrap();
 
         for i in (0..input_lines.len()).rev() {
             let initial_style =
-                get_line_metadata_from_file(&mut file, i + 1, content_start_offset, line_length);
+                get_line_metadata_from_file(&mut file, i + 1, content_start_offset, &header, &links)
+                    .expect("read mapping file failed");
 
             initial_style_for_each_line.push(initial_style);
         }
@@ -890,6 +921,950 @@ mod tests {
         assert_eq!(initial_style_for_each_line, expected);
     }
 
+    #[test]
+    fn line_number_zero_should_return_error() {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello")
+            .expect("write input file failed");
+
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), 0);
+
+        assert!(matches!(line_metadata, Err(Error::InvalidLineNumber(0))));
+    }
+
+    #[test]
+    fn missing_line_should_return_none() {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello\nworld")
+            .expect("write input file failed");
+
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), 3)
+            .expect("read mapping file failed");
+
+        assert_eq!(line_metadata, None);
+    }
+
+    #[test]
+    fn invalid_mapping_file_should_return_error() {
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_mapping_file_path.clone(), "not a mapping file\n")
+            .expect("write mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), get_tmp_file_path(), 1);
+
+        assert!(matches!(line_metadata, Err(Error::InvalidMappingFile(_))));
+    }
+
+    fn create_mapping_file(input: &str) -> (PathBuf, PathBuf) {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), input).expect("write input file failed");
+
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        return (tmp_input_file_path, tmp_mapping_file_path);
+    }
+
+    #[test]
+    fn mapping_file_header_should_describe_the_input_file() {
+        let input = "Hello\n\x1b[31mworld\nBye";
+        let (tmp_input_file_path, tmp_mapping_file_path) = create_mapping_file(input);
+
+        let header = get_mapping_file_header(tmp_mapping_file_path.clone()).expect("read mapping file failed");
+
+        assert_eq!(header.line_length, FULL_LINE_LENGTH);
+        assert_eq!(header.line_count, 3);
+        assert_eq!(header.source.size, input.len() as u64);
+        assert_eq!(
+            header.source.mismatch(&mut std::fs::File::open(tmp_input_file_path).expect("open input file")).expect("compare"),
+            None
+        );
+    }
+
+    #[test]
+    fn mapping_file_locations_should_be_little_endian() {
+        let (_, tmp_mapping_file_path) = create_mapping_file("Hello\nworld");
+
+        let mapping_file_content = std::fs::read(tmp_mapping_file_path).expect("read mapping file failed");
+        let second_line_location_end = mapping_file_content.len() - DELIMITER.len();
+
+        assert_eq!(
+            mapping_file_content[second_line_location_end - SECOND_PART_LINE_LENGTH..second_line_location_end],
+            [6, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn mapping_file_of_changed_input_file_should_return_stale_error() {
+        let (tmp_input_file_path, tmp_mapping_file_path) = create_mapping_file("Hello\nworld");
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello\nworld!").expect("write input file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path, tmp_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::StaleMappingFile(_))));
+    }
+
+    #[test]
+    fn mapping_file_of_other_input_file_with_the_same_size_should_return_stale_error() {
+        let (_, tmp_mapping_file_path) = create_mapping_file("Hello\nworld");
+        let (other_input_file_path, _) = create_mapping_file("Hello\nWorld");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path, other_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::StaleMappingFile(_))));
+    }
+
+    #[test]
+    fn copied_input_file_should_match_the_mapping_file_only_when_the_modified_time_is_preserved() {
+        let (tmp_input_file_path, tmp_mapping_file_path) = create_mapping_file("Hello\nworld");
+        let copied_input_file_path = get_tmp_file_path();
+
+        // Copying has a different modified time
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        std::fs::copy(tmp_input_file_path.clone(), copied_input_file_path.clone()).expect("copy input file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), copied_input_file_path.clone(), 2);
+
+        assert!(matches!(line_metadata, Err(Error::StaleMappingFile(_))));
+
+        // Same as `cp -p`
+        let modified = std::fs::metadata(tmp_input_file_path).and_then(|metadata| metadata.modified()).expect("get modified time");
+        std::fs::File::options()
+            .write(true)
+            .open(copied_input_file_path.clone())
+            .and_then(|file| file.set_modified(modified))
+            .expect("set modified time");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path, copied_input_file_path, 2)
+            .expect("read mapping file failed");
+
+        assert_eq!(line_metadata.map(|item| item.location_in_original_file), Some(6));
+    }
+
+    #[test]
+    fn version_1_mapping_file_should_return_unsupported_version_error() {
+        let (tmp_input_file_path, tmp_mapping_file_path) = create_mapping_file("Hello");
+
+        std::fs::write(tmp_mapping_file_path.clone(), FULL_LINE_LENGTH.to_string() + DELIMITER).expect("write mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path, tmp_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::UnsupportedMappingFileVersion(1))));
+    }
+
+    #[test]
+    fn incomplete_mapping_file_should_return_error() {
+        let (tmp_input_file_path, tmp_mapping_file_path) = create_mapping_file("Hello\nworld");
+
+        let mapping_file_content = std::fs::read(tmp_mapping_file_path.clone()).expect("read mapping file failed");
+        std::fs::write(tmp_mapping_file_path.clone(), &mapping_file_content[..mapping_file_content.len() - FULL_LINE_LENGTH])
+            .expect("write mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_mapping_file_path, tmp_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::InvalidMappingFile(_))));
+    }
+
+    #[test]
+    fn missing_mapping_file_should_return_error() {
+        let line_metadata = get_line_metadata_from_file_path(PathBuf::from("/non-existing-mapping-file"), get_tmp_file_path(), 1);
+
+        assert!(matches!(line_metadata, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn compressed_file_mapping_should_have_seek_points_and_same_lines_as_uncompressed_file() {
+        let first_frame_text = "\x1b[31mHello\nworld\n";
+        let second_frame_text = "how are \x1b[1myou\nGreat";
+
+        let first_frame = zstd::encode_all(first_frame_text.as_bytes(), 0).expect("compress");
+        let second_frame = zstd::encode_all(second_frame_text.as_bytes(), 0).expect("compress");
+
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_compressed_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_compressed_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), first_frame_text.to_string() + second_frame_text)
+            .expect("write input file failed");
+        std::fs::write(tmp_compressed_input_file_path.clone(), [first_frame.clone(), second_frame].concat())
+            .expect("write input file failed");
+
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+        create_mapping_file_from_input_path(tmp_compressed_mapping_file_path.clone(), tmp_compressed_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        let seek_points = get_seek_points_from_mapping_file(tmp_compressed_mapping_file_path.clone())
+            .expect("read mapping file failed");
+
+        assert_eq!(
+            seek_points,
+            vec![
+                SeekPoint::start(),
+                SeekPoint::member_start(first_frame.len(), first_frame_text.len()),
+            ]
+        );
+
+        for line_number in 1..=5 {
+            let expected = get_line_metadata_from_file_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), line_number)
+                .expect("read mapping file failed");
+            let actual = get_line_metadata_from_file_path(tmp_compressed_mapping_file_path.clone(), tmp_compressed_input_file_path.clone(), line_number)
+                .expect("read mapping file failed");
+
+            assert_eq!(actual, expected, "line {}", line_number);
+        }
+
+        assert_eq!(
+            get_seek_points_from_mapping_file(tmp_mapping_file_path.clone()).expect("read mapping file failed"),
+            vec![]
+        );
+    }
+
+// ---------------------------------------------
+// Compact layout
+// ---------------------------------------------
+
+    fn create_mapping_files_with_both_layouts(input: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_fixed_mapping_file_path = get_tmp_file_path();
+        let tmp_compact_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), input).expect("write input file failed");
+
+        create_mapping_file_with_layout(tmp_fixed_mapping_file_path.clone(), tmp_input_file_path.clone(), MappingLayout::Fixed)
+            .expect("create mapping file failed");
+        create_mapping_file_with_layout(tmp_compact_mapping_file_path.clone(), tmp_input_file_path.clone(), MappingLayout::Compact)
+            .expect("create mapping file failed");
+
+        return (tmp_input_file_path, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path);
+    }
+
+    #[test_case(""; "empty file")]
+    #[test_case("Hello\nworld"; "no style")]
+    #[test_case("\x1b[31mHello\n\x1b[1mworld\n\x1b[0mhow\n\x1b[31mare\nyou\n"; "repeated styles")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\link\nafter\x1b]8;;\x1b\\\n"; "hyperlink")]
+    #[test_case("\x1b[1;3;4;7;9;38;2;255;255;255;48;2;255;255;255mall styles\nhey"; "all styles")]
+    fn compact_mapping_file_should_have_the_same_lines_as_fixed_mapping_file(input: &str) {
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path) =
+            create_mapping_files_with_both_layouts(input);
+
+        let mut fixed_reader = MappingFileReader::open(tmp_fixed_mapping_file_path, tmp_input_file_path.clone())
+            .expect("open mapping file failed");
+        let mut compact_reader = MappingFileReader::open(tmp_compact_mapping_file_path, tmp_input_file_path)
+            .expect("open mapping file failed");
+
+        assert_eq!(compact_reader.header().layout, MappingLayout::Compact);
+        assert_eq!(compact_reader.header().line_count, fixed_reader.header().line_count);
+
+        // Including the line after the last line
+        for line_number in 1..=fixed_reader.header().line_count + 1 {
+            assert_eq!(
+                compact_reader.get_line_metadata(line_number).expect("read mapping file failed"),
+                fixed_reader.get_line_metadata(line_number).expect("read mapping file failed"),
+                "line {}",
+                line_number
+            );
+        }
+    }
+
+    #[test]
+    fn compact_mapping_file_should_store_each_style_once() {
+        let input = "\x1b[31mHello\n\x1b[1mworld\x1b[0m\n".repeat(1000);
+        let (_, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path) = create_mapping_files_with_both_layouts(&input);
+
+        let header = get_mapping_file_header(tmp_compact_mapping_file_path.clone()).expect("read mapping file failed");
+        let compact_mapping_file_size = std::fs::metadata(tmp_compact_mapping_file_path).expect("read mapping file failed").len() as usize;
+        let fixed_mapping_file_size = std::fs::metadata(tmp_fixed_mapping_file_path).expect("read mapping file failed").len() as usize;
+
+        // Red, red and bold, and no style
+        let expected_style_table = [
+            Span::empty().with_color(Color::Red).serialize_to_ansi_string(),
+            DELIMITER.as_bytes().to_vec(),
+            Span::empty().with_color(Color::Red).with_brightness(Brightness::Bold).serialize_to_ansi_string(),
+            DELIMITER.as_bytes().to_vec(),
+            DELIMITER.as_bytes().to_vec(),
+        ]
+            .concat();
+
+        assert_eq!(header.line_count, 2001);
+        assert_eq!(header.style_table_size, expected_style_table.len());
+        assert_eq!(
+            compact_mapping_file_size,
+            header.serialize().len() + DELIMITER.len() + header.line_count * COMPACT_LINE_LENGTH + expected_style_table.len()
+        );
+        assert!(compact_mapping_file_size * 5 < fixed_mapping_file_size);
+    }
+
+    #[test]
+    fn compact_mapping_file_should_not_be_read_as_fixed_mapping_file() {
+        let (tmp_input_file_path, _, tmp_compact_mapping_file_path) = create_mapping_files_with_both_layouts("Hello\nworld");
+
+        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_compact_mapping_file_path, tmp_input_file_path);
+
+        assert!(matches!(ready_data_for_reading_file, Err(Error::InvalidMappingFile(_))));
+    }
+
+    #[test]
+    fn compact_mapping_file_without_style_table_should_return_error() {
+        let (tmp_input_file_path, _, tmp_compact_mapping_file_path) = create_mapping_files_with_both_layouts("\x1b[31mHello\nworld");
+
+        let mapping_file_content = std::fs::read(tmp_compact_mapping_file_path.clone()).expect("read mapping file failed");
+        let header = get_mapping_file_header(tmp_compact_mapping_file_path.clone()).expect("read mapping file failed");
+        std::fs::write(
+            tmp_compact_mapping_file_path.clone(),
+            &mapping_file_content[..mapping_file_content.len() - header.style_table_size],
+        )
+            .expect("write mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_compact_mapping_file_path, tmp_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::InvalidMappingFile(_))));
+    }
+
+    #[test]
+    fn fixed_mapping_file_with_line_shorter_than_link_id_and_location_should_return_error() {
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, _) = create_mapping_files_with_both_layouts("Hello");
+
+        let header = get_mapping_file_header(tmp_fixed_mapping_file_path.clone()).expect("read mapping file failed");
+        let header = MappingHeader {
+            line_length: 10,
+            line_count: 1,
+            link_table_size: 0,
+            ..header
+        };
+
+        std::fs::write(tmp_fixed_mapping_file_path.clone(), header.serialize() + DELIMITER + &"0".repeat(10))
+            .expect("write mapping file failed");
+
+        let line_metadata = get_line_metadata_from_file_path(tmp_fixed_mapping_file_path, tmp_input_file_path, 1);
+
+        assert!(matches!(line_metadata, Err(Error::InvalidMappingFile(_))));
+    }
+
+    #[test_case(9_000_000_000_000_000_000, 30, 0; "line count times line length")]
+    #[test_case(1, FULL_LINE_LENGTH, usize::MAX; "link table size")]
+    fn mapping_file_with_sizes_that_overflow_should_return_error(line_count: usize, line_length: usize, link_table_size: usize) {
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, _) = create_mapping_files_with_both_layouts("Hello");
+
+        let header = get_mapping_file_header(tmp_fixed_mapping_file_path.clone()).expect("read mapping file failed");
+        let header = MappingHeader {
+            line_length,
+            line_count,
+            link_table_size,
+            ..header
+        };
+
+        std::fs::write(tmp_fixed_mapping_file_path.clone(), header.serialize() + DELIMITER).expect("write mapping file failed");
+
+        assert!(matches!(
+            get_mapping_file_header(tmp_fixed_mapping_file_path.clone()),
+            Err(Error::InvalidMappingFile(_))
+        ));
+        assert!(matches!(
+            get_line_metadata_from_file_path(tmp_fixed_mapping_file_path, tmp_input_file_path, 1),
+            Err(Error::InvalidMappingFile(_))
+        ));
+    }
+
+    #[test]
+    fn lines_starting_in_hyperlink_should_have_the_hyperlink_in_any_layout() {
+        let input = "\x1b]8;id=1;https://example.com\x1b\\Hello\nworld\x1b]8;;\x1b\\\n\x1b[31mBye \x1b]8;;file:///tmp\x1b\\file\n";
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path) =
+            create_mapping_files_with_both_layouts(input);
+
+        let example_link = Some(Hyperlink::new(b"https://example.com".to_vec()).with_params(b"id=1".to_vec()));
+        let file_link = Some(Hyperlink::new(b"file:///tmp".to_vec()));
+        let expected = [
+            Some(Span::empty().with_link(example_link.clone())),
+            Some(Span::empty().with_link(example_link)),
+            Some(Span::empty().with_color(Color::Red)),
+            // The empty line after the last new line
+            Some(Span::empty().with_color(Color::Red).with_link(file_link)),
+            None,
+        ];
+
+        for mapping_file_path in [tmp_fixed_mapping_file_path.clone(), tmp_compact_mapping_file_path] {
+            let mut reader = MappingFileReader::open(mapping_file_path, tmp_input_file_path.clone()).expect("open mapping file failed");
+
+            let initial_spans = (1..=expected.len())
+                .map(|line_number| reader.get_line_metadata(line_number).expect("read mapping file failed").map(|item| item.initial_span))
+                .collect::<Vec<Option<Span>>>();
+
+            assert_eq!(initial_spans, expected);
+        }
+
+        let mapping_text = String::from_utf8(std::fs::read(tmp_fixed_mapping_file_path).expect("read mapping file failed"))
+            .expect("mapping file is valid UTF-8");
+
+        for (index, expected_span) in expected.iter().enumerate() {
+            assert_eq!(
+                get_initial_style_for_line(mapping_text.clone(), index + 1)
+                    .expect("read mapping file failed")
+                    .map(|item| item.initial_span),
+                *expected_span,
+                "line {}",
+                index + 1
+            );
+        }
+    }
+
+// ---------------------------------------------
+// Sparse mapping file
+// ---------------------------------------------
+
+    // Returns the input file, the mapping file with every line and the sparse mapping file
+    fn create_sparse_and_full_mapping_files(
+        input: &[u8],
+        layout: MappingLayout,
+        checkpoint_interval: usize,
+    ) -> (PathBuf, PathBuf, PathBuf) {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_sparse_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), input).expect("write input file failed");
+
+        create_mapping_file_with_layout(tmp_mapping_file_path.clone(), tmp_input_file_path.clone(), layout)
+            .expect("create mapping file failed");
+        create_sparse_mapping_file(tmp_sparse_mapping_file_path.clone(), tmp_input_file_path.clone(), layout, checkpoint_interval)
+            .expect("create mapping file failed");
+
+        return (tmp_input_file_path, tmp_mapping_file_path, tmp_sparse_mapping_file_path);
+    }
+
+    fn assert_same_lines_in_sparse_mapping_file(
+        tmp_input_file_path: PathBuf,
+        tmp_mapping_file_path: PathBuf,
+        tmp_sparse_mapping_file_path: PathBuf,
+    ) {
+        let mut reader = MappingFileReader::open(tmp_mapping_file_path, tmp_input_file_path.clone())
+            .expect("open mapping file failed");
+        let mut sparse_reader = MappingFileReader::open(tmp_sparse_mapping_file_path, tmp_input_file_path)
+            .expect("open mapping file failed");
+
+        // Including the lines after the last line
+        for line_number in 1..=reader.header().line_count + 3 {
+            assert_eq!(
+                sparse_reader.get_line_metadata(line_number).expect("read mapping file failed"),
+                reader.get_line_metadata(line_number).expect("read mapping file failed"),
+                "line {}",
+                line_number
+            );
+        }
+    }
+
+    #[test_case(""; "empty file")]
+    #[test_case("Hello\nworld\nhow\nare\nyou\n"; "no style")]
+    #[test_case("\x1b[31mHello\n\x1b[1mworld\n\x1b[0mhow\n\x1b[31mare\nyou\n\x1b[4mI'm\nfine"; "style between checkpoints")]
+    #[test_case("Hello\x1b]0;window\ntitle\x07\nworld\n\x1b[1mhow\nare\x1b[22m\nyou"; "escape code with new line")]
+    #[test_case("Hello\x1b[3m\n\n\x1b[1m\n\nhow\n\nare\x1b[23m\n\nyou"; "checkpoints without text")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\link\nafter\nend\x1b]8;;\x1b\\\n"; "hyperlink")]
+    fn sparse_mapping_file_should_have_the_same_lines_as_mapping_file_with_every_line(input: &str) {
+        for layout in [MappingLayout::Fixed, MappingLayout::Compact] {
+            for checkpoint_interval in [1, 2, 3, 100] {
+                let (tmp_input_file_path, tmp_mapping_file_path, tmp_sparse_mapping_file_path) =
+                    create_sparse_and_full_mapping_files(input.as_bytes(), layout, checkpoint_interval);
+
+                assert_same_lines_in_sparse_mapping_file(tmp_input_file_path, tmp_mapping_file_path, tmp_sparse_mapping_file_path);
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_sparse_mapping_file_should_have_the_same_lines_as_mapping_file_with_every_line() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, "\x1b[31mHello\nworld\n\x1b[0mhow\nare you\n".repeat(100).as_bytes())
+            .expect("compress input");
+
+        let (tmp_input_file_path, tmp_mapping_file_path, tmp_sparse_mapping_file_path) =
+            create_sparse_and_full_mapping_files(&encoder.finish().expect("compress input"), MappingLayout::Fixed, 7);
+
+        assert_same_lines_in_sparse_mapping_file(tmp_input_file_path, tmp_mapping_file_path, tmp_sparse_mapping_file_path);
+    }
+
+    #[test]
+    fn sparse_mapping_file_should_have_only_every_nth_line() {
+        let input = "\x1b[31mHello\n\x1b[1mworld\x1b[0m\n".repeat(1000);
+        let (_, tmp_mapping_file_path, tmp_sparse_mapping_file_path) =
+            create_sparse_and_full_mapping_files(input.as_bytes(), MappingLayout::Fixed, 100);
+
+        let header = get_mapping_file_header(tmp_sparse_mapping_file_path.clone()).expect("read mapping file failed");
+        let sparse_mapping_file_size = std::fs::metadata(tmp_sparse_mapping_file_path).expect("read mapping file failed").len() as usize;
+        let mapping_file_size = std::fs::metadata(tmp_mapping_file_path).expect("read mapping file failed").len() as usize;
+
+        // Lines 1, 101, ..., 2001 of the 2001 lines
+        assert_eq!(header.checkpoint_interval, 100);
+        assert_eq!(header.line_count, 21);
+        assert_eq!(sparse_mapping_file_size, header.serialize().len() + DELIMITER.len() + 21 * FULL_LINE_LENGTH);
+        assert!(sparse_mapping_file_size * 50 < mapping_file_size);
+    }
+
+    #[test]
+    fn sparse_mapping_file_should_not_be_read_as_mapping_file_with_every_line() {
+        let (tmp_input_file_path, _, tmp_sparse_mapping_file_path) =
+            create_sparse_and_full_mapping_files(b"Hello\nworld\nhow", MappingLayout::Fixed, 2);
+
+        let ready_data_for_reading_file = get_mapping_file_ready_to_read(tmp_sparse_mapping_file_path, tmp_input_file_path);
+
+        assert!(matches!(ready_data_for_reading_file, Err(Error::InvalidMappingFile(_))));
+    }
+
+// ---------------------------------------------
+// Line index
+// ---------------------------------------------
+
+    const LINE_INDEX_INPUT: &str = "\x1b[31mHello\nworld\x1b[1m\nhow \x1b[0mare\nyou\n\x1b[4mI'm \x1b[3mfine\nBye";
+
+    fn assert_same_lines_in_line_index(line_index: &LineIndex, reader: &mut MappingFileReader) {
+        assert_eq!(line_index.len(), reader.header().line_count);
+
+        // Including the line after the last line
+        for line_number in 1..=line_index.len() + 1 {
+            assert_eq!(
+                line_index.get(line_number).expect("get line"),
+                reader.get_line_metadata(line_number).expect("read mapping file failed"),
+                "line {}",
+                line_number
+            );
+        }
+    }
+
+    #[test_case(""; "empty file")]
+    #[test_case(LINE_INDEX_INPUT; "styles")]
+    #[test_case("Hello\x1b]0;window\ntitle\x07\n\x1b[1m\n\nworld\n"; "lines without text")]
+    fn line_index_should_have_the_same_lines_as_mapping_file(input: &str) {
+        let (tmp_input_file_path, tmp_mapping_file_path, _) = create_mapping_files_with_both_layouts(input);
+
+        let mut reader = MappingFileReader::open(tmp_mapping_file_path, tmp_input_file_path.clone()).expect("open mapping file failed");
+
+        assert_same_lines_in_line_index(&LineIndex::from_file(tmp_input_file_path).expect("create line index"), &mut reader);
+        assert_same_lines_in_line_index(&LineIndex::from_buffer(input.as_bytes().to_vec()).expect("create line index"), &mut reader);
+    }
+
+    #[test]
+    fn line_index_of_compressed_file_should_have_the_same_lines_as_mapping_file() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, LINE_INDEX_INPUT.as_bytes()).expect("compress input");
+
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), encoder.finish().expect("compress input")).expect("write input file failed");
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone()).expect("create mapping file failed");
+
+        let line_index = LineIndex::from_file(tmp_input_file_path.clone()).expect("create line index");
+        let mut reader = MappingFileReader::open(tmp_mapping_file_path, tmp_input_file_path).expect("open mapping file failed");
+
+        assert_same_lines_in_line_index(&line_index, &mut reader);
+        assert_eq!(
+            line_index.lines(3..5).expect("read lines").collect::<Result<Vec<Line>, Error>>().expect("parse lines"),
+            buffer_to_lines(LINE_INDEX_INPUT.as_bytes(), ParseOptions::default())
+                .collect::<Result<Vec<Line>, Error>>()
+                .expect("parse lines")[2..4]
+        );
+    }
+
+    #[test_case(1..2; "first line")]
+    #[test_case(2..4; "middle lines")]
+    #[test_case(3..7; "until the last line")]
+    #[test_case(5..100; "after the last line")]
+    #[test_case(7..9; "only after the last line")]
+    #[test_case(3..3; "empty range")]
+    fn line_index_lines_should_be_the_same_as_parsing_the_whole_file(line_numbers: std::ops::Range<usize>) {
+        let tmp_input_file_path = get_tmp_file_path();
+        std::fs::write(tmp_input_file_path.clone(), LINE_INDEX_INPUT).expect("write input file failed");
+
+        let all_lines = buffer_to_lines(LINE_INDEX_INPUT.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<Line>, Error>>()
+            .expect("parse lines");
+        let expected = all_lines
+            .iter()
+            .skip(line_numbers.start - 1)
+            .take(line_numbers.len())
+            .cloned()
+            .collect::<Vec<Line>>();
+
+        for line_index in [
+            LineIndex::from_file(tmp_input_file_path.clone()).expect("create line index"),
+            LineIndex::from_buffer(LINE_INDEX_INPUT.as_bytes().to_vec()).expect("create line index"),
+        ] {
+            let lines = line_index
+                .lines(line_numbers.clone())
+                .expect("read lines")
+                .collect::<Result<Vec<Line>, Error>>()
+                .expect("parse lines");
+
+            assert_eq!(lines, expected);
+        }
+    }
+
+    #[test]
+    fn line_index_lines_starting_at_empty_line_should_keep_the_style() {
+        let input = "\x1b[31mA\n\nB\nC";
+        let tmp_input_file_path = get_tmp_file_path();
+        std::fs::write(tmp_input_file_path.clone(), input).expect("write input file failed");
+
+        let all_lines = buffer_to_lines(input.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<Line>, Error>>()
+            .expect("parse lines");
+
+        for line_index in [
+            LineIndex::from_file(tmp_input_file_path.clone()).expect("create line index"),
+            LineIndex::from_buffer(input.as_bytes().to_vec()).expect("create line index"),
+        ] {
+            let lines = line_index
+                .lines(2..4)
+                .expect("read lines")
+                .collect::<Result<Vec<Line>, Error>>()
+                .expect("parse lines");
+
+            assert_eq!(lines, all_lines[1..3]);
+            assert_eq!(lines[1].spans[0].color, Color::Red);
+        }
+    }
+
+    #[test]
+    fn line_index_line_number_should_start_from_1() {
+        let line_index = LineIndex::from_buffer(b"Hello".to_vec()).expect("create line index");
+
+        assert!(matches!(line_index.get(0), Err(Error::InvalidLineNumber(0))));
+        assert!(matches!(line_index.lines(0..1), Err(Error::InvalidLineNumber(0))));
+    }
+
+    #[test]
+    fn line_index_should_be_written_as_the_same_mapping_file() {
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path) =
+            create_mapping_files_with_both_layouts(LINE_INDEX_INPUT);
+
+        let line_index = LineIndex::from_file(tmp_input_file_path).expect("create line index");
+
+        for (layout, expected_mapping_file_path) in [
+            (MappingLayout::Fixed, tmp_fixed_mapping_file_path),
+            (MappingLayout::Compact, tmp_compact_mapping_file_path),
+        ] {
+            let tmp_mapping_file_path = get_tmp_file_path();
+            line_index.write_mapping_file(tmp_mapping_file_path.clone(), layout).expect("write mapping file failed");
+
+            assert_eq!(
+                String::from_utf8_lossy(&std::fs::read(tmp_mapping_file_path).expect("read mapping file failed")),
+                String::from_utf8_lossy(&std::fs::read(expected_mapping_file_path).expect("read mapping file failed")),
+                "{:?} layout",
+                layout
+            );
+        }
+    }
+
+    #[test]
+    fn line_index_of_buffer_should_be_written_as_mapping_file_of_file_with_the_same_content() {
+        let (tmp_input_file_path, tmp_mapping_file_path, _) = create_mapping_files_with_both_layouts(LINE_INDEX_INPUT);
+        let tmp_buffer_mapping_file_path = get_tmp_file_path();
+
+        LineIndex::from_buffer(LINE_INDEX_INPUT.as_bytes().to_vec())
+            .expect("create line index")
+            .write_mapping_file(tmp_buffer_mapping_file_path.clone(), MappingLayout::Fixed)
+            .expect("write mapping file failed");
+
+        let line_index = LineIndex::from_mapping_file(tmp_buffer_mapping_file_path, tmp_input_file_path.clone())
+            .expect("read line index from mapping file");
+        let mut reader = MappingFileReader::open(tmp_mapping_file_path, tmp_input_file_path).expect("open mapping file failed");
+
+        assert_same_lines_in_line_index(&line_index, &mut reader);
+    }
+
+    #[test]
+    fn line_index_should_be_read_from_mapping_file_of_any_layout() {
+        let (tmp_input_file_path, tmp_fixed_mapping_file_path, tmp_compact_mapping_file_path) =
+            create_mapping_files_with_both_layouts(LINE_INDEX_INPUT);
+        let tmp_sparse_mapping_file_path = get_tmp_file_path();
+
+        create_sparse_mapping_file(tmp_sparse_mapping_file_path.clone(), tmp_input_file_path.clone(), MappingLayout::Fixed, 2)
+            .expect("create mapping file failed");
+
+        for mapping_file_path in [tmp_fixed_mapping_file_path.clone(), tmp_compact_mapping_file_path, tmp_sparse_mapping_file_path] {
+            let line_index = LineIndex::from_mapping_file(mapping_file_path.clone(), tmp_input_file_path.clone())
+                .expect("read line index from mapping file");
+            let mut reader = MappingFileReader::open(tmp_fixed_mapping_file_path.clone(), tmp_input_file_path.clone())
+                .expect("open mapping file failed");
+
+            assert_same_lines_in_line_index(&line_index, &mut reader);
+        }
+    }
+
+// ---------------------------------------------
+// Create mapping file in parallel
+// ---------------------------------------------
+
+    // Returns the mapping file created in parallel and the mapping file created in a single thread
+    fn create_mapping_file_in_parallel_and_sequentially(
+        input: &[u8],
+        layout: MappingLayout,
+        checkpoint_interval: usize,
+        threads: usize,
+        chunk_size_in_bytes: usize,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_expected_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), input).expect("write input file failed");
+
+        create_mapping_file_in_parallel(
+            tmp_mapping_file_path.clone(),
+            tmp_input_file_path.clone(),
+            layout,
+            checkpoint_interval,
+            ParallelOptions {
+                threads: Some(threads),
+                chunk_size_in_bytes: Some(chunk_size_in_bytes),
+            },
+        )
+            .expect("create mapping file failed");
+        create_sparse_mapping_file(tmp_expected_mapping_file_path.clone(), tmp_input_file_path.clone(), layout, checkpoint_interval)
+            .expect("create mapping file failed");
+
+        return (
+            std::fs::read(tmp_mapping_file_path).expect("read mapping file failed"),
+            std::fs::read(tmp_expected_mapping_file_path).expect("read mapping file failed"),
+        );
+    }
+
+    #[test_case(""; "empty file")]
+    #[test_case("Hello\nworld\n"; "no style")]
+    #[test_case("\x1b[31mHello\nworld\n\x1b[1mhow\nare\x1b[0m\nyou"; "style across chunks")]
+    #[test_case("\x1b[1;3;4;9mHello\nworld\x1b[22;23m\nhow\x1b[4:3m\nare\x1b[24m\nyou"; "text styles added and removed across chunks")]
+    #[test_case("\x1b[38;2;1;2;3mHello\nworld\x1b[39m\nhow\n"; "same color as the probe style")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\Hello\nworld\x1b]8;;\x1b\\\nhow\n"; "hyperlink across chunks")]
+    #[test_case("Hello\x1b]0;window\ntitle\x07\nworld\nhow\nare\nyou\n"; "escape code with new line")]
+    #[test_case("Hello\x1bP q\n#0\n#1\n#2\x1b\\\x1b[31mworld\nhow\n"; "escape code with multiple new lines")]
+    #[test_case("Hello\n\x1b[31mworld\n\x1b]8;;unterminated\nhow\nare"; "unterminated escape code at the end")]
+    #[test_case("\x1b[31m\n\n\x1b[1m\n\x1b[0m\n\n"; "lines without text")]
+    #[test_case("Hello\n\x1b[31m\n\x1b]8;;https://example.com\x1b\\\nworld\n\x1b[0m"; "chunks that start with escape codes")]
+    #[test_case("Hello\x1b]0;a\nb\nc\nd\ne\nf\ng\nh\ni\nj\x07\x1b[31mworld\nhow\nare\nyou\n"; "escape code across many chunks")]
+    fn file_mapping_created_in_parallel_should_be_the_same_as_created_in_single_thread(input: &str) {
+        for layout in [MappingLayout::Fixed, MappingLayout::Compact] {
+            for chunk_size_in_bytes in [1, 3, 7, 1024] {
+                let (mapping, expected_mapping) =
+                    create_mapping_file_in_parallel_and_sequentially(input.as_bytes(), layout, 1, 3, chunk_size_in_bytes);
+
+                assert_eq!(
+                    String::from_utf8_lossy(&mapping),
+                    String::from_utf8_lossy(&expected_mapping),
+                    "{:?} layout with chunks of {} bytes",
+                    layout,
+                    chunk_size_in_bytes
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_file_mapping_created_in_parallel_should_be_the_same_as_created_in_single_thread() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"\x1b[31mHello\nworld\n\x1b[0mhow\nare you").expect("compress input");
+
+        let (mapping, expected_mapping) = create_mapping_file_in_parallel_and_sequentially(
+            &encoder.finish().expect("compress input"),
+            MappingLayout::Fixed,
+            1,
+            3,
+            1,
+        );
+
+        assert_eq!(String::from_utf8_lossy(&mapping), String::from_utf8_lossy(&expected_mapping));
+    }
+
+    const INPUT_PARTS: [&str; 22] = [
+        "a",
+        "bc ",
+        "\n",
+        "\x1b[31m",
+        "\x1b[1m",
+        "\x1b[2m",
+        "\x1b[0m",
+        "\x1b[22m",
+        "\x1b[3m",
+        "\x1b[23m",
+        "\x1b[4:3m",
+        "\x1b[24m",
+        "\x1b[38;2;1;2;3m",
+        "\x1b[39m",
+        "\x1b[48;5;4m",
+        "\x1b[58;5;9m",
+        "\x1b]8;;https://example.com\x1b\\",
+        "\x1b]8;;\x1b\\",
+        "\x1b]0;window\ntitle\x07",
+        "\x1bP q\n#0\x1b\\",
+        "\x1b]8;;unterminated\n",
+        "\x1b[",
+    ];
+
+    proptest! {
+        #[test]
+        fn test_file_mapping_created_in_parallel_should_be_the_same_as_created_in_single_thread(
+            parts in proptest::collection::vec(proptest::sample::select(INPUT_PARTS.to_vec()), 0..60),
+            threads in 1usize..4,
+            chunk_size_in_bytes in 1usize..40,
+            compact in any::<bool>(),
+            checkpoint_interval in 1usize..4,
+        ) {
+            let layout = if compact { MappingLayout::Compact } else { MappingLayout::Fixed };
+
+            let (mapping, expected_mapping) = create_mapping_file_in_parallel_and_sequentially(
+                parts.concat().as_bytes(),
+                layout,
+                checkpoint_interval,
+                threads,
+                chunk_size_in_bytes,
+            );
+
+            prop_assert_eq!(mapping, expected_mapping);
+        }
+    }
+
+// ---------------------------------------------
+// Update mapping file
+// ---------------------------------------------
+
+    // Create the mapping file for the original text, change the input file and update the mapping file
+    fn update_mapping_file(original_text: &str, new_text: &str) -> (MappingFileUpdate, Vec<u8>, Vec<u8>) {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_expected_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), original_text).expect("write input file failed");
+        create_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        std::fs::write(tmp_input_file_path.clone(), new_text).expect("write input file failed");
+
+        let update = update_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("update mapping file failed");
+
+        create_mapping_file_from_input_path(tmp_expected_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("create mapping file failed");
+
+        return (
+            update,
+            std::fs::read(tmp_mapping_file_path).expect("read mapping file failed"),
+            std::fs::read(tmp_expected_mapping_file_path).expect("read mapping file failed"),
+        );
+    }
+
+    #[test_case("Hello\n", "world\n", 1; "after new line")]
+    #[test_case("Hello\nwor", "ld\nhow are you", 1; "after partial line")]
+    #[test_case("Hello\n\x1b[31mworld\n", "\x1b[1mhow\nare you\n", 2; "keep the style of the last line")]
+    #[test_case("Hello\n", "\x1b[31mworld", 0; "style at the start of the last line")]
+    #[test_case("\x1b[31mHello\x1b[", "1m world\nhow are you", 1; "unfinished escape code")]
+    #[test_case("", "Hello\nworld", 1; "empty file")]
+    #[test_case(
+        "\x1b]8;;https://a.com\x1b\\Hello\n",
+        "world\n\x1b]8;;https://b.com\x1b\\how\nare\x1b]8;;\x1b\\ you\n",
+        3;
+        "hyperlinks"
+    )]
+    fn updating_mapping_file_of_appended_file_should_be_the_same_as_creating_it(
+        original_text: &str,
+        appended_text: &str,
+        expected_new_lines: usize,
+    ) {
+        let (update, mapping, expected_mapping) =
+            update_mapping_file(original_text, &(original_text.to_string() + appended_text));
+
+        assert_eq!(update, MappingFileUpdate::Appended { new_lines: expected_new_lines });
+        assert_eq!(String::from_utf8_lossy(&mapping), String::from_utf8_lossy(&expected_mapping));
+    }
+
+    #[test_case("Hello\nworld\n", "Bye\n"; "shorter file")]
+    #[test_case("Hello\nworld\nhow are you", "Hello\nwor\nld how are you"; "last full line changed")]
+    #[test_case("Hello\nworld\nhow are you", "Hello\n\x1b[31mworld\nhow are you"; "style of last full line changed")]
+    #[test_case("Hello\nworld\nhow are you\n", "Jello\nworld\nhow are you\nappended"; "line before the last full lines changed")]
+    fn updating_mapping_file_of_rewritten_file_should_create_it_from_scratch(original_text: &str, new_text: &str) {
+        let (update, mapping, expected_mapping) = update_mapping_file(original_text, new_text);
+
+        assert_eq!(update, MappingFileUpdate::Recreated);
+        assert_eq!(String::from_utf8_lossy(&mapping), String::from_utf8_lossy(&expected_mapping));
+    }
+
+    #[test]
+    fn updating_missing_mapping_file_should_create_it() {
+        let tmp_input_file_path = get_tmp_file_path();
+        let tmp_mapping_file_path = get_tmp_file_path();
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello\nworld").expect("write input file failed");
+
+        let update = update_mapping_file_from_input_path(tmp_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("update mapping file failed");
+
+        assert_eq!(update, MappingFileUpdate::Recreated);
+        assert_eq!(
+            get_line_metadata_from_file_path(tmp_mapping_file_path, tmp_input_file_path, 2)
+                .expect("read mapping file failed")
+                .map(|item| item.location_in_original_file),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn updating_sparse_mapping_file_should_create_it_again_with_the_same_checkpoint_interval() {
+        let (tmp_input_file_path, _, tmp_sparse_mapping_file_path) =
+            create_sparse_and_full_mapping_files(b"Hello\nworld\n", MappingLayout::Fixed, 2);
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello\nworld\n\x1b[31mhow\nare you").expect("write input file failed");
+
+        let update = update_mapping_file_from_input_path(tmp_sparse_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("update mapping file failed");
+
+        let mut reader = MappingFileReader::open(tmp_sparse_mapping_file_path, tmp_input_file_path).expect("open mapping file failed");
+
+        assert_eq!(update, MappingFileUpdate::Recreated);
+        assert_eq!(reader.header().checkpoint_interval, 2);
+        assert_eq!(reader.header().line_count, 2);
+        assert_eq!(
+            reader.get_line_metadata(4).expect("read mapping file failed"),
+            Some(MappingItem {
+                initial_span: Span::empty().with_color(Color::Red),
+                location_in_original_file: 21,
+            })
+        );
+    }
+
+    #[test]
+    fn updating_compact_mapping_file_should_create_it_again_with_the_compact_layout() {
+        let (tmp_input_file_path, _, tmp_compact_mapping_file_path) = create_mapping_files_with_both_layouts("Hello\n");
+
+        std::fs::write(tmp_input_file_path.clone(), "Hello\n\x1b[31mworld").expect("write input file failed");
+
+        let update = update_mapping_file_from_input_path(tmp_compact_mapping_file_path.clone(), tmp_input_file_path.clone())
+            .expect("update mapping file failed");
+
+        let mut reader = MappingFileReader::open(tmp_compact_mapping_file_path, tmp_input_file_path).expect("open mapping file failed");
+
+        assert_eq!(update, MappingFileUpdate::Recreated);
+        assert_eq!(reader.header().layout, MappingLayout::Compact);
+        assert_eq!(
+            reader.get_line_metadata(2).expect("read mapping file failed"),
+            Some(MappingItem {
+                initial_span: Span::empty().with_color(Color::Red),
+                location_in_original_file: 6,
+            })
+        );
+    }
+
 //
 // #[test]
 // fn should_throw_for_missing_line_in_mapping() {
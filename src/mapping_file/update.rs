@@ -0,0 +1,288 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+
+use crate::error::Error;
+use crate::files::compression::{detect_file_compression, Compression};
+use crate::files::file_reader::FileReaderOptions;
+use crate::mapping_file::constants::*;
+use crate::mapping_file::create::{create_line_map, create_sparse_mapping_file, get_mapping_parse_options};
+use crate::mapping_file::fingerprint::SourceFingerprint;
+use crate::mapping_file::header::{MappingHeader, MappingLayout};
+use crate::mapping_file::links::{read_link_table, LinkTable};
+use crate::mapping_file::read::{get_line_metadata_from_file, get_mapping_file_header, open_mapping_file};
+use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
+use crate::parse_file::types::ReadAnsiFileOptions;
+use crate::types::Line;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MappingFileUpdate {
+    // Only the lines after the last line in the mapping file were parsed
+    Appended { new_lines: usize },
+
+    // The input file was rewritten (or the mapping file can't be resumed) so the mapping file was created from scratch
+    Recreated,
+}
+
+// Where to continue parsing the input file from
+struct ResumePoint {
+    header: MappingHeader,
+
+    // The mapping of the lines from the line the parsing continues from until the last line
+    existing_lines_map: Vec<u8>,
+
+    // Where the existing lines map starts in the mapping file
+    mapping_file_offset: usize,
+
+    location_in_input_file: usize,
+    parse_options: ParseOptions,
+
+    // The link table of the mapping file, the new lines add their hyperlinks to it
+    links: Vec<Hyperlink>,
+}
+
+// Update the mapping file of an input file that was only appended to since the mapping file was created (e.g. logs),
+// the input is parsed again from the last full line, and the mapping file is created from scratch when the input was rewritten
+pub fn update_mapping_file_from_input_path(
+    mapping_file_path: PathBuf,
+    input_file_path: PathBuf,
+) -> Result<MappingFileUpdate, Error> {
+    let file_path = match input_file_path.to_str() {
+        Some(file_path) => file_path.to_string(),
+        None => return Err(Error::InvalidPath(input_file_path)),
+    };
+
+    let resume_point = match get_resume_point(&mapping_file_path, &input_file_path)? {
+        Some(resume_point) => resume_point,
+        None => return recreate_mapping_file(mapping_file_path, input_file_path),
+    };
+
+    let source = SourceFingerprint::from_file(&mut File::open(&input_file_path)?)?;
+
+    let from_bytes = resume_point.location_in_input_file;
+    let mut link_table = LinkTable::from_links(resume_point.links);
+
+    let mut lines = read_ansi_file_to_lines(ReadAnsiFileOptions {
+        file_options: FileReaderOptions {
+            file_path,
+            chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
+            from_bytes: Some(from_bytes),
+            // Bytes that are appended while parsing are not part of the fingerprint
+            to_bytes: Some(source.size as usize),
+            seek_point: None,
+            backend: None,
+        },
+        parse_options: resume_point.parse_options,
+    })?
+    .map(|line| {
+        line.map(|mut line| {
+            // The locations are from where the parsing started
+            line.location_in_file += from_bytes;
+
+            line
+        })
+    });
+
+    let existing_lines_count = resume_point.existing_lines_map.len() / FULL_LINE_LENGTH;
+
+    let parsed_existing_lines_map = lines
+        .by_ref()
+        .take(existing_lines_count)
+        .map(|line| line.and_then(|line| create_line_map(line, &mut link_table)))
+        .collect::<Result<Vec<Vec<u8>>, Error>>()?
+        .concat();
+
+    if !was_appended(&resume_point.existing_lines_map, &parsed_existing_lines_map) {
+        return recreate_mapping_file(mapping_file_path, input_file_path);
+    }
+
+    // Replacing the last line as it may have not been complete
+    let last_line_offset_in_map = (existing_lines_count - 1) * FULL_LINE_LENGTH;
+
+    let header = MappingHeader {
+        source,
+        ..resume_point.header
+    };
+
+    // The mapping file is updated in a copy that replaces it once it's complete,
+    // so an interrupted update does not leave the mapping file with the header of the old lines and only some of the new lines
+    let updated_mapping_file_path = get_updated_mapping_file_path(&mapping_file_path);
+
+    let new_lines = match write_updated_mapping_file(
+        &mapping_file_path,
+        &updated_mapping_file_path,
+        header,
+        resume_point.mapping_file_offset + last_line_offset_in_map,
+        &parsed_existing_lines_map[last_line_offset_in_map..],
+        lines,
+        link_table,
+    ) {
+        Ok(new_lines) => new_lines,
+        Err(error) => {
+            // The mapping file was not changed
+            let _ = std::fs::remove_file(&updated_mapping_file_path);
+
+            return Err(error);
+        }
+    };
+
+    std::fs::rename(&updated_mapping_file_path, &mapping_file_path)?;
+
+    return Ok(MappingFileUpdate::Appended { new_lines });
+}
+
+fn get_updated_mapping_file_path(mapping_file_path: &Path) -> PathBuf {
+    let mut updated_mapping_file_path = OsString::from(mapping_file_path.as_os_str());
+    updated_mapping_file_path.push(".update");
+
+    return PathBuf::from(updated_mapping_file_path);
+}
+
+// Copy the start of the mapping file until the replaced lines, and write the replaced lines, the new lines and the link table after it,
+// returns the number of new lines
+fn write_updated_mapping_file(
+    mapping_file_path: &Path,
+    updated_mapping_file_path: &Path,
+    header: MappingHeader,
+    kept_length: usize,
+    replaced_lines_map: &[u8],
+    new_lines: impl Iterator<Item=Result<Line, Error>>,
+    mut link_table: LinkTable,
+) -> Result<usize, Error> {
+    let mut updated_mapping_file = File::create(updated_mapping_file_path)?;
+
+    let copied_length = std::io::copy(&mut File::open(mapping_file_path)?.take(kept_length as u64), &mut updated_mapping_file)?;
+
+    if copied_length != kept_length as u64 {
+        return Err(Error::InvalidMappingFile("mapping file changed while updating it".to_string()));
+    }
+
+    updated_mapping_file.write_all(replaced_lines_map)?;
+
+    let mut new_lines_count = 0;
+
+    let new_lines_map = new_lines
+        .map(|line| line.and_then(|line| create_line_map(line, &mut link_table)))
+        .chunks(1024 * 1024 * 10);
+
+    for chunk in &new_lines_map {
+        let merged = chunk.collect::<Result<Vec<Vec<u8>>, Error>>()?;
+        new_lines_count += merged.len();
+
+        updated_mapping_file.write_all(&merged.concat())?;
+    }
+
+    drop(new_lines_map);
+
+    let link_table = link_table.serialize();
+    updated_mapping_file.write_all(&link_table)?;
+
+    let header = MappingHeader {
+        line_count: header.line_count + new_lines_count,
+        link_table_size: link_table.len(),
+        ..header
+    };
+
+    // The header length does not change so it can be replaced in place
+    updated_mapping_file.seek(SeekFrom::Start(0))?;
+    updated_mapping_file.write_all(header.serialize().as_bytes())?;
+
+    // The content must be written before the mapping file is replaced with it
+    updated_mapping_file.sync_all()?;
+
+    return Ok(new_lines_count);
+}
+
+// The full lines must be the same, and the last line must start at the same location
+// but its initial style can change when it was empty or partial (e.g. a style at the start of the line was not written yet),
+// the lines before them are not parsed again, the fingerprint of the old content makes sure they were not changed
+fn was_appended(existing_lines_map: &[u8], parsed_existing_lines_map: &[u8]) -> bool {
+    if existing_lines_map.len() != parsed_existing_lines_map.len() || existing_lines_map.is_empty() {
+        return false;
+    }
+
+    let last_line_offset = existing_lines_map.len() - FULL_LINE_LENGTH;
+    let last_line_location_offset = last_line_offset + FIRST_PART_LINE_LENGTH + LINK_ID_LENGTH;
+    let last_line_location = last_line_location_offset..last_line_location_offset + SECOND_PART_LINE_LENGTH;
+
+    return existing_lines_map[..last_line_offset] == parsed_existing_lines_map[..last_line_offset]
+        && existing_lines_map[last_line_location.clone()] == parsed_existing_lines_map[last_line_location];
+}
+
+fn recreate_mapping_file(mapping_file_path: PathBuf, input_file_path: PathBuf) -> Result<MappingFileUpdate, Error> {
+    // Keep the layout and the checkpoint interval the mapping file was created with
+    let (layout, checkpoint_interval) = get_mapping_file_header(mapping_file_path.clone())
+        .map(|header| (header.layout, header.checkpoint_interval))
+        .unwrap_or((MappingLayout::Fixed, 1));
+
+    create_sparse_mapping_file(mapping_file_path, input_file_path, layout, checkpoint_interval)?;
+
+    return Ok(MappingFileUpdate::Recreated);
+}
+
+// None when the mapping file can't be resumed and must be created from scratch
+fn get_resume_point(mapping_file_path: &Path, input_file_path: &Path) -> Result<Option<ResumePoint>, Error> {
+    let (mut mapping_file, offsets, header) = match open_mapping_file(mapping_file_path.to_path_buf()) {
+        Ok(mapping_file) => mapping_file,
+        Err(Error::Io(error)) if error.kind() == ErrorKind::NotFound => return Ok(None),
+
+        // Older formats are created again, other files are not overwritten
+        Err(Error::UnsupportedMappingFileVersion(_)) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    // Created with a different line length or for a compressed file that can't be appended to,
+    // the compact layout has the style table after the lines and a sparse mapping file does not have the last line,
+    // so they are created again as well
+    if header.layout != MappingLayout::Fixed
+        || header.checkpoint_interval != 1
+        || header.line_length != FULL_LINE_LENGTH
+        || header.compression != Compression::None
+    {
+        return Ok(None);
+    }
+
+    let mut input_file = File::open(input_file_path)?;
+
+    if detect_file_compression(&mut input_file)? != Compression::None {
+        return Ok(None);
+    }
+
+    // The input file was rewritten when it's shorter or the samples of the old content are different
+    if !header.source.is_prefix_of(&mut input_file)? {
+        return Ok(None);
+    }
+
+    if header.line_count == 0 {
+        return Ok(None);
+    }
+
+    // Parsing from the line before the last one to verify the last full line was not changed
+    let line_number = (header.line_count - 1).max(1);
+
+    let links = read_link_table(&mut mapping_file, offsets.link_table, header.link_table_size)?;
+
+    let mapping_item = get_line_metadata_from_file(&mut mapping_file, line_number, offsets.content_start, &header, &links)?
+        .ok_or_else(|| Error::InvalidMappingFile("missing line".to_string()))?;
+
+    // The header is followed by the delimiter and then the lines, the link table after them is not part of the lines map
+    let mapping_file_offset = offsets.content_start + DELIMITER.len() + (line_number - 1) * FULL_LINE_LENGTH;
+    let mut existing_lines_map = vec![0u8; (header.line_count - line_number + 1) * FULL_LINE_LENGTH];
+
+    mapping_file.seek(SeekFrom::Start(mapping_file_offset as u64))?;
+    mapping_file.read_exact(&mut existing_lines_map)?;
+
+    return Ok(Some(ResumePoint {
+        header,
+        existing_lines_map,
+        mapping_file_offset,
+        location_in_input_file: mapping_item.location_in_original_file,
+        parse_options: get_mapping_parse_options().with_initial_span(mapping_item.initial_span),
+        links,
+    }));
+}
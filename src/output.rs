@@ -1,3 +1,5 @@
+pub mod html;
+
 pub enum OutputDestination {
     Stdout,
 
@@ -0,0 +1,624 @@
+use std::iter;
+
+use const_typed_builder::Builder;
+
+use crate::error::Error;
+use crate::parse_ansi_text::ansi::colors::{get_palette_index, Color};
+use crate::parse_ansi_text::ansi::style::{Brightness, TextStyle};
+use crate::parse_ansi_text::ansi::theme::{get_css_color, Theme};
+use crate::parse_ansi_text::ansi::types::Span;
+use crate::types::Line;
+
+// Names of the 16 colors in the CSS classes, in the theme order
+const THEME_COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+// Hyperlinks come from the input (e.g. CI logs) so other schemes like `javascript:` are rendered as text
+const SAFE_LINK_SCHEMES: [&str; 4] = ["http://", "https://", "mailto:", "ftp://"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlStyles {
+    // Each span has a `style` attribute
+    Inline,
+
+    // Each span has CSS classes from `get_html_stylesheet`, colors that are not one of the 16 theme colors (8-bit and RGB)
+    // and the colors of inverse, dim and hidden text are still in the `style` attribute as they depend on the other colors
+    Classes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HtmlMode {
+    // Only the `<pre>` element, to add to an existing page
+    Fragment,
+
+    // Full HTML document with the stylesheet
+    Document { title: String },
+}
+
+#[derive(Debug, Clone, Default, Builder)]
+pub struct HtmlOptions {
+    // Inline styles when not set
+    pub styles: Option<HtmlStyles>,
+
+    // The xterm theme when not set
+    pub theme: Option<Theme>,
+
+    // Each line gets an anchor with its number (e.g. `#L10`) so it can be linked to
+    pub line_numbers: bool,
+
+    // The number of the first line, for rendering from the middle of the file, 1 when not set
+    pub first_line_number: Option<usize>,
+
+    // Fragment when not set
+    pub mode: Option<HtmlMode>,
+}
+
+// Render the lines as they are parsed, the first item is the start of the document and the last one is the end
+pub fn lines_to_html(
+    lines: impl Iterator<Item=Result<Line, Error>>,
+    options: HtmlOptions,
+) -> impl Iterator<Item=Result<String, Error>> {
+    let start = get_html_start(&options);
+    let end = get_html_end(&options);
+    let first_line_number = options.first_line_number.unwrap_or(1);
+
+    let lines = lines.enumerate().map(move |(index, line)| {
+        line.map(|line| {
+            // The new line is between the lines so there is no empty line at the end
+            let separator = if index == 0 { "" } else { "\n" };
+
+            format!("{}{}", separator, line_to_html(&line, first_line_number + index, &options))
+        })
+    });
+
+    return iter::once(Ok(start)).chain(lines).chain(iter::once(Ok(end)));
+}
+
+pub fn line_to_html(line: &Line, line_number: usize, options: &HtmlOptions) -> String {
+    let content = line.spans.iter().map(|span| span_to_html(span, options)).collect::<String>();
+
+    if !options.line_numbers {
+        return content;
+    }
+
+    return format!(
+        "<span class=\"ansi-line\" id=\"L{0}\"><a class=\"ansi-line-number\" href=\"#L{0}\">{0}</a>{1}</span>",
+        line_number, content
+    );
+}
+
+pub fn span_to_html(span: &Span, options: &HtmlOptions) -> String {
+    let theme = options.theme.unwrap_or_default();
+
+    let (mut classes, css) = match options.styles.unwrap_or(HtmlStyles::Inline) {
+        HtmlStyles::Inline => (vec![], span.create_css_string(&theme)),
+        HtmlStyles::Classes => (get_span_classes(span), get_span_inline_colors_css(span, &theme)),
+    };
+
+    // Blinking is an animation so it's a class with both styles
+    if let Some(blink_class) = get_blink_class(span) {
+        classes.push(blink_class.to_string());
+    }
+
+    let mut attributes = "".to_string();
+
+    if !classes.is_empty() {
+        attributes = format!("{} class=\"{}\"", attributes, classes.join(" "));
+    }
+
+    if !css.is_empty() {
+        attributes = format!("{} style=\"{}\"", attributes, escape_html(&css));
+    }
+
+    let text = escape_html(&String::from_utf8_lossy(&span.text));
+
+    // Avoid empty elements for text without style
+    let html = if attributes.is_empty() {
+        text
+    } else {
+        format!("<span{}>{}</span>", attributes, text)
+    };
+
+    return match get_safe_link(span) {
+        Some(uri) => format!("<a href=\"{}\">{}</a>", escape_html(&uri), html),
+        None => html,
+    };
+}
+
+// The rules for the line numbers and blinking, and for the CSS classes when using classes
+pub fn get_html_stylesheet(options: &HtmlOptions) -> String {
+    let mut rules = vec![
+        ".ansi-line-number { display: inline-block; min-width: 4ch; margin-right: 1ch; text-align: right; color: gray; text-decoration: none; user-select: none; }".to_string(),
+        "@keyframes ansi-blink { 50% { opacity: 0; } }".to_string(),
+        ".ansi-blink { animation: ansi-blink 1s step-end infinite; }".to_string(),
+        ".ansi-rapid-blink { animation: ansi-blink 0.4s step-end infinite; }".to_string(),
+    ];
+
+    if options.styles == Some(HtmlStyles::Classes) {
+        rules.extend(
+            get_class_rules(&options.theme.unwrap_or_default())
+                .into_iter()
+                .map(|(class, css)| format!(".{} {{ {} }}", class, css)),
+        );
+    }
+
+    return rules.join("\n");
+}
+
+fn get_html_start(options: &HtmlOptions) -> String {
+    return match &options.mode {
+        Some(HtmlMode::Document { title }) => format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}",
+            escape_html(title),
+            get_html_stylesheet(options),
+            get_pre_start(options)
+        ),
+        Some(HtmlMode::Fragment) | None => get_pre_start(options),
+    };
+}
+
+fn get_html_end(options: &HtmlOptions) -> String {
+    return match &options.mode {
+        Some(HtmlMode::Document { .. }) => "</pre>\n</body>\n</html>\n".to_string(),
+        Some(HtmlMode::Fragment) | None => "</pre>\n".to_string(),
+    };
+}
+
+// The text without colors has the theme colors like the terminal it was printed in
+fn get_pre_start(options: &HtmlOptions) -> String {
+    let theme = options.theme.unwrap_or_default();
+
+    return format!(
+        "<pre class=\"ansi\" style=\"color: {};background-color: {};\">",
+        get_css_color(theme.foreground),
+        get_css_color(theme.background)
+    );
+}
+
+// Inverse, dim and hidden text depend on the other colors so they are not classes
+fn has_dependent_colors(span: &Span) -> bool {
+    return span.brightness == Brightness::Dim || span.text_style.intersects(TextStyle::Inverse | TextStyle::Hidden);
+}
+
+// The colors that don't have a class
+fn get_span_inline_colors_css(span: &Span, theme: &Theme) -> String {
+    if has_dependent_colors(span) {
+        return span.create_colors_css_string(theme);
+    }
+
+    let without_theme_color = |color: Color| if get_palette_index(color).is_some() { Color::None } else { color };
+
+    return Span {
+        color: without_theme_color(span.color),
+        bg_color: without_theme_color(span.bg_color),
+        ..Span::clone_without_text(span)
+    }
+    .create_colors_css_string(theme);
+}
+
+fn get_span_classes(span: &Span) -> Vec<String> {
+    let style_span = Span::clone_without_text(span);
+
+    let mut classes = get_text_style_classes()
+        .into_iter()
+        .filter(|(_, class_span)| {
+            class_span.brightness == style_span.brightness && class_span.brightness != Brightness::None
+                || class_span.text_style != TextStyle::None && style_span.text_style.contains(class_span.text_style)
+        })
+        .map(|(class, _)| class.to_string())
+        .collect::<Vec<String>>();
+
+    // `text-decoration-line` is a single property so each combination of lines has its own class
+    let text_decoration_lines = style_span.get_text_decoration_lines();
+
+    if !text_decoration_lines.is_empty() {
+        classes.push(format!("ansi-{}", text_decoration_lines.join("-")));
+    }
+
+    if let Some(text_decoration_style) = style_span.get_text_decoration_style() {
+        classes.push(format!("ansi-decoration-{}", text_decoration_style));
+    }
+
+    if has_dependent_colors(span) {
+        return classes;
+    }
+
+    if let Some(index) = get_palette_index(span.color) {
+        classes.push(format!("ansi-fg-{}", THEME_COLOR_NAMES[index]));
+    }
+
+    if let Some(index) = get_palette_index(span.bg_color) {
+        classes.push(format!("ansi-bg-{}", THEME_COLOR_NAMES[index]));
+    }
+
+    return classes;
+}
+
+fn get_blink_class(span: &Span) -> Option<&'static str> {
+    if span.text_style.contains(TextStyle::RapidBlink) {
+        return Some("ansi-rapid-blink");
+    }
+
+    if span.text_style.contains(TextStyle::SlowBlink) {
+        return Some("ansi-blink");
+    }
+
+    return None;
+}
+
+// Classes of the styles that are a single CSS property, with a span that has only that style
+fn get_text_style_classes() -> [(&'static str, Span); 4] {
+    return [
+        ("ansi-bold", Span::empty().with_brightness(Brightness::Bold)),
+        ("ansi-italic", Span::empty().with_text_style(TextStyle::Italic)),
+        ("ansi-superscript", Span::empty().with_text_style(TextStyle::Superscript)),
+        ("ansi-subscript", Span::empty().with_text_style(TextStyle::Subscript)),
+    ];
+}
+
+// Every class `get_span_classes` can return with its CSS
+fn get_class_rules(theme: &Theme) -> Vec<(String, String)> {
+    let mut rules = get_text_style_classes()
+        .into_iter()
+        .map(|(class, span)| (class.to_string(), span.create_css_string(theme)))
+        .collect::<Vec<(String, String)>>();
+
+    let text_decoration_lines = [TextStyle::Strikethrough, TextStyle::Underline, TextStyle::Overline];
+
+    // Every combination of the decoration lines
+    for combination in 1..(1 << text_decoration_lines.len()) {
+        let text_style = text_decoration_lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| combination & (1 << index) != 0)
+            .fold(TextStyle::None, |text_style, (_, line)| text_style | *line);
+        let span = Span::empty().with_text_style(text_style);
+
+        rules.push((format!("ansi-{}", span.get_text_decoration_lines().join("-")), span.create_css_string(theme)));
+    }
+
+    for text_style in [TextStyle::DoubleUnderline, TextStyle::CurlyUnderline, TextStyle::DottedUnderline, TextStyle::DashedUnderline] {
+        if let Some(text_decoration_style) = Span::empty().with_text_style(text_style).get_text_decoration_style() {
+            rules.push((
+                format!("ansi-decoration-{}", text_decoration_style),
+                format!("text-decoration-style: {};", text_decoration_style),
+            ));
+        }
+    }
+
+    for (index, name) in THEME_COLOR_NAMES.iter().enumerate() {
+        let color = get_css_color(theme.colors[index]);
+
+        rules.push((format!("ansi-fg-{}", name), format!("color: {};", color)));
+        rules.push((format!("ansi-bg-{}", name), format!("background-color: {};", color)));
+    }
+
+    return rules;
+}
+
+fn get_safe_link(span: &Span) -> Option<String> {
+    let uri = String::from_utf8_lossy(&span.link.as_ref()?.uri).to_string();
+    let lowercase_uri = uri.to_lowercase();
+
+    if !SAFE_LINK_SCHEMES.iter().any(|scheme| lowercase_uri.starts_with(scheme)) {
+        return None;
+    }
+
+    return Some(uri);
+}
+
+// Control characters (e.g. carriage return or an escape that did not start a valid sequence) are removed
+// as they are not allowed in HTML, tabs and new lines are kept
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\t' | '\n' => escaped.push(character),
+            _ if character.is_control() => {}
+            _ => escaped.push(character),
+        }
+    }
+
+    return escaped;
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
+
+    use super::*;
+
+    fn create_line(spans: Vec<Span>) -> Line {
+        return Line {
+            spans,
+            location_in_file: 0,
+        };
+    }
+
+    fn create_link(uri: &str) -> Option<Hyperlink> {
+        return Some(Hyperlink {
+            uri: uri.as_bytes().to_vec(),
+            params: vec![],
+        });
+    }
+
+    fn with_classes() -> HtmlOptions {
+        return HtmlOptions {
+            styles: Some(HtmlStyles::Classes),
+            ..HtmlOptions::default()
+        };
+    }
+
+    #[test]
+    fn span_without_style_should_be_text_only() {
+        let span = Span::empty().with_text(b"Hello".to_vec());
+
+        assert_eq!(span_to_html(&span, &HtmlOptions::default()), "Hello");
+    }
+
+    #[test]
+    fn span_text_should_be_escaped() {
+        let span = Span::empty().with_text(b"<b>\"Tom\" & 'Jerry'</b>".to_vec());
+
+        assert_eq!(
+            span_to_html(&span, &HtmlOptions::default()),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn inline_styles_should_resolve_colors_from_the_theme() {
+        let span = Span::empty()
+            .with_text(b"Hello".to_vec())
+            .with_brightness(Brightness::Bold)
+            .with_color(Color::Red)
+            .with_bg_color(Color::Rgb(1, 2, 3));
+
+        assert_eq!(
+            span_to_html(&span, &HtmlOptions::default()),
+            "<span style=\"font-weight: bold;color: rgb(205, 0, 0);background-color: rgb(1, 2, 3);\">Hello</span>"
+        );
+
+        let mut theme = Theme::default();
+        theme.colors[1] = (255, 85, 85);
+
+        assert_eq!(
+            span_to_html(
+                &span,
+                &HtmlOptions {
+                    theme: Some(theme),
+                    ..HtmlOptions::default()
+                }
+            ),
+            "<span style=\"font-weight: bold;color: rgb(255, 85, 85);background-color: rgb(1, 2, 3);\">Hello</span>"
+        );
+    }
+
+    #[test]
+    fn classes_should_only_keep_colors_outside_the_theme_inline() {
+        let span = Span::empty()
+            .with_text(b"Hello".to_vec())
+            .with_text_style(TextStyle::Italic | TextStyle::Overline | TextStyle::CurlyUnderline)
+            .with_color(Color::BrightCyan)
+            .with_bg_color(Color::EightBit(196))
+            .with_underline_color(Color::Rgb(1, 2, 3));
+
+        assert_eq!(
+            span_to_html(&span, &with_classes()),
+            "<span class=\"ansi-italic ansi-underline-overline ansi-decoration-wavy ansi-fg-bright-cyan\" style=\"text-decoration-color: rgb(1, 2, 3);background-color: rgb(255, 0, 0);\">Hello</span>"
+        );
+    }
+
+    #[test]
+    fn classes_should_keep_colors_of_inverse_and_dim_inline() {
+        let span = Span::empty()
+            .with_text(b"Hello".to_vec())
+            .with_brightness(Brightness::Bold)
+            .with_text_style(TextStyle::Inverse)
+            .with_color(Color::Red);
+
+        assert_eq!(
+            span_to_html(&span, &with_classes()),
+            "<span class=\"ansi-bold\" style=\"color: rgb(255, 255, 255);background-color: rgb(205, 0, 0);\">Hello</span>"
+        );
+
+        let span = span.with_brightness(Brightness::Dim).with_text_style(TextStyle::None);
+
+        assert_eq!(
+            span_to_html(&span, &with_classes()),
+            "<span style=\"color: rgb(230, 127, 127);\">Hello</span>"
+        );
+    }
+
+    #[test]
+    fn every_class_should_be_in_the_stylesheet() {
+        let stylesheet = get_html_stylesheet(&with_classes());
+
+        let span = Span::empty()
+            .with_text(b"Hello".to_vec())
+            .with_brightness(Brightness::Bold)
+            .with_text_style(
+                TextStyle::Superscript
+                    | TextStyle::Strikethrough
+                    | TextStyle::DoubleUnderline
+                    | TextStyle::RapidBlink,
+            )
+            .with_color(Color::EightBit(3))
+            .with_bg_color(Color::White);
+
+        let mut classes = get_span_classes(&span);
+        classes.extend(get_blink_class(&span).map(str::to_string));
+
+        assert_eq!(
+            classes,
+            vec![
+                "ansi-bold",
+                "ansi-superscript",
+                "ansi-line-through-underline",
+                "ansi-decoration-double",
+                "ansi-fg-yellow",
+                "ansi-bg-white",
+                "ansi-rapid-blink",
+            ]
+        );
+
+        for class in classes {
+            assert!(stylesheet.contains(&format!(".{} {{", class)), "missing rule for {}", class);
+        }
+    }
+
+    #[test]
+    fn stylesheet_should_only_have_the_line_numbers_and_blink_rules_for_inline_styles() {
+        assert_eq!(get_html_stylesheet(&HtmlOptions::default()).lines().count(), 4);
+    }
+
+    #[test]
+    fn blink_should_be_an_animation_class_instead_of_text_decoration() {
+        let span = Span::empty()
+            .with_text(b"Hello".to_vec())
+            .with_text_style(TextStyle::SlowBlink | TextStyle::Underline);
+
+        assert_eq!(
+            span_to_html(&span, &HtmlOptions::default()),
+            "<span class=\"ansi-blink\" style=\"text-decoration: underline;\">Hello</span>"
+        );
+        assert_eq!(
+            span_to_html(&span, &with_classes()),
+            "<span class=\"ansi-underline ansi-blink\">Hello</span>"
+        );
+    }
+
+    #[test]
+    fn control_characters_should_be_removed_from_the_text() {
+        let span = Span::empty().with_text(b"a\x00b\x07c\x1bd\te\r".to_vec());
+
+        assert_eq!(span_to_html(&span, &HtmlOptions::default()), "abcd\te");
+    }
+
+    #[test]
+    fn link_should_be_an_anchor_only_for_safe_schemes() {
+        let span = Span::empty()
+            .with_text(b"docs".to_vec())
+            .with_link(create_link("https://example.com/?a=1&b=2"));
+
+        assert_eq!(
+            span_to_html(&span, &HtmlOptions::default()),
+            "<a href=\"https://example.com/?a=1&amp;b=2\">docs</a>"
+        );
+
+        let span = span.with_link(create_link("javascript:alert(1)"));
+
+        assert_eq!(span_to_html(&span, &HtmlOptions::default()), "docs");
+    }
+
+    #[test]
+    fn line_numbers_should_add_anchors() {
+        let line = create_line(vec![Span::empty().with_text(b"Hello".to_vec())]);
+
+        let options = HtmlOptions {
+            line_numbers: true,
+            ..HtmlOptions::default()
+        };
+
+        assert_eq!(
+            line_to_html(&line, 12, &options),
+            "<span class=\"ansi-line\" id=\"L12\"><a class=\"ansi-line-number\" href=\"#L12\">12</a>Hello</span>"
+        );
+    }
+
+    #[test]
+    fn line_numbers_should_start_from_the_first_line_number() {
+        let lines = vec![
+            Ok(create_line(vec![Span::empty().with_text(b"first".to_vec())])),
+            Ok(create_line(vec![Span::empty().with_text(b"second".to_vec())])),
+        ];
+
+        let options = HtmlOptions {
+            line_numbers: true,
+            first_line_number: Some(100),
+            ..HtmlOptions::default()
+        };
+
+        let html = lines_to_html(lines.into_iter(), options)
+            .collect::<Result<String, Error>>()
+            .expect("render lines");
+
+        assert!(html.contains("id=\"L100\"><a class=\"ansi-line-number\" href=\"#L100\">100</a>first"));
+        assert!(html.contains("id=\"L101\"><a class=\"ansi-line-number\" href=\"#L101\">101</a>second"));
+    }
+
+    #[test]
+    fn fragment_should_be_a_pre_element_with_the_lines() {
+        let lines = vec![
+            Ok(create_line(vec![Span::empty().with_text(b"first".to_vec())])),
+            Ok(create_line(vec![])),
+            Ok(create_line(vec![Span::empty().with_text(b"third".to_vec()).with_color(Color::Blue)])),
+        ];
+
+        let html = lines_to_html(lines.into_iter(), HtmlOptions::default())
+            .collect::<Result<String, Error>>()
+            .expect("render lines");
+
+        assert_eq!(
+            html,
+            "<pre class=\"ansi\" style=\"color: rgb(0, 0, 0);background-color: rgb(255, 255, 255);\">first\n\n<span style=\"color: rgb(0, 0, 238);\">third</span></pre>\n"
+        );
+    }
+
+    #[test]
+    fn document_should_have_the_stylesheet_and_escaped_title() {
+        let lines = vec![Ok(create_line(vec![Span::empty().with_text(b"Hello".to_vec())]))];
+
+        let options = HtmlOptions {
+            mode: Some(HtmlMode::Document {
+                title: "build <1>".to_string(),
+            }),
+            ..with_classes()
+        };
+
+        let html = lines_to_html(lines.into_iter(), options.clone())
+            .collect::<Result<String, Error>>()
+            .expect("render lines");
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>build &lt;1&gt;</title>"));
+        assert!(html.contains(&format!("<style>\n{}\n</style>", get_html_stylesheet(&options))));
+        assert!(html.ends_with(">Hello</pre>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn errors_should_be_kept() {
+        let lines = vec![
+            Ok(create_line(vec![])),
+            Err(Error::InvalidLineNumber(0)),
+        ];
+
+        let result = lines_to_html(lines.into_iter(), HtmlOptions::default()).collect::<Result<String, Error>>();
+
+        assert!(result.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 pub mod ansi_sequence_helpers;
 pub mod colors;
 pub mod constants;
+pub mod hyperlink;
 pub mod style;
+pub mod theme;
 pub mod types;
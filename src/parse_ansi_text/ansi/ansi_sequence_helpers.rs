@@ -1,16 +1,29 @@
 // use ansi_parser::AnsiSequence;
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::colors::*;
+use crate::parse_ansi_text::ansi::hyperlink::{get_hyperlink_from_command, Hyperlink};
 use crate::parse_ansi_text::ansi::style::*;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::parsers::ESCAPE_AS_BYTES;
-use crate::parse_ansi_text::raw_ansi_parse::AnsiSequence;
+use crate::parse_ansi_text::raw_ansi_parse::control_sequence::graphics_mode_parameters_to_string;
+use crate::parse_ansi_text::raw_ansi_parse::{AnsiSequence, GraphicsModeParameter};
 
 pub enum AnsiSequenceType {
-    Unsupported,
     Reset,
     ForegroundColor(Color),
     BackgroundColor(Color),
+    UnderlineColor(Color),
     Brightness(Brightness),
     TextStyle(TextStyle),
+
+    // Replace the current underline style (only one underline style can be applied at a time)
+    UnderlineStyle(UnderlineStyle),
+
+    // Turn off single text style without affecting the rest of the styles
+    RemoveTextStyle(TextStyle),
+
+    // None is the end of the link
+    Hyperlink(Option<Hyperlink>),
 }
 
 pub fn old_ansi_sequence_to_new<'a>(seq: ansi_parser::AnsiSequence) -> AnsiSequence<'a> {
@@ -25,7 +38,9 @@ pub fn old_ansi_sequence_to_new<'a>(seq: ansi_parser::AnsiSequence) -> AnsiSeque
         ansi_parser::AnsiSequence::CursorRestore => AnsiSequence::CursorRestore,
         ansi_parser::AnsiSequence::EraseDisplay => AnsiSequence::EraseDisplay,
         ansi_parser::AnsiSequence::EraseLine => AnsiSequence::EraseLine,
-        ansi_parser::AnsiSequence::SetGraphicsMode(a) => AnsiSequence::SetGraphicsMode(a),
+        ansi_parser::AnsiSequence::SetGraphicsMode(a) => {
+            AnsiSequence::SetGraphicsMode(a.iter().map(|value| (*value).into()).collect())
+        }
         ansi_parser::AnsiSequence::SetMode(a) => AnsiSequence::SetMode(a),
         ansi_parser::AnsiSequence::ResetMode(a) => AnsiSequence::ResetMode(a),
         ansi_parser::AnsiSequence::HideCursor => AnsiSequence::HideCursor,
@@ -67,65 +82,113 @@ pub fn old_ansi_sequence_to_new<'a>(seq: ansi_parser::AnsiSequence) -> AnsiSeque
     }
 }
 
-pub fn get_type_from_ansi_sequence(seq: &AnsiSequence) -> AnsiSequenceType {
+// Each graphics mode parameter is a separate style operation that should be applied in order,
+// e.g. `\x1B[0;1;31m` is reset, bold and red foreground
+//
+// Unsupported or invalid parameters are returned as errors without affecting the rest of the parameters,
+// unsupported sequences are only returned when the options report them as formatting them is expensive
+pub fn get_type_from_ansi_sequence(seq: &AnsiSequence, options: &ParseOptions) -> Vec<Result<AnsiSequenceType, Error>> {
     if !is_ansi_sequence_code_supported(&seq) {
-        println!("Unsupported ansi sequence: {:?}", seq);
-        return AnsiSequenceType::Unsupported;
+        return get_unsupported_sequence_error(seq, options);
     }
 
-    // println!("Supported Ansi sequence: {:?}", seq);
-
-    // Instead of match as we only support single, change to if not set graphics mode panic
-
     match seq {
         // TODO - what it means?
         AnsiSequence::SetGraphicsMode(vec) => {
-            // println!("SetGraphicsMode: {:?}", vec);
-
+            // No parameters is the same as reset (\x1B[m is the same as \x1B[0m)
             if vec.len() == 0 {
-                println!("Unrecognized graphics mode: {:?}", vec);
-                return AnsiSequenceType::Unsupported;
+                return vec![Ok(AnsiSequenceType::Reset)];
             }
 
-            if vec[0] == 0 {
-                return AnsiSequenceType::Reset;
-            }
+            let mut sequence_types = vec![];
+            let mut index = 0;
 
-            let color_type = get_color_type(vec);
-
-            // TODO - should replace here default with none color?
-            match color_type {
-                ColorType::Foreground(color) => {
-                    return AnsiSequenceType::ForegroundColor(color);
-                }
-                ColorType::Background(color) => {
-                    return AnsiSequenceType::BackgroundColor(color);
-                }
-                _ => {}
-            }
+            while index < vec.len() {
+                // Extended colors (e.g. \x1B[38;2;R;G;Bm) use the next parameters as well
+                let parameters_length = get_color_parameters_length(&vec[index..]);
 
-            let brightness = get_brightness_type(vec[0]);
+                sequence_types.push(get_type_from_graphics_mode_parameters(
+                    &vec[index..index + parameters_length],
+                ));
 
-            if brightness != Brightness::None {
-                return AnsiSequenceType::Brightness(brightness);
+                index += parameters_length;
             }
 
-            let style = get_text_style_type(vec[0]);
-
-            if style != TextStyle::None {
-                return AnsiSequenceType::TextStyle(style);
-            }
+            return sequence_types;
+        }
 
-            println!("Unrecognized graphics mode: {:?}", vec);
+        AnsiSequence::OperatingSystemCommand(command) => {
+            return match command.hyperlink() {
+                Some(hyperlink) => vec![Ok(AnsiSequenceType::Hyperlink(get_hyperlink_from_command(&hyperlink)))],
+                None => get_unsupported_sequence_error(seq, options),
+            };
         }
 
         _ => {
             // Should not be here
-            panic!("supported ANSI sequence have no handling: {:?}", seq);
+            return get_unsupported_sequence_error(seq, options);
+        }
+    }
+}
+
+fn get_unsupported_sequence_error(seq: &AnsiSequence, options: &ParseOptions) -> Vec<Result<AnsiSequenceType, Error>> {
+    // Unsupported sequences don't stop the parsing in any mode, so they are ignored when they are not reported
+    if !options.reports_anomalies() {
+        return vec![];
+    }
+
+    return vec![Err(Error::UnsupportedSequence(format!("{:?}", seq)))];
+}
+
+fn get_type_from_graphics_mode_parameters(vec: &[GraphicsModeParameter]) -> Result<AnsiSequenceType, Error> {
+    if vec[0].value == 0 {
+        return Ok(AnsiSequenceType::Reset);
+    }
+
+    let color_type = get_color_type(vec)?;
+
+    // TODO - should replace here default with none color?
+    match color_type {
+        ColorType::Foreground(color) => {
+            return Ok(AnsiSequenceType::ForegroundColor(color));
+        }
+        ColorType::Background(color) => {
+            return Ok(AnsiSequenceType::BackgroundColor(color));
+        }
+        ColorType::Underline(color) => {
+            return Ok(AnsiSequenceType::UnderlineColor(color));
         }
+        _ => {}
+    }
+
+    // Normal intensity turn off both bold and dim
+    if is_normal_intensity(vec[0].value) {
+        return Ok(AnsiSequenceType::Brightness(Brightness::None));
+    }
+
+    let brightness = get_brightness_type(vec[0].value);
+
+    if brightness != Brightness::None {
+        return Ok(AnsiSequenceType::Brightness(brightness));
     }
 
-    return AnsiSequenceType::Unsupported;
+    if let Some(underline_style) = get_underline_style_type(&vec[0]) {
+        return Ok(AnsiSequenceType::UnderlineStyle(underline_style));
+    }
+
+    let style = get_text_style_type(&vec[0]);
+
+    if style != TextStyle::None {
+        return Ok(AnsiSequenceType::TextStyle(style));
+    }
+
+    let style_to_remove = get_text_style_to_remove_type(&vec[0]);
+
+    if style_to_remove != TextStyle::None {
+        return Ok(AnsiSequenceType::RemoveTextStyle(style_to_remove));
+    }
+
+    return Err(Error::UnsupportedGraphicsMode(graphics_mode_parameters_to_string(vec)));
 }
 
 pub fn is_ansi_sequence_code_supported(seq: &AnsiSequence) -> bool {
@@ -195,6 +258,15 @@ pub fn is_ansi_sequence_code_supported(seq: &AnsiSequence) -> bool {
         AnsiSequence::SetSingleShift2 => false,
         AnsiSequence::SetSingleShift3 => false,
         AnsiSequence::SetTopAndBottom(_, _) => false,
+        AnsiSequence::ControlSequence(_) => false,
+
+        // Only hyperlinks affect the spans
+        AnsiSequence::OperatingSystemCommand(command) => command.hyperlink().is_some(),
+
+        AnsiSequence::DeviceControlString(_) => false,
+        AnsiSequence::StartOfString(_) => false,
+        AnsiSequence::PrivacyMessage(_) => false,
+        AnsiSequence::ApplicationProgramCommand(_) => false,
     };
 
     return supported;
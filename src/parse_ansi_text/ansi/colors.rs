@@ -1,4 +1,6 @@
-use heapless::Vec;
+use crate::error::Error;
+use crate::parse_ansi_text::raw_ansi_parse::control_sequence::graphics_mode_parameters_to_string;
+use crate::parse_ansi_text::raw_ansi_parse::GraphicsModeParameter;
 
 pub const BLACK_FOREGROUND_CODE: &str = "\x1B[30m";
 pub const BLACK_BACKGROUND_CODE: &str = "\x1B[40m";
@@ -70,16 +72,29 @@ pub fn RGB_BACKGROUND_CODE(r: u8, g: u8, b: u8) -> String {
     format!("\x1B[48;2;{};{};{}m", r, g, b)
 }
 
+#[allow(non_snake_case)]
+pub fn EIGHT_BIT_UNDERLINE_COLOR_CODE(byte: u8) -> String {
+    format!("\x1B[58;5;{}m", byte)
+}
+
+#[allow(non_snake_case)]
+pub fn RGB_UNDERLINE_COLOR_CODE(r: u8, g: u8, b: u8) -> String {
+    format!("\x1B[58;2;{};{};{}m", r, g, b)
+}
+
 pub const LARGEST_RGB_FOREGROUND_CODE: &str = "\x1B[38;2;255;255;255m";
 pub const LARGEST_RGB_BACKGROUND_CODE: &str = "\x1B[48;2;255;255;255m";
+pub const LARGEST_RGB_UNDERLINE_COLOR_CODE: &str = "\x1B[58;2;255;255;255m";
 
 pub const DEFAULT_FOREGROUND_CODE: &str = "\x1B[39m";
 pub const DEFAULT_BACKGROUND_CODE: &str = "\x1B[49m";
+pub const DEFAULT_UNDERLINE_COLOR_CODE: &str = "\x1B[59m";
 
 pub enum ColorType {
     None,
     Foreground(Color),
     Background(Color),
+    Underline(Color),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
@@ -164,12 +179,12 @@ pub enum Color {
     Default,
 }
 
-pub fn get_color_type(vec: &Vec<u8, 5>) -> ColorType {
+pub fn get_color_type(vec: &[GraphicsModeParameter]) -> Result<ColorType, Error> {
     if vec.len() == 0 {
-        return ColorType::None;
+        return Ok(ColorType::None);
     }
 
-    let code = vec[0];
+    let code = vec[0].value;
 
     // 3 bit color
     if code >= 30 && code <= 49 {
@@ -185,83 +200,112 @@ pub fn get_color_type(vec: &Vec<u8, 5>) -> ColorType {
             5 => Color::Magenta,
             6 => Color::Cyan,
             7 => Color::White,
-            8 => {
-                if vec.len() < 2 {
-                    panic!("Invalid Color code {:?}", vec);
-                }
-
-                let color_type = vec[1];
-
-                match color_type {
-                    // RGB
-                    2 => {
-                        let color: Color;
-                        if vec.len() < 5 {
-                            println!("Invalid RGB color code: {:?}", vec);
-                            color = Color::None;
-                        } else {
-                            color = Color::Rgb(vec[2], vec[3], vec[4]);
-                        }
-
-                        color
-                    },
-                    // 8-bit color
-                    5 => {
-                        let color: Color;
-
-                        if vec.len() < 3 {
-                            println!("Invalid 8bit color code: {:?}", vec);
-                            color = Color::None;
-                        } else {
-                            let (r, g, b) = get_rgb_values_from_8_bit(vec[2]);
-                            color = Color::Rgb(r, g, b);
-                        }
-
-                        color
-                    },
-                    _ => panic!("Unknown color code {}, it should be either 2 for 8bit color or 5 for RGB color. vec is: {:?}", color_type, vec)
-                }
-            }
-            9 => Color::Default,
-            _ => panic!("Invalid color code: {:?}", vec),
+            8 => get_extended_color(vec)?,
+            // 9
+            _ => Color::Default,
         };
 
         if type_color_digit == 3 {
-            return ColorType::Foreground(color);
+            return Ok(ColorType::Foreground(color));
         }
 
-        if type_color_digit == 4 {
-            return ColorType::Background(color);
-        }
+        return Ok(ColorType::Background(color));
     }
 
-    // 4 bit color
-    if code >= 90 && code <= 107 {
-        let code_color_digit = code % 10;
-        let type_color_digit = code / 10;
+    // Underline color, only extended colors are supported (\x1B[58;5;nm or \x1B[58;2;r;g;bm)
+    if code == 58 {
+        return Ok(ColorType::Underline(get_extended_color(vec)?));
+    }
 
-        let color = match code_color_digit {
-            0 => Color::BrightBlack,
-            1 => Color::BrightRed,
-            2 => Color::BrightGreen,
-            3 => Color::BrightYellow,
-            4 => Color::BrightBlue,
-            5 => Color::BrightMagenta,
-            6 => Color::BrightCyan,
-            7 => Color::BrightWhite,
-            _ => panic!("Invalid color code: {:?}", vec),
-        };
+    if code == 59 {
+        return Ok(ColorType::Underline(Color::Default));
+    }
 
-        if type_color_digit == 9 {
-            return ColorType::Foreground(color);
-        }
+    // 4 bit color (98, 99, 108 and 109 are not colors)
+    let color = match code {
+        90 | 100 => Color::BrightBlack,
+        91 | 101 => Color::BrightRed,
+        92 | 102 => Color::BrightGreen,
+        93 | 103 => Color::BrightYellow,
+        94 | 104 => Color::BrightBlue,
+        95 | 105 => Color::BrightMagenta,
+        96 | 106 => Color::BrightCyan,
+        97 | 107 => Color::BrightWhite,
+        _ => return Ok(ColorType::None),
+    };
 
-        if type_color_digit == 10 {
-            return ColorType::Background(color);
-        }
+    if code < 100 {
+        return Ok(ColorType::Foreground(color));
+    }
+
+    return Ok(ColorType::Background(color));
+}
+
+// Extended color (`38`, `48` and `58`) is either 8-bit color (`38;5;n`) or RGB color (`38;2;r;g;b`)
+fn get_extended_color(vec: &[GraphicsModeParameter]) -> Result<Color, Error> {
+    let arguments = get_extended_color_arguments(vec);
+
+    let invalid_color = || Error::InvalidColor(graphics_mode_parameters_to_string(vec));
+
+    return match arguments.first() {
+        // RGB
+        Some(2) => match arguments.get(1..4) {
+            Some(rgb) => Ok(Color::Rgb(rgb[0], rgb[1], rgb[2])),
+            None => Err(invalid_color()),
+        },
+        // 8-bit color, kept as the index so the first 16 colors are resolved from the theme
+        Some(5) => match arguments.get(1) {
+            Some(index) => Ok(Color::EightBit(*index)),
+            None => Err(invalid_color()),
+        },
+        // Missing or unknown color space
+        _ => Err(invalid_color()),
+    };
+}
+
+// How many parameters the color at the start of the parameters use
+// 1 for everything except for extended colors in the semicolon form (e.g. 5 for `38;2;255;0;0`)
+pub fn get_color_parameters_length(vec: &[GraphicsModeParameter]) -> usize {
+    let code = vec[0].value;
+
+    if !matches!(code, 38 | 48 | 58) || !vec[0].sub_parameters.is_empty() {
+        return 1;
     }
 
-    return ColorType::None;
+    let length = match vec.get(1).map(|param| param.value) {
+        // RGB
+        Some(2) => 5,
+        // 8-bit color
+        Some(5) => 3,
+        _ => 1,
+    };
+
+    return length.min(vec.len());
+}
+
+// Get the arguments of extended color (the values after 38 or 48) as if they were semicolon separated
+// `38;2;255;0;0`, `38:2::255:0:0` and `38:2:255:0:0` all return [2, 255, 0, 0]
+fn get_extended_color_arguments(vec: &[GraphicsModeParameter]) -> Vec<u8> {
+    let sub_parameters = &vec[0].sub_parameters;
+
+    // Semicolon form, the arguments are the next parameters
+    if sub_parameters.is_empty() {
+        return vec[1..].iter().map(|param| param.value).collect();
+    }
+
+    // Empty sub parameter is the same as 0
+    let mut arguments: Vec<u8> = sub_parameters
+        .iter()
+        .map(|sub_parameter| sub_parameter.unwrap_or(0))
+        .collect();
+
+    // In ITU T.416 the RGB values come after color space id (`38:2:<color-space-id>:r:g:b`),
+    // but it's common to omit it (`38:2:r:g:b`)
+    if arguments[0] == 2 && arguments.len() > 4 {
+        arguments.remove(1);
+    }
+
+    return arguments;
 }
 
 pub fn get_rgb_values_from_8_bit(eight_bit_color: u8) -> (u8, u8, u8) {
@@ -835,7 +879,61 @@ pub fn convert_color_type_to_ansi_code(color_type: ColorType) -> String {
             Color::EightBit(b) => EIGHT_BIT_BACKGROUND_CODE(b),
             Color::Rgb(r, g, b) => RGB_BACKGROUND_CODE(r, g, b),
         },
+        // There are no 3 and 4 bit underline color codes, so they use their 8-bit color index
+        ColorType::Underline(color) => match color {
+            Color::None => "".to_string(),
+            Color::Default => DEFAULT_UNDERLINE_COLOR_CODE.to_string(),
+
+            Color::Black => EIGHT_BIT_UNDERLINE_COLOR_CODE(0),
+            Color::Red => EIGHT_BIT_UNDERLINE_COLOR_CODE(1),
+            Color::Green => EIGHT_BIT_UNDERLINE_COLOR_CODE(2),
+            Color::Yellow => EIGHT_BIT_UNDERLINE_COLOR_CODE(3),
+            Color::Blue => EIGHT_BIT_UNDERLINE_COLOR_CODE(4),
+            Color::Magenta => EIGHT_BIT_UNDERLINE_COLOR_CODE(5),
+            Color::Cyan => EIGHT_BIT_UNDERLINE_COLOR_CODE(6),
+            Color::White => EIGHT_BIT_UNDERLINE_COLOR_CODE(7),
+
+            Color::BrightBlack => EIGHT_BIT_UNDERLINE_COLOR_CODE(8),
+            Color::BrightRed => EIGHT_BIT_UNDERLINE_COLOR_CODE(9),
+            Color::BrightGreen => EIGHT_BIT_UNDERLINE_COLOR_CODE(10),
+            Color::BrightYellow => EIGHT_BIT_UNDERLINE_COLOR_CODE(11),
+            Color::BrightBlue => EIGHT_BIT_UNDERLINE_COLOR_CODE(12),
+            Color::BrightMagenta => EIGHT_BIT_UNDERLINE_COLOR_CODE(13),
+            Color::BrightCyan => EIGHT_BIT_UNDERLINE_COLOR_CODE(14),
+            Color::BrightWhite => EIGHT_BIT_UNDERLINE_COLOR_CODE(15),
+
+            Color::EightBit(b) => EIGHT_BIT_UNDERLINE_COLOR_CODE(b),
+            Color::Rgb(r, g, b) => RGB_UNDERLINE_COLOR_CODE(r, g, b),
+        },
     };
 
     return ansi_code.to_string();
 }
+
+// Index of the color in the 16 colors palette, None for colors that are not part of it
+pub fn get_palette_index(color: Color) -> Option<usize> {
+    return match color {
+        Color::Black => Some(0),
+        Color::Red => Some(1),
+        Color::Green => Some(2),
+        Color::Yellow => Some(3),
+        Color::Blue => Some(4),
+        Color::Magenta => Some(5),
+        Color::Cyan => Some(6),
+        Color::White => Some(7),
+
+        Color::BrightBlack => Some(8),
+        Color::BrightRed => Some(9),
+        Color::BrightGreen => Some(10),
+        Color::BrightYellow => Some(11),
+        Color::BrightBlue => Some(12),
+        Color::BrightMagenta => Some(13),
+        Color::BrightCyan => Some(14),
+        Color::BrightWhite => Some(15),
+
+        // The first 16 8-bit colors are the same as the 3 and 4 bit colors
+        Color::EightBit(eight_bit) if eight_bit < 16 => Some(eight_bit as usize),
+
+        _ => None,
+    };
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::parse_ansi_text::raw_ansi_parse::operating_system_command::HyperlinkCommand;
+
+// The hyperlink the text is part of (OSC 8)
+#[derive(PartialEq, Eq, Clone, Hash)]
+pub struct Hyperlink {
+    pub uri: Vec<u8>,
+
+    // `:` separated `key=value` pairs, e.g. `id=1`
+    pub params: Vec<u8>,
+}
+
+impl fmt::Debug for Hyperlink {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Hyperlink")
+            .field("uri", &String::from_utf8_lossy(&self.uri))
+            .field("params", &String::from_utf8_lossy(&self.params))
+            .finish()
+    }
+}
+
+impl Hyperlink {
+    pub fn new(uri: Vec<u8>) -> Hyperlink {
+        Hyperlink {
+            uri,
+            params: vec![],
+        }
+    }
+
+    pub fn with_params(mut self, params: Vec<u8>) -> Hyperlink {
+        self.params = params;
+        self
+    }
+}
+
+// Return None when the command is the end of the link
+pub fn get_hyperlink_from_command(command: &HyperlinkCommand) -> Option<Hyperlink> {
+    if command.is_end() {
+        return None;
+    }
+
+    return Some(Hyperlink::new(command.uri.to_vec()).with_params(command.params.to_vec()));
+}
+
+#[allow(non_snake_case)]
+pub fn HYPERLINK_START_CODE(hyperlink: &Hyperlink) -> Vec<u8> {
+    // \x1B]8;<params>;<uri>\x1B\
+    return [
+        b"\x1B]8;".as_slice(),
+        hyperlink.params.as_slice(),
+        b";",
+        hyperlink.uri.as_slice(),
+        b"\x1B\\",
+    ]
+    .concat();
+}
+
+pub const HYPERLINK_END_CODE: &str = "\x1B]8;;\x1B\\";
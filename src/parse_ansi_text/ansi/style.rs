@@ -1,20 +1,110 @@
+use crate::parse_ansi_text::raw_ansi_parse::GraphicsModeParameter;
+
 bitflags::bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct TextStyle: u8 {
+    pub struct TextStyle: u16 {
 
-        const None = 0b00000000;
+        const None = 0b00000000_00000000;
 
         // \x1B[3m
-        const Italic = 0b00000001;
+        const Italic = 0b00000000_00000001;
 
-        // \x1B[4m
-        const Underline = 0b00000010;
+        // \x1B[4m or \x1B[4:1m
+        const Underline = 0b00000000_00000010;
 
         // \x1B[7m code
-        const Inverse = 0b00000100;
+        const Inverse = 0b00000000_00000100;
 
         // \x1B[9m
-        const Strikethrough = 0b00001000;
+        const Strikethrough = 0b00000000_00001000;
+
+        // \x1B[5m
+        const SlowBlink = 0b00000000_00010000;
+
+        // \x1B[6m
+        const RapidBlink = 0b00000000_00100000;
+
+        // \x1B[8m
+        // also known as conceal
+        const Hidden = 0b00000000_01000000;
+
+        // \x1B[53m
+        const Overline = 0b00000000_10000000;
+
+        // \x1B[21m or \x1B[4:2m
+        const DoubleUnderline = 0b00000001_00000000;
+
+        // \x1B[4:3m
+        const CurlyUnderline = 0b00000010_00000000;
+
+        // \x1B[4:4m
+        const DottedUnderline = 0b00000100_00000000;
+
+        // \x1B[4:5m
+        const DashedUnderline = 0b00001000_00000000;
+
+        // \x1B[73m
+        const Superscript = 0b00010000_00000000;
+
+        // \x1B[74m
+        const Subscript = 0b00100000_00000000;
+    }
+}
+
+impl TextStyle {
+    // Only one of the underline styles can be applied at a time
+    pub const ANY_UNDERLINE: TextStyle = TextStyle::Underline
+        .union(TextStyle::DoubleUnderline)
+        .union(TextStyle::CurlyUnderline)
+        .union(TextStyle::DottedUnderline)
+        .union(TextStyle::DashedUnderline);
+
+    pub const ANY_BLINK: TextStyle = TextStyle::SlowBlink.union(TextStyle::RapidBlink);
+
+    pub const ANY_VERTICAL_ALIGNMENT: TextStyle = TextStyle::Superscript.union(TextStyle::Subscript);
+
+    // Add the style, the blink speeds replace each other and so do superscript and subscript
+    pub fn with_added_style(&self, style: TextStyle) -> TextStyle {
+        let mut text_style = *self;
+
+        if style.intersects(TextStyle::ANY_BLINK) {
+            text_style = text_style.difference(TextStyle::ANY_BLINK);
+        }
+
+        if style.intersects(TextStyle::ANY_VERTICAL_ALIGNMENT) {
+            text_style = text_style.difference(TextStyle::ANY_VERTICAL_ALIGNMENT);
+        }
+
+        return text_style | style;
+    }
+
+    // Replace the current underline style with the new one
+    pub fn with_underline_style(&self, underline_style: UnderlineStyle) -> TextStyle {
+        return self.difference(TextStyle::ANY_UNDERLINE) | underline_style.to_text_style();
+    }
+
+    pub fn get_underline_style(&self) -> UnderlineStyle {
+        if self.contains(TextStyle::DoubleUnderline) {
+            return UnderlineStyle::Double;
+        }
+
+        if self.contains(TextStyle::CurlyUnderline) {
+            return UnderlineStyle::Curly;
+        }
+
+        if self.contains(TextStyle::DottedUnderline) {
+            return UnderlineStyle::Dotted;
+        }
+
+        if self.contains(TextStyle::DashedUnderline) {
+            return UnderlineStyle::Dashed;
+        }
+
+        if self.contains(TextStyle::Underline) {
+            return UnderlineStyle::Single;
+        }
+
+        return UnderlineStyle::None;
     }
 }
 
@@ -32,6 +122,47 @@ pub enum Brightness {
     Dim,
 }
 
+// The underline style is stored in the text style flags, this is for setting a single underline style
+// as each underline style replace the previous one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnderlineStyle {
+    // \x1B[24m or \x1B[4:0m
+    None,
+
+    // \x1B[4m or \x1B[4:1m
+    Single,
+
+    // \x1B[21m or \x1B[4:2m
+    Double,
+
+    // \x1B[4:3m
+    Curly,
+
+    // \x1B[4:4m
+    Dotted,
+
+    // \x1B[4:5m
+    Dashed,
+}
+
+impl UnderlineStyle {
+    pub fn to_text_style(&self) -> TextStyle {
+        return match self {
+            UnderlineStyle::None => TextStyle::None,
+            UnderlineStyle::Single => TextStyle::Underline,
+            UnderlineStyle::Double => TextStyle::DoubleUnderline,
+            UnderlineStyle::Curly => TextStyle::CurlyUnderline,
+            UnderlineStyle::Dotted => TextStyle::DottedUnderline,
+            UnderlineStyle::Dashed => TextStyle::DashedUnderline,
+        };
+    }
+}
+
+// \x1B[22m - neither bold nor dim
+pub fn is_normal_intensity(code: u8) -> bool {
+    return code == 22;
+}
+
 pub fn get_brightness_type(code: u8) -> Brightness {
     return match code {
         1 => Brightness::Bold,
@@ -40,12 +171,51 @@ pub fn get_brightness_type(code: u8) -> Brightness {
     };
 }
 
-pub fn get_text_style_type(code: u8) -> TextStyle {
-    return match code {
+// Underline codes return the underline style to set, replacing the previous one
+pub fn get_underline_style_type(param: &GraphicsModeParameter) -> Option<UnderlineStyle> {
+    return match param.value {
+        // The first sub parameter is the underline style (`4:3` is curly underline)
+        4 => match param.sub_parameters.first() {
+            None | Some(None) => Some(UnderlineStyle::Single),
+            Some(Some(0)) => Some(UnderlineStyle::None),
+            Some(Some(1)) => Some(UnderlineStyle::Single),
+            Some(Some(2)) => Some(UnderlineStyle::Double),
+            Some(Some(3)) => Some(UnderlineStyle::Curly),
+            Some(Some(4)) => Some(UnderlineStyle::Dotted),
+            Some(Some(5)) => Some(UnderlineStyle::Dashed),
+            _ => None,
+        },
+        21 => Some(UnderlineStyle::Double),
+        24 => Some(UnderlineStyle::None),
+        _ => None,
+    };
+}
+
+pub fn get_text_style_type(param: &GraphicsModeParameter) -> TextStyle {
+    return match param.value {
         3 => TextStyle::Italic,
-        4 => TextStyle::Underline,
+        5 => TextStyle::SlowBlink,
+        6 => TextStyle::RapidBlink,
         7 => TextStyle::Inverse,
+        8 => TextStyle::Hidden,
         9 => TextStyle::Strikethrough,
+        53 => TextStyle::Overline,
+        73 => TextStyle::Superscript,
+        74 => TextStyle::Subscript,
+        _ => TextStyle::None,
+    };
+}
+
+// The text style that the code turn off, for example \x1B[23m turn off italic
+pub fn get_text_style_to_remove_type(param: &GraphicsModeParameter) -> TextStyle {
+    return match param.value {
+        23 => TextStyle::Italic,
+        25 => TextStyle::ANY_BLINK,
+        27 => TextStyle::Inverse,
+        28 => TextStyle::Hidden,
+        29 => TextStyle::Strikethrough,
+        55 => TextStyle::Overline,
+        75 => TextStyle::ANY_VERTICAL_ALIGNMENT,
         _ => TextStyle::None,
     };
 }
@@ -58,8 +228,47 @@ pub const UNDERLINE_CODE: &str = "\x1B[4m";
 pub const INVERSE_CODE: &str = "\x1B[7m";
 #[allow(dead_code)]
 pub const STRIKETHROUGH_CODE: &str = "\x1B[9m";
+#[allow(dead_code)]
+pub const SLOW_BLINK_CODE: &str = "\x1B[5m";
+#[allow(dead_code)]
+pub const RAPID_BLINK_CODE: &str = "\x1B[6m";
+#[allow(dead_code)]
+pub const HIDDEN_CODE: &str = "\x1B[8m";
+#[allow(dead_code)]
+pub const OVERLINE_CODE: &str = "\x1B[53m";
+#[allow(dead_code)]
+pub const DOUBLE_UNDERLINE_CODE: &str = "\x1B[21m";
+#[allow(dead_code)]
+pub const CURLY_UNDERLINE_CODE: &str = "\x1B[4:3m";
+#[allow(dead_code)]
+pub const DOTTED_UNDERLINE_CODE: &str = "\x1B[4:4m";
+#[allow(dead_code)]
+pub const DASHED_UNDERLINE_CODE: &str = "\x1B[4:5m";
+#[allow(dead_code)]
+pub const SUPERSCRIPT_CODE: &str = "\x1B[73m";
+#[allow(dead_code)]
+pub const SUBSCRIPT_CODE: &str = "\x1B[74m";
 
 #[allow(dead_code)]
 pub const BOLD_CODE: &str = "\x1B[1m";
 #[allow(dead_code)]
 pub const DIM_CODE: &str = "\x1B[2m";
+
+#[allow(dead_code)]
+pub const NORMAL_INTENSITY_CODE: &str = "\x1B[22m";
+#[allow(dead_code)]
+pub const NOT_ITALIC_CODE: &str = "\x1B[23m";
+#[allow(dead_code)]
+pub const NOT_UNDERLINED_CODE: &str = "\x1B[24m";
+#[allow(dead_code)]
+pub const NOT_BLINKING_CODE: &str = "\x1B[25m";
+#[allow(dead_code)]
+pub const NOT_INVERSE_CODE: &str = "\x1B[27m";
+#[allow(dead_code)]
+pub const NOT_HIDDEN_CODE: &str = "\x1B[28m";
+#[allow(dead_code)]
+pub const NOT_STRIKETHROUGH_CODE: &str = "\x1B[29m";
+#[allow(dead_code)]
+pub const NOT_OVERLINED_CODE: &str = "\x1B[55m";
+#[allow(dead_code)]
+pub const NOT_SUPERSCRIPT_OR_SUBSCRIPT_CODE: &str = "\x1B[75m";
@@ -0,0 +1,225 @@
+use crate::parse_ansi_text::ansi::colors::{get_palette_index, get_rgb_values_from_8_bit, Color};
+
+pub type Rgb = (u8, u8, u8);
+
+// The colors that terminals let the user change, used to turn the parsed colors into actual colors (e.g. in CSS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    // The 16 colors in the order of the 8-bit colors (black to white and then the bright colors)
+    pub colors: [Rgb; 16],
+
+    // Used when the text has no color
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+pub const XTERM_THEME: Theme = Theme {
+    colors: [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ],
+    foreground: (0, 0, 0),
+    background: (255, 255, 255),
+};
+
+// The default dark theme of the VS Code terminal
+pub const VSCODE_THEME: Theme = Theme {
+    colors: [
+        (0, 0, 0),
+        (205, 49, 49),
+        (13, 188, 121),
+        (229, 229, 16),
+        (36, 114, 200),
+        (188, 63, 188),
+        (17, 168, 205),
+        (229, 229, 229),
+        (102, 102, 102),
+        (241, 76, 76),
+        (35, 209, 139),
+        (245, 245, 67),
+        (59, 142, 234),
+        (214, 112, 214),
+        (41, 184, 219),
+        (229, 229, 229),
+    ],
+    foreground: (204, 204, 204),
+    background: (30, 30, 30),
+};
+
+// Solarized dark, the bright colors are the base tones as in the original terminal themes
+pub const SOLARIZED_THEME: Theme = Theme {
+    colors: [
+        (7, 54, 66),
+        (220, 50, 47),
+        (133, 153, 0),
+        (181, 137, 0),
+        (38, 139, 210),
+        (211, 54, 130),
+        (42, 161, 152),
+        (238, 232, 213),
+        (0, 43, 54),
+        (203, 75, 22),
+        (88, 110, 117),
+        (101, 123, 131),
+        (131, 148, 150),
+        (108, 113, 196),
+        (147, 161, 161),
+        (253, 246, 227),
+    ],
+    foreground: (131, 148, 150),
+    background: (0, 43, 54),
+};
+
+impl Default for Theme {
+    fn default() -> Theme {
+        return XTERM_THEME;
+    }
+}
+
+impl Theme {
+    // Built-in theme by its name
+    pub fn from_name(name: &str) -> Option<Theme> {
+        return match name {
+            "xterm" => Some(XTERM_THEME),
+            "vscode" => Some(VSCODE_THEME),
+            "solarized" => Some(SOLARIZED_THEME),
+            _ => None,
+        };
+    }
+
+    // Comma separated hex colors (e.g. `#000000,#cd0000,...`), the 16 colors optionally followed by the foreground and background,
+    // the foreground and background of the default theme are used when missing, None when the theme is invalid
+    pub fn parse(theme: &str) -> Option<Theme> {
+        let colors = theme
+            .split(',')
+            .map(|color| parse_hex_color(color.trim()))
+            .collect::<Option<Vec<Rgb>>>()?;
+
+        let default_theme = Theme::default();
+
+        let (foreground, background) = match colors.len() {
+            16 => (default_theme.foreground, default_theme.background),
+            18 => (colors[16], colors[17]),
+            _ => return None,
+        };
+
+        return Some(Theme {
+            colors: colors[..16].try_into().ok()?,
+            foreground,
+            background,
+        });
+    }
+
+    // None for the default color and when there is no color, the foreground or background should be used instead
+    pub fn get_rgb(&self, color: Color) -> Option<Rgb> {
+        if let Some(index) = get_palette_index(color) {
+            return Some(self.colors[index]);
+        }
+
+        return match color {
+            Color::EightBit(eight_bit) => Some(get_rgb_values_from_8_bit(eight_bit)),
+            Color::Rgb(r, g, b) => Some((r, g, b)),
+            _ => None,
+        };
+    }
+}
+
+// Dim text is half way between its color and the background it's on, like terminals do
+pub fn get_dim_color(color: Rgb, background: Rgb) -> Rgb {
+    let blend = |channel: u8, background_channel: u8| ((channel as u16 + background_channel as u16) / 2) as u8;
+
+    return (
+        blend(color.0, background.0),
+        blend(color.1, background.1),
+        blend(color.2, background.2),
+    );
+}
+
+pub fn get_css_color(color: Rgb) -> String {
+    return format!("rgb({}, {}, {})", color.0, color.1, color.2);
+}
+
+fn parse_hex_color(color: &str) -> Option<Rgb> {
+    let hex = color.strip_prefix('#')?;
+
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let parse_channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+
+    return Some((parse_channel(0)?, parse_channel(2)?, parse_channel(4)?));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_theme_should_have_16_hex_colors() {
+        let theme = Theme::parse(
+            "#000000,#800000,#008000,#808000,#000080,#800080,#008080,#c0c0c0,\
+            #808080,#ff0000,#00ff00,#ffff00,#0000ff,#ff00ff,#00ffff, #FFFFFF",
+        )
+        .expect("valid theme");
+
+        assert_eq!(theme.colors[1], (128, 0, 0));
+        assert_eq!(theme.colors[15], (255, 255, 255));
+        assert_eq!(theme.foreground, XTERM_THEME.foreground);
+        assert_eq!(theme.background, XTERM_THEME.background);
+    }
+
+    #[test]
+    fn parse_theme_should_have_foreground_and_background_after_the_16_colors() {
+        let colors = [["#010203"; 16].join(","), "#aabbcc".to_string(), "#112233".to_string()].join(",");
+
+        let theme = Theme::parse(&colors).expect("valid theme");
+
+        assert_eq!(theme.colors, [(1, 2, 3); 16]);
+        assert_eq!(theme.foreground, (170, 187, 204));
+        assert_eq!(theme.background, (17, 34, 51));
+    }
+
+    #[test]
+    fn parse_invalid_theme_should_return_none() {
+        assert_eq!(Theme::parse("#000000,#ffffff"), None);
+        assert_eq!(Theme::parse(&["#000000"; 17].join(",")), None);
+        assert_eq!(Theme::parse(&["#00000g"; 16].join(",")), None);
+        assert_eq!(Theme::parse(&["000000"; 16].join(",")), None);
+    }
+
+    #[test]
+    fn get_rgb_should_use_the_theme_only_for_the_16_colors() {
+        let theme = Theme::from_name("solarized").expect("built-in theme");
+
+        assert_eq!(theme.get_rgb(Color::Red), Some((220, 50, 47)));
+        assert_eq!(theme.get_rgb(Color::BrightRed), Some((203, 75, 22)));
+        assert_eq!(theme.get_rgb(Color::EightBit(9)), Some((203, 75, 22)));
+        assert_eq!(theme.get_rgb(Color::EightBit(196)), Some(get_rgb_values_from_8_bit(196)));
+        assert_eq!(theme.get_rgb(Color::Rgb(1, 2, 3)), Some((1, 2, 3)));
+        assert_eq!(theme.get_rgb(Color::Default), None);
+        assert_eq!(theme.get_rgb(Color::None), None);
+    }
+
+    #[test]
+    fn dim_color_should_be_between_the_color_and_the_background() {
+        assert_eq!(get_dim_color((255, 255, 255), (0, 0, 0)), (127, 127, 127));
+        assert_eq!(get_dim_color((200, 100, 0), (100, 100, 100)), (150, 100, 50));
+    }
+}
@@ -1,10 +1,17 @@
-use crate::parse_ansi_text::ansi::colors::ColorType::{Background, Foreground};
+use crate::parse_ansi_text::ansi::colors::ColorType::{Background, Foreground, Underline};
 use crate::parse_ansi_text::ansi::colors::{
-    convert_color_type_to_ansi_code, get_rgb_values_from_8_bit, Color,
+    convert_color_type_to_ansi_code, Color,
 };
+use crate::parse_ansi_text::ansi::hyperlink::{
+    get_hyperlink_from_command, Hyperlink, HYPERLINK_END_CODE, HYPERLINK_START_CODE,
+};
+use crate::parse_ansi_text::ansi::theme::{get_css_color, get_dim_color, Theme};
+use crate::parse_ansi_text::raw_ansi_parse::operating_system_command::HyperlinkCommand;
 use crate::parse_ansi_text::ansi::style::{
-    Brightness, TextStyle, BOLD_CODE, DIM_CODE, INVERSE_CODE, ITALIC_CODE, STRIKETHROUGH_CODE,
-    UNDERLINE_CODE,
+    Brightness, TextStyle, UnderlineStyle, BOLD_CODE, CURLY_UNDERLINE_CODE, DASHED_UNDERLINE_CODE,
+    DIM_CODE, DOTTED_UNDERLINE_CODE, DOUBLE_UNDERLINE_CODE, HIDDEN_CODE, INVERSE_CODE, ITALIC_CODE,
+    OVERLINE_CODE, RAPID_BLINK_CODE, SLOW_BLINK_CODE, STRIKETHROUGH_CODE, SUBSCRIPT_CODE,
+    SUPERSCRIPT_CODE, UNDERLINE_CODE,
 };
 use std::ops::Deref;
 use std::{fmt, str};
@@ -15,8 +22,14 @@ pub struct Span {
     pub color: Color,
     pub bg_color: Color,
 
+    // Color of the underline, overline and strikethrough (\x1B[58;2;R;G;Bm)
+    pub underline_color: Color,
+
     pub brightness: Brightness,
     pub text_style: TextStyle,
+
+    // OSC 8 hyperlink, unlike the other styles it is not affected by reset (\x1B[0m)
+    pub link: Option<Hyperlink>,
 }
 
 impl fmt::Debug for Span {
@@ -25,8 +38,10 @@ impl fmt::Debug for Span {
             .field("text", &str::from_utf8(self.text.deref()).unwrap())
             .field("color", &self.color)
             .field("bg_color", &self.bg_color)
+            .field("underline_color", &self.underline_color)
             .field("brightness", &self.brightness)
             .field("text_style", &self.text_style)
+            .field("link", &self.link)
             .finish()
     }
 }
@@ -37,6 +52,8 @@ impl Span {
             text: vec![],
             color: Color::None,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text_style: TextStyle::None,
             brightness: Brightness::None,
         }
@@ -67,6 +84,16 @@ impl Span {
         self
     }
 
+    pub fn with_underline_color(mut self, underline_color: Color) -> Span {
+        // Default color is None
+        if matches!(underline_color, Color::Default) {
+            self.underline_color = Color::None;
+        } else {
+            self.underline_color = underline_color;
+        }
+        self
+    }
+
     pub fn with_brightness(mut self, brightness: Brightness) -> Span {
         self.brightness = brightness;
         self
@@ -77,62 +104,127 @@ impl Span {
         self
     }
 
+    pub fn with_link(mut self, link: Option<Hyperlink>) -> Span {
+        self.link = link;
+        self
+    }
+
     pub fn clone_without_text(span: &Span) -> Span {
         Span {
             text: vec![],
             color: span.color,
             bg_color: span.bg_color,
+            underline_color: span.underline_color,
             brightness: span.brightness,
             text_style: span.text_style,
+            link: span.link.clone(),
         }
     }
 
-    // TODO - add tests
-    pub fn create_css_string(&self) -> String {
+    // The colors are resolved from the theme so the CSS looks like the text in a terminal with that theme
+    pub fn create_css_string(&self, theme: &Theme) -> String {
         let mut css = "".to_string();
 
-        // Brightness
+        // Brightness, dim is in the colors
         if matches!(self.brightness, Brightness::Bold) {
             css = format!("{}font-weight: bold;", css);
-        } else if matches!(self.brightness, Brightness::Dim) {
-            css = format!("{}font-weight: lighter;", css);
         }
 
         // Text style
-        // TODO - support inverse
         if self.text_style & TextStyle::Italic != TextStyle::empty() {
             css = format!("{}font-style: italic;", css);
         }
-        if self.text_style & (TextStyle::Underline | TextStyle::Strikethrough)
-            == TextStyle::Underline | TextStyle::Strikethrough
-        {
-            css = format!("{}text-decoration: line-through underline;", css);
-        } else if self.text_style & TextStyle::Underline != TextStyle::empty() {
-            css = format!("{}text-decoration: underline;", css);
-        } else if self.text_style & TextStyle::Strikethrough != TextStyle::empty() {
-            css = format!("{}text-decoration: line-through;", css);
+
+        let text_decoration_lines = self.get_text_decoration_lines();
+
+        if !text_decoration_lines.is_empty() {
+            css = format!("{}text-decoration: {};", css, text_decoration_lines.join(" "));
+        }
+
+        if let Some(text_decoration_style) = self.get_text_decoration_style() {
+            css = format!("{}text-decoration-style: {};", css, text_decoration_style);
         }
 
-        // Color
-        if !matches!(self.color, Color::None) {
-            css = format!(
-                "{}color: {};",
-                css,
-                Self::get_color_str_from_color(self.color).unwrap()
-            );
+        if self.text_style & TextStyle::Superscript != TextStyle::empty() {
+            css = format!("{}vertical-align: super;", css);
+        } else if self.text_style & TextStyle::Subscript != TextStyle::empty() {
+            css = format!("{}vertical-align: sub;", css);
         }
 
-        if !matches!(self.bg_color, Color::None) {
-            css = format!(
-                "{}background-color: {};",
-                css,
-                Self::get_color_str_from_color(self.bg_color).unwrap()
+        return css + &self.create_colors_css_string(theme);
+    }
+
+    // Only the color properties of `create_css_string`, they depend on each other as inverse, dim and hidden change the text color
+    pub fn create_colors_css_string(&self, theme: &Theme) -> String {
+        let mut color = theme.get_rgb(self.color);
+        let mut bg_color = theme.get_rgb(self.bg_color);
+
+        // Text without colors is swapped as well, it's the background color on the foreground color
+        if self.text_style & TextStyle::Inverse != TextStyle::empty() {
+            (color, bg_color) = (
+                Some(bg_color.unwrap_or(theme.background)),
+                Some(color.unwrap_or(theme.foreground)),
             );
         }
 
+        if matches!(self.brightness, Brightness::Dim) {
+            color = Some(get_dim_color(
+                color.unwrap_or(theme.foreground),
+                bg_color.unwrap_or(theme.background),
+            ));
+        }
+
+        let mut css = "".to_string();
+
+        if !self.get_text_decoration_lines().is_empty() {
+            if let Some(underline_color) = theme.get_rgb(self.underline_color) {
+                css = format!("{}text-decoration-color: {};", css, get_css_color(underline_color));
+            }
+        }
+
+        // Hidden text still takes space and can be selected, like in terminals
+        if self.text_style & TextStyle::Hidden != TextStyle::empty() {
+            css = format!("{}color: transparent;", css);
+        } else if let Some(color) = color {
+            css = format!("{}color: {};", css, get_css_color(color));
+        }
+
+        if let Some(bg_color) = bg_color {
+            css = format!("{}background-color: {};", css, get_css_color(bg_color));
+        }
+
         return css;
     }
 
+    // The values of the CSS `text-decoration-line` property,
+    // blink is not one of them as browsers ignore `text-decoration: blink`
+    pub fn get_text_decoration_lines(&self) -> Vec<&'static str> {
+        let mut text_decoration_lines = vec![];
+
+        if self.text_style & TextStyle::Strikethrough != TextStyle::empty() {
+            text_decoration_lines.push("line-through");
+        }
+        if self.text_style & TextStyle::ANY_UNDERLINE != TextStyle::empty() {
+            text_decoration_lines.push("underline");
+        }
+        if self.text_style & TextStyle::Overline != TextStyle::empty() {
+            text_decoration_lines.push("overline");
+        }
+
+        return text_decoration_lines;
+    }
+
+    // The value of the CSS `text-decoration-style` property, None for the default single line
+    pub fn get_text_decoration_style(&self) -> Option<&'static str> {
+        return match self.text_style.get_underline_style() {
+            UnderlineStyle::Double => Some("double"),
+            UnderlineStyle::Curly => Some("wavy"),
+            UnderlineStyle::Dotted => Some("dotted"),
+            UnderlineStyle::Dashed => Some("dashed"),
+            UnderlineStyle::None | UnderlineStyle::Single => None,
+        };
+    }
+
     pub fn serialize_to_ansi_string(self) -> Vec<u8> {
         let mut ansi_string = vec![];
 
@@ -150,12 +242,41 @@ impl Span {
         if self.text_style & TextStyle::Italic != TextStyle::empty() {
             ansi_string = [ansi_string, ITALIC_CODE.as_bytes().to_vec()].concat();
         }
-        if self.text_style & TextStyle::Underline != TextStyle::empty() {
-            ansi_string = [ansi_string, UNDERLINE_CODE.as_bytes().to_vec()].concat();
+
+        // Only one underline style can be applied at a time
+        let underline_code = match self.text_style.get_underline_style() {
+            UnderlineStyle::None => None,
+            UnderlineStyle::Single => Some(UNDERLINE_CODE),
+            UnderlineStyle::Double => Some(DOUBLE_UNDERLINE_CODE),
+            UnderlineStyle::Curly => Some(CURLY_UNDERLINE_CODE),
+            UnderlineStyle::Dotted => Some(DOTTED_UNDERLINE_CODE),
+            UnderlineStyle::Dashed => Some(DASHED_UNDERLINE_CODE),
+        };
+        if let Some(underline_code) = underline_code {
+            ansi_string = [ansi_string, underline_code.as_bytes().to_vec()].concat();
         }
+
         if self.text_style & TextStyle::Strikethrough != TextStyle::empty() {
             ansi_string = [ansi_string, STRIKETHROUGH_CODE.as_bytes().to_vec()].concat();
         }
+        if self.text_style & TextStyle::SlowBlink != TextStyle::empty() {
+            ansi_string = [ansi_string, SLOW_BLINK_CODE.as_bytes().to_vec()].concat();
+        }
+        if self.text_style & TextStyle::RapidBlink != TextStyle::empty() {
+            ansi_string = [ansi_string, RAPID_BLINK_CODE.as_bytes().to_vec()].concat();
+        }
+        if self.text_style & TextStyle::Hidden != TextStyle::empty() {
+            ansi_string = [ansi_string, HIDDEN_CODE.as_bytes().to_vec()].concat();
+        }
+        if self.text_style & TextStyle::Overline != TextStyle::empty() {
+            ansi_string = [ansi_string, OVERLINE_CODE.as_bytes().to_vec()].concat();
+        }
+        if self.text_style & TextStyle::Superscript != TextStyle::empty() {
+            ansi_string = [ansi_string, SUPERSCRIPT_CODE.as_bytes().to_vec()].concat();
+        }
+        if self.text_style & TextStyle::Subscript != TextStyle::empty() {
+            ansi_string = [ansi_string, SUBSCRIPT_CODE.as_bytes().to_vec()].concat();
+        }
 
         // Color
         ansi_string = [
@@ -172,9 +293,33 @@ impl Span {
                 .to_vec(),
         ]
         .concat();
+        ansi_string = [
+            ansi_string,
+            convert_color_type_to_ansi_code(Underline(self.underline_color))
+                .as_bytes()
+                .to_vec(),
+        ]
+        .concat();
 
-        // Text
-        ansi_string = [ansi_string, self.text].concat();
+        // Hyperlink is closed after the text so it won't apply to the text that come after the span
+        match self.link {
+            Some(link) if !self.text.is_empty() => {
+                ansi_string = [
+                    ansi_string,
+                    HYPERLINK_START_CODE(&link),
+                    self.text,
+                    HYPERLINK_END_CODE.as_bytes().to_vec(),
+                ]
+                .concat();
+            }
+            Some(link) => {
+                ansi_string = [ansi_string, HYPERLINK_START_CODE(&link)].concat();
+            }
+            None => {
+                // Text
+                ansi_string = [ansi_string, self.text].concat();
+            }
+        }
 
         return ansi_string;
     }
@@ -188,38 +333,95 @@ impl Span {
             self.bg_color = Color::None;
         }
 
+        if matches!(self.underline_color, Color::Default) {
+            self.underline_color = Color::None;
+        }
+
         self
     }
+}
 
-    fn get_color_str_from_color(color: Color) -> Option<String> {
-        match color {
-            Color::Default => None,
-            Color::None => None,
-            Color::Black => Some("black".to_string()),
-            Color::Red => Some("red".to_string()),
-            Color::Green => Some("green".to_string()),
-            Color::Yellow => Some("yellow".to_string()),
-            Color::Blue => Some("blue".to_string()),
-            Color::Magenta => Some("magenta".to_string()),
-            Color::Cyan => Some("cyan".to_string()),
-            Color::White => Some("white".to_string()),
-
-            // TODO - maybe make the bright color return RGB instead of the name?
-            Color::BrightBlack => Some("brightBlack".to_string()),
-            Color::BrightRed => Some("brightRed".to_string()),
-            Color::BrightGreen => Some("brightGreen".to_string()),
-            Color::BrightYellow => Some("brightYellow".to_string()),
-            Color::BrightBlue => Some("brightBlue".to_string()),
-            Color::BrightMagenta => Some("brightMagenta".to_string()),
-            Color::BrightCyan => Some("brightCyan".to_string()),
-            Color::BrightWhite => Some("brightWhite".to_string()),
-
-            Color::EightBit(eight_bit) => {
-                let (r, g, b) = get_rgb_values_from_8_bit(eight_bit);
-
-                Some(format!("rgb({}, {}, {})", r, g, b))
-            }
-            Color::Rgb(r, g, b) => Some(format!("rgb({}, {}, {})", r, g, b)),
+// Same as `Span` but the text and link are borrowed from the parsed buffer instead of copied
+#[derive(PartialEq, Clone, Copy)]
+pub struct SpanRef<'a> {
+    pub text: &'a [u8],
+    pub color: Color,
+    pub bg_color: Color,
+    pub underline_color: Color,
+
+    pub brightness: Brightness,
+    pub text_style: TextStyle,
+
+    pub link: Option<HyperlinkCommand<'a>>,
+}
+
+impl fmt::Debug for SpanRef<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SpanRef")
+            .field("text", &String::from_utf8_lossy(self.text))
+            .field("color", &self.color)
+            .field("bg_color", &self.bg_color)
+            .field("underline_color", &self.underline_color)
+            .field("brightness", &self.brightness)
+            .field("text_style", &self.text_style)
+            .field("link", &self.link.map(|link| String::from_utf8_lossy(link.uri).to_string()))
+            .finish()
+    }
+}
+
+impl<'a> SpanRef<'a> {
+    pub fn empty() -> SpanRef<'a> {
+        SpanRef {
+            text: b"",
+            color: Color::None,
+            bg_color: Color::None,
+            underline_color: Color::None,
+            text_style: TextStyle::None,
+            brightness: Brightness::None,
+            link: None,
+        }
+    }
+
+    pub fn with_text(mut self, text: &'a [u8]) -> SpanRef<'a> {
+        self.text = text;
+        self
+    }
+
+    // Span ref without text with the same style as the span
+    pub fn from_style(span: &'a Span) -> SpanRef<'a> {
+        SpanRef {
+            text: b"",
+            color: span.color,
+            bg_color: span.bg_color,
+            underline_color: span.underline_color,
+            brightness: span.brightness,
+            text_style: span.text_style,
+            link: span.link.as_ref().map(|link| HyperlinkCommand {
+                params: &link.params,
+                uri: &link.uri,
+            }),
+        }
+    }
+
+    // Whether both spans would look the same if they had the same text
+    pub fn has_same_style(&self, other: &SpanRef) -> bool {
+        return self.color == other.color
+            && self.bg_color == other.bg_color
+            && self.underline_color == other.underline_color
+            && self.brightness == other.brightness
+            && self.text_style == other.text_style
+            && self.link == other.link;
+    }
+
+    pub fn to_span(&self) -> Span {
+        Span {
+            text: self.text.to_vec(),
+            color: self.color,
+            bg_color: self.bg_color,
+            underline_color: self.underline_color,
+            brightness: self.brightness,
+            text_style: self.text_style,
+            link: self.link.as_ref().and_then(get_hyperlink_from_command),
         }
     }
 }
@@ -228,6 +430,7 @@ impl Span {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use crate::parse_ansi_text::ansi::theme::{SOLARIZED_THEME, VSCODE_THEME, XTERM_THEME};
 
     #[test]
     fn create_span_with_no_styling_have_no_styles_and_only_text() {
@@ -239,6 +442,8 @@ mod tests {
 
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
                 brightness: Brightness::None,
             }
@@ -252,6 +457,8 @@ mod tests {
 
             color: Color::Red,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text_style: TextStyle::None,
             brightness: Brightness::None,
         };
@@ -263,6 +470,8 @@ mod tests {
 
                 color: Color::Red,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
                 brightness: Brightness::None,
             }
@@ -276,6 +485,8 @@ mod tests {
 
             color: Color::Red,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text_style: TextStyle::None,
             brightness: Brightness::None,
         };
@@ -286,11 +497,131 @@ mod tests {
 
                 color: Color::Red,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
                 brightness: Brightness::None,
             }
         );
     }
 
-    // TODO - add tests for create_css_string
+    #[test]
+    fn create_css_string_should_combine_all_text_decoration_lines() {
+        let span = Span::empty().with_text_style(
+            TextStyle::Strikethrough | TextStyle::CurlyUnderline | TextStyle::Overline | TextStyle::SlowBlink,
+        );
+        assert_eq!(
+            span.create_css_string(&Theme::default()),
+            "text-decoration: line-through underline overline;text-decoration-style: wavy;"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_support_hidden_and_superscript() {
+        let span = Span::empty().with_text_style(TextStyle::Hidden | TextStyle::Superscript);
+        assert_eq!(span.create_css_string(&Theme::default()), "vertical-align: super;color: transparent;");
+    }
+
+    #[test]
+    fn serialize_to_ansi_string_should_include_extended_text_style() {
+        let span = Span::empty()
+            .with_text_style(TextStyle::DoubleUnderline | TextStyle::RapidBlink | TextStyle::Overline)
+            .with_text(b"Hello".to_vec());
+        assert_eq!(
+            String::from_utf8(span.serialize_to_ansi_string()).unwrap(),
+            "\x1B[21m\x1B[6m\x1B[53mHello"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_set_text_decoration_color_from_underline_color() {
+        let span = Span::empty()
+            .with_text_style(TextStyle::Underline)
+            .with_underline_color(Color::Rgb(255, 0, 10));
+        assert_eq!(
+            span.create_css_string(&Theme::default()),
+            "text-decoration: underline;text-decoration-color: rgb(255, 0, 10);"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_resolve_bright_and_8_bit_colors_from_the_theme() {
+        let span = Span::empty().with_color(Color::BrightRed).with_bg_color(Color::EightBit(4));
+        assert_eq!(
+            span.create_css_string(&XTERM_THEME),
+            "color: rgb(255, 0, 0);background-color: rgb(0, 0, 238);"
+        );
+        assert_eq!(
+            span.create_css_string(&VSCODE_THEME),
+            "color: rgb(241, 76, 76);background-color: rgb(36, 114, 200);"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_not_set_default_color() {
+        let span = Span::empty().with_color(Color::Default).with_bg_color(Color::Default);
+        assert_eq!(span.create_css_string(&Theme::default()), "");
+    }
+
+    #[test]
+    fn create_css_string_should_swap_colors_for_inverse() {
+        let span = Span::empty()
+            .with_text_style(TextStyle::Inverse)
+            .with_color(Color::Rgb(1, 2, 3))
+            .with_bg_color(Color::Green);
+        assert_eq!(
+            span.create_css_string(&XTERM_THEME),
+            "color: rgb(0, 205, 0);background-color: rgb(1, 2, 3);"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_use_theme_foreground_and_background_for_inverse_without_colors() {
+        let span = Span::empty().with_text_style(TextStyle::Inverse);
+        assert_eq!(
+            span.create_css_string(&SOLARIZED_THEME),
+            "color: rgb(0, 43, 54);background-color: rgb(131, 148, 150);"
+        );
+    }
+
+    #[test]
+    fn create_css_string_should_blend_dim_color_with_the_background() {
+        let span = Span::empty()
+            .with_brightness(Brightness::Dim)
+            .with_color(Color::Rgb(200, 100, 0))
+            .with_bg_color(Color::Rgb(100, 100, 100));
+        assert_eq!(
+            span.create_css_string(&Theme::default()),
+            "color: rgb(150, 100, 50);background-color: rgb(100, 100, 100);"
+        );
+
+        // Without colors it's the theme foreground on the theme background
+        let span = Span::empty().with_brightness(Brightness::Dim);
+        assert_eq!(span.create_css_string(&XTERM_THEME), "color: rgb(127, 127, 127);");
+    }
+
+    #[test]
+    fn serialize_to_ansi_string_should_include_underline_color() {
+        let span = Span::empty()
+            .with_text_style(TextStyle::CurlyUnderline)
+            .with_color(Color::Red)
+            .with_underline_color(Color::Rgb(255, 0, 10))
+            .with_text(b"Hello".to_vec());
+        assert_eq!(
+            String::from_utf8(span.serialize_to_ansi_string()).unwrap(),
+            "\x1B[4:3m\x1B[31m\x1B[58;2;255;0;10mHello"
+        );
+    }
+
+    #[test]
+    fn serialize_to_ansi_string_should_wrap_the_text_with_the_hyperlink() {
+        let span = Span::empty()
+            .with_color(Color::Red)
+            .with_link(Some(Hyperlink::new(b"https://example.com".to_vec()).with_params(b"id=1".to_vec())))
+            .with_text(b"Hello".to_vec());
+        assert_eq!(
+            String::from_utf8(span.serialize_to_ansi_string()).unwrap(),
+            "\x1B[31m\x1B]8;id=1;https://example.com\x1B\\Hello\x1B]8;;\x1B\\"
+        );
+    }
 }
@@ -1,2 +1,3 @@
 pub mod parse_ansi_as_line_of_spans;
 pub mod parse_ansi_as_spans;
+pub mod parse_ansi_as_span_refs;
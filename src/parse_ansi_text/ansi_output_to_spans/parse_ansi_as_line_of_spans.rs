@@ -3,8 +3,10 @@ use memchr::memchr;
 use crate::parse_ansi_text::ansi::ansi_sequence_helpers::{
     get_type_from_ansi_sequence, AnsiSequenceType,
 };
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::colors::Color;
 use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::Output;
 use crate::types::Line;
 
@@ -18,7 +20,10 @@ pub fn convert_ansi_output_lines_of_spans_continues<'a>(
     output: Option<Output<'a>>,
     current_line: &'a mut Line,
     end_location: usize,
-) -> ResultType {
+    // The location of the output in the text, used for diagnostics
+    location: usize,
+    options: &ParseOptions,
+) -> Result<ResultType, Error> {
     let current_spans = &mut current_line.spans;
 
     if current_spans.len() == 0 {
@@ -44,15 +49,13 @@ pub fn convert_ansi_output_lines_of_spans_continues<'a>(
         let before_new_line = current_span.text[..new_line_index].to_vec();
         current_span.text = before_new_line;
 
-        if current_span.text.is_empty() {
-            current_spans.pop();
-        }
+        remove_empty_last_span(current_spans, options);
 
-        return ResultType::Parse(next_line);
+        return Ok(ResultType::Parse(next_line));
     }
 
     if output.is_none() {
-        return ResultType::WaitForNext;
+        return Ok(ResultType::WaitForNext);
     }
 
     let output = output.unwrap();
@@ -77,103 +80,165 @@ pub fn convert_ansi_output_lines_of_spans_continues<'a>(
                 let before_new_line = current_span.text[..new_line_index].to_vec();
                 current_span.text = before_new_line;
 
-                if current_span.text.is_empty() {
-                    current_spans.pop();
-                }
+                remove_empty_last_span(current_spans, options);
 
-                return ResultType::Parse(next_line);
+                return Ok(ResultType::Parse(next_line));
             }
 
-            ResultType::WaitForNext
+            Ok(ResultType::WaitForNext)
         }
         Output::Escape(seq) => {
-            let sequence_type = get_type_from_ansi_sequence(&seq);
-
-            match sequence_type {
-                AnsiSequenceType::Unsupported => ResultType::WaitForNext,
-                AnsiSequenceType::Reset => {
-                    // Ignore spans that are just empty text even if they have style as this won't be shown
-                    if !current_span.text.is_empty() {
-                        current_spans.push(Span::empty());
-                        return ResultType::WaitForNext;
-                    }
-
-                    return ResultType::WaitForNext;
-                }
-                AnsiSequenceType::ForegroundColor(mut color) => {
-                    // Default color is same as none
-                    if matches!(color, Color::Default) {
-                        color = Color::None;
-                    }
-
-                    // TODO - add here that if current color is default or None and new color is default or none don't treat as different
-                    if !current_span.text.is_empty() && current_span.color != color {
-                        let cloned = current_span.clone();
-                        current_spans.push(
-                            cloned
-                                .with_text(vec![])
-                                // Apply the color
-                                .with_color(color),
-                        );
-                    } else {
-                        current_span.color = color;
-                    }
-
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::BackgroundColor(mut color) => {
-                    // Default color is same as none
-                    if matches!(color, Color::Default) {
-                        color = Color::None;
-                    }
-
-                    if !current_span.text.is_empty() && current_span.bg_color != color {
-                        let cloned = current_span.clone();
-                        current_spans.push(
-                            cloned
-                                .clone()
-                                .with_text(vec![])
-                                // Apply the background color
-                                .with_bg_color(color),
-                        );
-                    } else {
-                        current_span.bg_color = color;
-                    }
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::Brightness(brightness) => {
-                    if !current_span.text.is_empty() && current_span.brightness != brightness {
-                        let cloned = current_span.clone();
-                        current_spans.push(
-                            cloned
-                                .clone()
-                                .with_text(vec![])
-                                // Apply the background color
-                                .with_brightness(brightness),
-                        );
-                    } else {
-                        current_span.brightness = brightness;
-                    }
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::TextStyle(style) => {
-                    if !current_span.text.is_empty() && current_span.text_style != style {
-                        let cloned = current_span.clone();
-                        let text_style = cloned.text_style;
-                        current_spans.push(
-                            cloned
-                                .with_text(vec![])
-                                // Merge the style
-                                .with_text_style(text_style | style),
-                        );
-                    } else {
-                        // Merge the style
-                        current_span.text_style |= style;
-                    }
-
-                    ResultType::WaitForNext
+            for sequence_type in get_type_from_ansi_sequence(&seq, options) {
+                match sequence_type {
+                    Ok(sequence_type) => apply_sequence_type_to_spans(current_spans, sequence_type),
+                    Err(error) => options.handle_anomaly(error, location)?,
                 }
             }
+
+            Ok(ResultType::WaitForNext)
         }
     };
 }
+
+// The span without text at the end of the line is not shown,
+// unless it's the only span and the style of empty lines is kept
+pub(crate) fn remove_empty_last_span(spans: &mut Vec<Span>, options: &ParseOptions) {
+    if options.keep_empty_line_style && spans.len() == 1 {
+        return;
+    }
+
+    if spans.last().is_some_and(|span| span.text.is_empty()) {
+        spans.pop();
+    }
+}
+
+fn apply_sequence_type_to_spans(current_spans: &mut Vec<Span>, sequence_type: AnsiSequenceType) {
+    let current_span = current_spans.last_mut().unwrap();
+
+    match sequence_type {
+        AnsiSequenceType::Reset => {
+            // Ignore spans that are just empty text even if they have style as this won't be shown
+            // Reset does not end the hyperlink
+            let link = current_span.link.clone();
+
+            if !current_span.text.is_empty() {
+                current_spans.push(Span::empty().with_link(link));
+            } else {
+                *current_span = Span::empty().with_link(link);
+            }
+        }
+        AnsiSequenceType::ForegroundColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            // TODO - add here that if current color is default or None and new color is default or none don't treat as different
+            if !current_span.text.is_empty() && current_span.color != color {
+                let cloned = current_span.clone();
+                current_spans.push(
+                    cloned
+                        .with_text(vec![])
+                        // Apply the color
+                        .with_color(color),
+                );
+            } else {
+                current_span.color = color;
+            }
+        }
+        AnsiSequenceType::BackgroundColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            if !current_span.text.is_empty() && current_span.bg_color != color {
+                let cloned = current_span.clone();
+                current_spans.push(
+                    cloned
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the background color
+                        .with_bg_color(color),
+                );
+            } else {
+                current_span.bg_color = color;
+            }
+        }
+        AnsiSequenceType::UnderlineColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            if !current_span.text.is_empty() && current_span.underline_color != color {
+                let cloned = current_span.clone();
+                current_spans.push(
+                    cloned
+                        .with_text(vec![])
+                        // Apply the underline color
+                        .with_underline_color(color),
+                );
+            } else {
+                current_span.underline_color = color;
+            }
+        }
+        AnsiSequenceType::Brightness(brightness) => {
+            if !current_span.text.is_empty() && current_span.brightness != brightness {
+                let cloned = current_span.clone();
+                current_spans.push(
+                    cloned
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the background color
+                        .with_brightness(brightness),
+                );
+            } else {
+                current_span.brightness = brightness;
+            }
+        }
+        AnsiSequenceType::TextStyle(style) => {
+            if !current_span.text.is_empty() && current_span.text_style != style {
+                let cloned = current_span.clone();
+                let text_style = cloned.text_style;
+                current_spans.push(
+                    cloned
+                        .with_text(vec![])
+                        // Merge the style
+                        .with_text_style(text_style.with_added_style(style)),
+                );
+            } else {
+                // Merge the style
+                current_span.text_style = current_span.text_style.with_added_style(style);
+            }
+        }
+        AnsiSequenceType::UnderlineStyle(underline_style) => {
+            let text_style = current_span.text_style.with_underline_style(underline_style);
+
+            if !current_span.text.is_empty() && current_span.text_style != text_style {
+                let cloned = current_span.clone();
+                current_spans.push(cloned.with_text(vec![]).with_text_style(text_style));
+            } else {
+                current_span.text_style = text_style;
+            }
+        }
+        AnsiSequenceType::Hyperlink(link) => {
+            if !current_span.text.is_empty() && current_span.link != link {
+                let cloned = current_span.clone();
+                current_spans.push(cloned.with_text(vec![]).with_link(link));
+            } else {
+                current_span.link = link;
+            }
+        }
+        AnsiSequenceType::RemoveTextStyle(style) => {
+            let text_style = current_span.text_style.difference(style);
+
+            if !current_span.text.is_empty() && current_span.text_style != text_style {
+                let cloned = current_span.clone();
+                current_spans.push(cloned.with_text(vec![]).with_text_style(text_style));
+            } else {
+                current_span.text_style = text_style;
+            }
+        }
+    }
+}
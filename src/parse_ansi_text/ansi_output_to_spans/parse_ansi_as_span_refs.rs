@@ -0,0 +1,139 @@
+use crate::parse_ansi_text::ansi::ansi_sequence_helpers::{
+    get_type_from_ansi_sequence, AnsiSequenceType,
+};
+use crate::error::Error;
+use crate::parse_ansi_text::ansi::colors::Color;
+use crate::parse_ansi_text::ansi::types::SpanRef;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_ansi_text::raw_ansi_parse::AnsiSequence;
+
+// Unlike the other converters the style is kept separately from the text,
+// and a new span is only created when text arrive (see `SpanRefBuilder`)
+//
+// The text and hyperlinks are borrowed, but graphics mode sequences still allocate their parameters and style operations
+pub fn apply_ansi_sequence_to_span_ref_style<'a>(
+    seq: &AnsiSequence<'a>,
+    style: &mut SpanRef<'a>,
+    // The location of the sequence in the buffer, used for diagnostics
+    location: usize,
+    options: &ParseOptions,
+) -> Result<(), Error> {
+    // Borrow the hyperlink from the sequence instead of copying it
+    if let AnsiSequence::OperatingSystemCommand(command) = seq {
+        if let Some(hyperlink) = command.hyperlink() {
+            style.link = if hyperlink.is_end() { None } else { Some(hyperlink) };
+
+            return Ok(());
+        }
+    }
+
+    for sequence_type in get_type_from_ansi_sequence(seq, options) {
+        let sequence_type = match sequence_type {
+            Ok(sequence_type) => sequence_type,
+            Err(error) => {
+                options.handle_anomaly(error, location)?;
+                continue;
+            }
+        };
+
+        match sequence_type {
+            AnsiSequenceType::Reset => {
+                // Reset does not end the hyperlink
+                let link = style.link;
+                *style = SpanRef::empty();
+                style.link = link;
+            }
+            AnsiSequenceType::ForegroundColor(color) => {
+                style.color = replace_default_color_with_none(color);
+            }
+            AnsiSequenceType::BackgroundColor(color) => {
+                style.bg_color = replace_default_color_with_none(color);
+            }
+            AnsiSequenceType::UnderlineColor(color) => {
+                style.underline_color = replace_default_color_with_none(color);
+            }
+            AnsiSequenceType::Brightness(brightness) => {
+                style.brightness = brightness;
+            }
+            AnsiSequenceType::TextStyle(text_style) => {
+                // Merge the style
+                style.text_style = style.text_style.with_added_style(text_style);
+            }
+            AnsiSequenceType::UnderlineStyle(underline_style) => {
+                style.text_style = style.text_style.with_underline_style(underline_style);
+            }
+            AnsiSequenceType::RemoveTextStyle(text_style) => {
+                style.text_style = style.text_style.difference(text_style);
+            }
+            AnsiSequenceType::Hyperlink(_) => {
+                // Handled above without copying
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn replace_default_color_with_none(color: Color) -> Color {
+    // Default color is same as none
+    if matches!(color, Color::Default) {
+        return Color::None;
+    }
+
+    return color;
+}
+
+// Build span refs from text in the buffer, the span text grows as long as the next text
+// is right after it in the buffer and has the same style
+pub struct SpanRefBuilder<'a> {
+    buffer: &'a [u8],
+    current_span: SpanRef<'a>,
+    current_span_start: usize,
+}
+
+impl<'a> SpanRefBuilder<'a> {
+    pub fn new(buffer: &'a [u8]) -> SpanRefBuilder<'a> {
+        SpanRefBuilder {
+            buffer,
+            current_span: SpanRef::empty(),
+            current_span_start: 0,
+        }
+    }
+
+    // Returns the previous span when the text can't be added to it
+    pub fn append_text(&mut self, style: &SpanRef<'a>, start: usize, end: usize) -> Option<SpanRef<'a>> {
+        if start == end {
+            return None;
+        }
+
+        let current_span_end = self.current_span_start + self.current_span.text.len();
+
+        if !self.current_span.text.is_empty()
+            && current_span_end == start
+            && self.current_span.has_same_style(style)
+        {
+            self.current_span.text = &self.buffer[self.current_span_start..end];
+
+            return None;
+        }
+
+        let previous_span = self.take();
+
+        self.current_span = style.with_text(&self.buffer[start..end]);
+        self.current_span_start = start;
+
+        return previous_span;
+    }
+
+    // Take the current span if it has text
+    pub fn take(&mut self) -> Option<SpanRef<'a>> {
+        if self.current_span.text.is_empty() {
+            return None;
+        }
+
+        let span = self.current_span;
+        self.current_span = SpanRef::empty();
+
+        return Some(span);
+    }
+}
@@ -2,8 +2,10 @@
 use crate::parse_ansi_text::ansi::ansi_sequence_helpers::{
     get_type_from_ansi_sequence, AnsiSequenceType,
 };
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::colors::Color;
 use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::Output;
 
 pub enum ResultType {
@@ -16,91 +18,183 @@ pub enum ResultType {
 pub fn convert_ansi_output_to_spans_continues<'a>(
     output: Output<'a>,
     current_span: &'a mut Span,
-) -> ResultType {
+    // The location of the output in the text, used for diagnostics
+    location: usize,
+    options: &ParseOptions,
+) -> Result<ResultType, Error> {
     return match output {
         Output::TextBlock(text) => {
-            current_span.text.append(text.text.to_vec().as_mut());
-            ResultType::WaitForNext
+            current_span.text.extend_from_slice(text.text);
+            Ok(ResultType::WaitForNext)
         }
         Output::Escape(seq) => {
-            let sequence_type = get_type_from_ansi_sequence(&seq);
+            // Once a new span is created the rest of the operations apply to it
+            let mut next_span: Option<Span> = None;
 
-            match sequence_type {
-                AnsiSequenceType::Unsupported => ResultType::WaitForNext,
-                AnsiSequenceType::Reset => {
-                    // Ignore spans that are just empty text even if they have style as this won't be shown
-                    if current_span.text.len() > 0 {
-                        return ResultType::Parse(Span::empty());
+            for sequence_type in get_type_from_ansi_sequence(&seq, options) {
+                let sequence_type = match sequence_type {
+                    Ok(sequence_type) => sequence_type,
+                    Err(error) => {
+                        options.handle_anomaly(error, location)?;
+                        continue;
                     }
+                };
 
-                    ResultType::Skip
-                }
-                AnsiSequenceType::ForegroundColor(mut color) => {
-                    // Default color is same as none
-                    if matches!(color, Color::Default) {
-                        color = Color::None;
-                    }
+                let span = match next_span.as_mut() {
+                    Some(span) => span,
+                    None => &mut *current_span,
+                };
 
-                    // TODO - add here that if current color is default or None and new color is default or none don't treat as different
-                    if current_span.text.len() > 0 && current_span.color != color {
-                        return ResultType::Parse(
-                            current_span
-                                .clone()
-                                .with_text(vec![])
-                                // Apply the color
-                                .with_color(color),
-                        );
+                match apply_sequence_type_to_span(span, sequence_type) {
+                    ResultType::Parse(new_span) => {
+                        next_span = Some(new_span);
                     }
-
-                    current_span.color = color;
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::BackgroundColor(mut color) => {
-                    // Default color is same as none
-                    if matches!(color, Color::Default) {
-                        color = Color::None;
+                    ResultType::Skip => {
+                        // Reset does not end the hyperlink
+                        *span = Span::empty().with_link(span.link.take());
                     }
-
-                    if current_span.text.len() > 0 && current_span.bg_color != color {
-                        return ResultType::Parse(
-                            current_span
-                                .clone()
-                                .with_text(vec![])
-                                // Apply the background color
-                                .with_bg_color(color),
-                        );
+                    ResultType::WaitForNext => {
+                        // Do nothing with the current span
                     }
-                    current_span.bg_color = color;
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::Brightness(brightness) => {
-                    if current_span.text.len() > 0 && current_span.brightness != brightness {
-                        return ResultType::Parse(
-                            current_span
-                                .clone()
-                                .with_text(vec![])
-                                // Apply the background color
-                                .with_brightness(brightness),
-                        );
-                    }
-                    current_span.brightness = brightness;
-                    ResultType::WaitForNext
-                }
-                AnsiSequenceType::TextStyle(style) => {
-                    if current_span.text.len() > 0 && current_span.text_style != style {
-                        return ResultType::Parse(
-                            current_span
-                                .clone()
-                                .with_text(vec![])
-                                // Merge the style
-                                .with_text_style(current_span.text_style | style),
-                        );
-                    }
-                    // Merge the style
-                    current_span.text_style = current_span.text_style | style;
-                    ResultType::WaitForNext
                 }
             }
+
+            match next_span {
+                Some(next_span) => Ok(ResultType::Parse(next_span)),
+                None => Ok(ResultType::WaitForNext),
+            }
+        }
+    };
+}
+
+fn apply_sequence_type_to_span(current_span: &mut Span, sequence_type: AnsiSequenceType) -> ResultType {
+    return match sequence_type {
+        AnsiSequenceType::Reset => {
+            // Ignore spans that are just empty text even if they have style as this won't be shown
+            if current_span.text.len() > 0 {
+                // Reset does not end the hyperlink
+                return ResultType::Parse(Span::empty().with_link(current_span.link.clone()));
+            }
+
+            ResultType::Skip
+        }
+        AnsiSequenceType::ForegroundColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            // TODO - add here that if current color is default or None and new color is default or none don't treat as different
+            if current_span.text.len() > 0 && current_span.color != color {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the color
+                        .with_color(color),
+                );
+            }
+
+            current_span.color = color;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::BackgroundColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            if current_span.text.len() > 0 && current_span.bg_color != color {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the background color
+                        .with_bg_color(color),
+                );
+            }
+            current_span.bg_color = color;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::UnderlineColor(mut color) => {
+            // Default color is same as none
+            if matches!(color, Color::Default) {
+                color = Color::None;
+            }
+
+            if current_span.text.len() > 0 && current_span.underline_color != color {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the underline color
+                        .with_underline_color(color),
+                );
+            }
+            current_span.underline_color = color;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::Brightness(brightness) => {
+            if current_span.text.len() > 0 && current_span.brightness != brightness {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        // Apply the background color
+                        .with_brightness(brightness),
+                );
+            }
+            current_span.brightness = brightness;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::TextStyle(style) => {
+            if current_span.text.len() > 0 && current_span.text_style != style {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        // Merge the style
+                        .with_text_style(current_span.text_style.with_added_style(style)),
+                );
+            }
+            // Merge the style
+            current_span.text_style = current_span.text_style.with_added_style(style);
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::UnderlineStyle(underline_style) => {
+            let text_style = current_span.text_style.with_underline_style(underline_style);
+
+            if current_span.text.len() > 0 && current_span.text_style != text_style {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        .with_text_style(text_style),
+                );
+            }
+            current_span.text_style = text_style;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::Hyperlink(link) => {
+            if current_span.text.len() > 0 && current_span.link != link {
+                return ResultType::Parse(current_span.clone().with_text(vec![]).with_link(link));
+            }
+            current_span.link = link;
+            ResultType::WaitForNext
+        }
+        AnsiSequenceType::RemoveTextStyle(style) => {
+            let text_style = current_span.text_style.difference(style);
+
+            if current_span.text.len() > 0 && current_span.text_style != text_style {
+                return ResultType::Parse(
+                    current_span
+                        .clone()
+                        .with_text(vec![])
+                        .with_text_style(text_style),
+                );
+            }
+            current_span.text_style = text_style;
+            ResultType::WaitForNext
         }
     };
 }
@@ -1,3 +1,4 @@
+use crate::parse_ansi_text::raw_ansi_parse::parsers::ESCAPE_AS_BYTES;
 use crate::parse_ansi_text::raw_ansi_parse::{AnsiSequence, Output, parse_escape, Text};
 
 #[derive(Debug, PartialEq, Clone)]
@@ -5,6 +6,9 @@ pub struct ParseAnsiResult<'a> {
     pub output: Option<Output<'a>>,
     pub size: usize,
     pub pending_string: &'a [u8],
+
+    // The escape character was returned as text as it's not followed by a valid sequence
+    pub malformed_escape: bool,
 }
 
 pub fn parse_ansi_continues(
@@ -26,11 +30,13 @@ pub fn parse_ansi_continues(
                     })),
                     size,
                     pending_string: buf,
+                    malformed_escape: str.starts_with(ESCAPE_AS_BYTES),
                 },
                 _ => ParseAnsiResult {
                     output: Some(Output::Escape(seq)),
                     pending_string: buf,
-                    size
+                    size,
+                    malformed_escape: false,
                 },
             }
         }
@@ -38,6 +44,7 @@ pub fn parse_ansi_continues(
             output: None,
             size: 0,
             pending_string: buf,
+            malformed_escape: false,
         },
     };
 }
@@ -1,14 +1,91 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::types::Span;
 
+// Malformed or unsupported sequence and where it was found
+#[derive(Debug)]
+pub struct Diagnostic {
+    // Byte offset from the start of the parsed text
+    pub location: usize,
+
+    pub error: Error,
+}
+
+// Called for every malformed or unsupported sequence that did not stop the parsing
+pub type DiagnosticsSink = Arc<dyn Fn(&Diagnostic) + Send + Sync>;
+
+// Shared list of diagnostics, clone it before passing the options to the parser to read the diagnostics after the parsing
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticsCollector {
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl DiagnosticsCollector {
+    pub fn new() -> DiagnosticsCollector {
+        DiagnosticsCollector::default()
+    }
+
+    pub fn push(&self, diagnostic: Diagnostic) {
+        // Poisoned lock only means that other thread panicked while pushing, the list itself is still valid
+        let mut diagnostics = self.diagnostics.lock().unwrap_or_else(|error| error.into_inner());
+
+        diagnostics.push(diagnostic);
+    }
+
+    // Take the diagnostics that were collected so far
+    pub fn take(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.diagnostics.lock().unwrap_or_else(|error| error.into_inner());
+
+        return std::mem::take(&mut *diagnostics);
+    }
+}
+
 #[derive(Clone, Debug)]
+pub enum ParseMode {
+    // Malformed escapes are treated as text and invalid parameters are dropped
+    Lenient,
+
+    // The first malformed sequence stops the parsing, and is returned as `Error::MalformedSequence` with its location
+    Strict,
+
+    // Same as lenient, but every malformed or unsupported sequence is recorded in the collector with its location
+    Collect(DiagnosticsCollector),
+}
+
+#[derive(Clone)]
 pub struct ParseOptions {
     pub initial_span: Span,
+
+    pub mode: ParseMode,
+
+    // When not set the diagnostics are ignored
+    pub diagnostics_sink: Option<DiagnosticsSink>,
+
+    // Lines that have no text keep a span without text with the style at the start of the line instead of having no spans,
+    // so the style each line starts with is known (e.g. for the mapping file) even when the line is empty
+    pub keep_empty_line_style: bool,
+}
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ParseOptions")
+            .field("initial_span", &self.initial_span)
+            .field("mode", &self.mode)
+            .field("diagnostics_sink", &self.diagnostics_sink.is_some())
+            .field("keep_empty_line_style", &self.keep_empty_line_style)
+            .finish()
+    }
 }
 
 impl ParseOptions {
     pub fn default() -> ParseOptions {
         ParseOptions {
             initial_span: Span::empty(),
+            mode: ParseMode::Lenient,
+            diagnostics_sink: None,
+            keep_empty_line_style: false,
         }
     }
 
@@ -16,4 +93,46 @@ impl ParseOptions {
         self.initial_span = initial_span;
         self
     }
+
+    pub fn with_mode(mut self, mode: ParseMode) -> ParseOptions {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_diagnostics_sink(mut self, diagnostics_sink: impl Fn(&Diagnostic) + Send + Sync + 'static) -> ParseOptions {
+        self.diagnostics_sink = Some(Arc::new(diagnostics_sink));
+        self
+    }
+
+    pub fn with_empty_line_style(mut self, keep_empty_line_style: bool) -> ParseOptions {
+        self.keep_empty_line_style = keep_empty_line_style;
+        self
+    }
+
+    // Whether anomalies that don't stop the parsing are passed to the diagnostics sink or the collector
+    pub fn reports_anomalies(&self) -> bool {
+        return self.diagnostics_sink.is_some() || matches!(self.mode, ParseMode::Collect(_));
+    }
+
+    // Returns error only when the parsing should stop (malformed sequence in strict mode)
+    pub fn handle_anomaly(&self, error: Error, location: usize) -> Result<(), Error> {
+        if matches!(self.mode, ParseMode::Strict) && error.is_malformed() {
+            return Err(Error::MalformedSequence {
+                location,
+                error: Box::new(error),
+            });
+        }
+
+        let diagnostic = Diagnostic { location, error };
+
+        if let Some(diagnostics_sink) = &self.diagnostics_sink {
+            diagnostics_sink(&diagnostic);
+        }
+
+        if let ParseMode::Collect(collector) = &self.mode {
+            collector.push(diagnostic);
+        }
+
+        return Ok(());
+    }
 }
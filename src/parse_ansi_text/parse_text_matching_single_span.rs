@@ -1,63 +1,81 @@
-use std::iter::Iterator;
-
-use ansi_parser::{AnsiParser, Output};
-
 use crate::parse_ansi_text::ansi::ansi_sequence_helpers::{
-    get_type_from_ansi_sequence, old_ansi_sequence_to_new, AnsiSequenceType,
+    get_type_from_ansi_sequence, AnsiSequenceType,
 };
 use crate::parse_ansi_text::ansi::colors::Color;
 use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_ansi_text::raw_ansi_parse::Output;
 
 // The text here MUST correspond to a single span.
 // Span with empty text is valid here
 pub fn parse_text_matching_single_span(text: &str) -> Span {
     let mut span = Span::empty();
-    let mut raw_ansi_parse = text.ansi_parse();
+    let mut result = parse_ansi_continues(text.as_bytes());
+
+    while let Some(output) = result.output {
+        result = parse_ansi_continues(result.pending_string);
 
-    while let Some(output) = raw_ansi_parse.next() {
         match output {
             Output::TextBlock(text) => {
-                span.text = [span.text, text.as_bytes().to_vec()].concat();
+                span.text = [span.text, text.text.to_vec()].concat();
             }
             Output::Escape(seq) => {
-                let sequence_type = get_type_from_ansi_sequence(&old_ansi_sequence_to_new(seq));
-
-                match sequence_type {
-                    AnsiSequenceType::Unsupported => {
-                        continue;
-                    }
-                    AnsiSequenceType::Reset => {
-                        // Can't be here as this is guaranteed to not have reset
-                        // But if we getting it anyway, then reset the styles
-                        span = Span::empty();
-                    }
-                    AnsiSequenceType::ForegroundColor(mut color) => {
-                        // Default color is same as none
-                        if matches!(color, Color::Default) {
-                            color = Color::None;
+                // The text is created by us, so it should not have unsupported sequences
+                for sequence_type in get_type_from_ansi_sequence(&seq, &ParseOptions::default()).into_iter().flatten() {
+                    match sequence_type {
+                        AnsiSequenceType::Reset => {
+                            // Can't be here as this is guaranteed to not have reset
+                            // But if we getting it anyway, then reset the styles
+                            span = Span::empty().with_link(span.link);
                         }
+                        AnsiSequenceType::ForegroundColor(mut color) => {
+                            // Default color is same as none
+                            if matches!(color, Color::Default) {
+                                color = Color::None;
+                            }
 
-                        span.color = color;
-                    }
-                    AnsiSequenceType::BackgroundColor(mut color) => {
-                        // Default color is same as none
-                        if matches!(color, Color::Default) {
-                            color = Color::None;
+                            span.color = color;
+                        }
+                        AnsiSequenceType::BackgroundColor(mut color) => {
+                            // Default color is same as none
+                            if matches!(color, Color::Default) {
+                                color = Color::None;
+                            }
+                            span.bg_color = color;
+                        }
+                        AnsiSequenceType::UnderlineColor(mut color) => {
+                            // Default color is same as none
+                            if matches!(color, Color::Default) {
+                                color = Color::None;
+                            }
+                            span.underline_color = color;
+                        }
+                        AnsiSequenceType::Brightness(brightness) => {
+                            span.brightness = brightness;
+                        }
+                        AnsiSequenceType::TextStyle(style) => {
+                            // Merge the style
+                            span.text_style = span.text_style.with_added_style(style);
+                        }
+                        AnsiSequenceType::UnderlineStyle(underline_style) => {
+                            span.text_style = span.text_style.with_underline_style(underline_style);
+                        }
+                        AnsiSequenceType::RemoveTextStyle(style) => {
+                            span.text_style = span.text_style.difference(style);
+                        }
+                        AnsiSequenceType::Hyperlink(link) => {
+                            span.link = link;
                         }
-                        span.bg_color = color;
-                    }
-                    AnsiSequenceType::Brightness(brightness) => {
-                        span.brightness = brightness;
-                    }
-                    AnsiSequenceType::TextStyle(style) => {
-                        // Merge the style
-                        span.text_style = span.text_style | style;
                     }
                 }
             }
         }
     }
 
+    // Unfinished escape code is treated as text
+    span.text = [span.text, result.pending_string.to_vec()].concat();
+
     return span;
 }
 
@@ -146,4 +164,19 @@ mod tests {
             .with_text("Hello world".to_string().as_bytes().to_vec());
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn should_remove_text_style_that_was_turned_off() {
+        let input_str = "".to_string() +
+            ITALIC_CODE +
+            UNDERLINE_CODE +
+            NOT_ITALIC_CODE +
+            BOLD_CODE +
+            NORMAL_INTENSITY_CODE;
+
+        let output: Span = parse_text_matching_single_span(input_str.as_str());
+
+        let expected = Span::empty().with_text_style(TextStyle::Underline);
+        assert_eq!(output, expected);
+    }
 }
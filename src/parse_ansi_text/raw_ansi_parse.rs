@@ -1,8 +1,16 @@
+pub mod control_sequence;
+pub mod control_string;
 pub mod enums;
+pub mod operating_system_command;
 pub mod output;
 pub mod parsers;
 
 // Make it public to consumers of the library, aka. external API
+pub use control_sequence::{ControlSequence, GraphicsModeParameter};
 pub use enums::AnsiSequence;
+pub use control_string::{ControlString, StringTerminator};
+pub use operating_system_command::{
+    OperatingSystemCommand, OperatingSystemCommandType, ShellIntegrationMark,
+};
 pub use output::{Output, Text};
 pub use parsers::parse_escape;
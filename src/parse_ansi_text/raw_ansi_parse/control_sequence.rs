@@ -0,0 +1,247 @@
+use atoi::atoi;
+
+// Control Sequence Introducer (CSI) as defined in ECMA-48 section 5.4
+// The format is:
+// ESC [ <parameter bytes> <intermediate bytes> <final byte>
+//
+// parameter bytes: 0x30–0x3F (0–9:;<=>?)
+// intermediate bytes: 0x20–0x2F (space and !"#$%&'()*+,-./)
+// final byte: 0x40–0x7E (@A–Z[\]^_`a–z{|}~)
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlSequence<'a> {
+    // One of `<`, `=`, `>` or `?` when the sequence is a private one, e.g. the `?` in `\x1b[?25h`
+    pub private_marker: Option<u8>,
+
+    // The raw parameter bytes without the private marker, e.g. `1;31` for `\x1b[1;31m`
+    pub parameters: &'a [u8],
+
+    pub intermediates: &'a [u8],
+
+    pub final_byte: u8,
+}
+
+// Single parameter of SGR (Select Graphic Rendition) with its colon separated sub parameters (ITU T.416)
+// e.g. `38:2::255:0:0` is the parameter 38 with the sub parameters [2, None, 255, 0, 0]
+// and `38;2;255;0;0` is 5 parameters without sub parameters
+#[derive(Debug, PartialEq, Clone)]
+pub struct GraphicsModeParameter {
+    pub value: u8,
+
+    // Empty sub parameter (like the color space id in `38:2::255:0:0`) is None
+    pub sub_parameters: Vec<Option<u8>>,
+}
+
+impl GraphicsModeParameter {
+    pub fn new(value: u8) -> GraphicsModeParameter {
+        GraphicsModeParameter {
+            value,
+            sub_parameters: vec![],
+        }
+    }
+
+    pub fn with_sub_parameters(mut self, sub_parameters: Vec<Option<u8>>) -> GraphicsModeParameter {
+        self.sub_parameters = sub_parameters;
+        self
+    }
+}
+
+impl From<u8> for GraphicsModeParameter {
+    fn from(value: u8) -> Self {
+        GraphicsModeParameter::new(value)
+    }
+}
+
+impl std::fmt::Display for GraphicsModeParameter {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}", self.value)?;
+
+        for sub_parameter in &self.sub_parameters {
+            match sub_parameter {
+                Some(sub_parameter) => write!(formatter, ":{}", sub_parameter)?,
+                None => write!(formatter, ":")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// `;` separated parameters as they appear in the sequence, e.g. `38;2;255;0;0`
+pub fn graphics_mode_parameters_to_string(parameters: &[GraphicsModeParameter]) -> String {
+    return parameters
+        .iter()
+        .map(|param| param.to_string())
+        .collect::<Vec<String>>()
+        .join(";");
+}
+
+pub fn is_parameter_byte(byte: u8) -> bool {
+    return (0x30..=0x3F).contains(&byte);
+}
+
+pub fn is_intermediate_byte(byte: u8) -> bool {
+    return (0x20..=0x2F).contains(&byte);
+}
+
+pub fn is_final_byte(byte: u8) -> bool {
+    return (0x40..=0x7E).contains(&byte);
+}
+
+pub fn is_private_marker(byte: u8) -> bool {
+    return matches!(byte, b'<' | b'=' | b'>' | b'?');
+}
+
+impl<'a> ControlSequence<'a> {
+    // Split the parameters by `;`, empty parameter (e.g. the first one in `\x1b[;1m`) is returned as empty slice
+    pub fn parameters_iter(&self) -> impl Iterator<Item = &'a [u8]> {
+        let parameters = self.parameters;
+
+        // No parameters at all is not the same as single empty parameter
+        let skip = if parameters.is_empty() { 1 } else { 0 };
+
+        return parameters.split(|byte| *byte == b';').skip(skip);
+    }
+
+    // Get the parameters as numbers, empty parameters get the default value
+    // returns None if any of the parameters is not a number or does not fit in u32
+    pub fn numeric_parameters(&self, default: u32) -> Option<Vec<u32>> {
+        return self
+            .parameters_iter()
+            .map(|param| parse_numeric_parameter(param, default))
+            .collect();
+    }
+
+    // SGR (Select Graphic Rendition) - `\x1b[<params>m`
+    pub fn is_select_graphic_rendition(&self) -> bool {
+        return self.final_byte == b'm' && self.private_marker.is_none() && self.intermediates.is_empty();
+    }
+
+    // Get the parameters of SGR, each parameter can have colon separated sub parameters
+    // Empty parameter is the same as 0 (e.g. `\x1b[;1m` is the same as `\x1b[0;1m`)
+    // returns None if any of the values is not a number or does not fit in u8
+    pub fn graphics_mode_parameters(&self) -> Option<Vec<GraphicsModeParameter>> {
+        return self
+            .parameters_iter()
+            .map(parse_graphics_mode_parameter)
+            .collect();
+    }
+}
+
+fn parse_graphics_mode_parameter(param: &[u8]) -> Option<GraphicsModeParameter> {
+    let mut parts = param.split(|byte| *byte == b':');
+
+    // split always return at least one item
+    let value = parse_u8_parameter(parts.next().unwrap_or(b""))?.unwrap_or(0);
+
+    let sub_parameters = parts
+        .map(parse_u8_parameter)
+        .collect::<Option<Vec<Option<u8>>>>()?;
+
+    return Some(GraphicsModeParameter::new(value).with_sub_parameters(sub_parameters));
+}
+
+// Outer option is whether the parameter is valid, inner option is whether the parameter is empty
+fn parse_u8_parameter(param: &[u8]) -> Option<Option<u8>> {
+    if param.is_empty() {
+        return Some(None);
+    }
+
+    if !param.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    return atoi::<u8>(param).map(Some);
+}
+
+fn parse_numeric_parameter(param: &[u8], default: u32) -> Option<u32> {
+    if param.is_empty() {
+        return Some(default);
+    }
+
+    if !param.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    // atoi return None on overflow
+    return atoi::<u32>(param);
+}
+
+impl std::fmt::Display for ControlSequence<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "[")?;
+
+        if let Some(private_marker) = self.private_marker {
+            write!(formatter, "{}", private_marker as char)?;
+        }
+
+        write!(
+            formatter,
+            "{}{}{}",
+            String::from_utf8_lossy(self.parameters),
+            String::from_utf8_lossy(self.intermediates),
+            self.final_byte as char
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn create(parameters: &[u8]) -> ControlSequence {
+        ControlSequence {
+            private_marker: None,
+            parameters,
+            intermediates: b"",
+            final_byte: b'm',
+        }
+    }
+
+    #[test]
+    fn no_parameters_should_return_empty_list() {
+        assert_eq!(create(b"").numeric_parameters(0), Some(vec![]));
+    }
+
+    #[test]
+    fn empty_parameters_should_get_the_default_value() {
+        assert_eq!(create(b";1;").numeric_parameters(0), Some(vec![0, 1, 0]));
+    }
+
+    #[test]
+    fn should_parse_any_number_of_parameters() {
+        assert_eq!(
+            create(b"1;38;2;10;20;30;4").numeric_parameters(0),
+            Some(vec![1, 38, 2, 10, 20, 30, 4])
+        );
+    }
+
+    #[test]
+    fn non_numeric_parameters_should_return_none() {
+        assert_eq!(create(b"38:2:1:2:3").numeric_parameters(0), None);
+    }
+
+    #[test]
+    fn graphics_mode_parameters_should_have_colon_separated_sub_parameters() {
+        assert_eq!(
+            create(b"1;38:2::255:0:10;4:3").graphics_mode_parameters(),
+            Some(vec![
+                GraphicsModeParameter::new(1),
+                GraphicsModeParameter::new(38).with_sub_parameters(vec![
+                    Some(2),
+                    None,
+                    Some(255),
+                    Some(0),
+                    Some(10)
+                ]),
+                GraphicsModeParameter::new(4).with_sub_parameters(vec![Some(3)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn graphics_mode_parameters_that_does_not_fit_in_u8_should_return_none() {
+        assert_eq!(create(b"38:2::256:0:0").graphics_mode_parameters(), None);
+    }
+}
@@ -0,0 +1,46 @@
+// Control strings as defined in ECMA-48 section 5.6
+// The format is:
+// <introducer> <payload> <terminator>
+//
+// introducers:
+// DCS (Device Control String): ESC P - e.g. sixel images
+// SOS (Start Of String): ESC X
+// PM (Privacy Message): ESC ^
+// APC (Application Program Command): ESC _
+// OSC (Operating System Command): ESC ] - has its own type as it's the most common one
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlString<'a> {
+    pub payload: &'a [u8],
+
+    pub terminator: StringTerminator,
+}
+
+// Both terminators are common, so we keep the one that was used in order to write the sequence back as is
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StringTerminator {
+    // BEL - \x07
+    Bell,
+
+    // ST - \x1b\
+    StringTerminator,
+}
+
+impl StringTerminator {
+    pub fn as_bytes(&self) -> &'static [u8] {
+        return match self {
+            StringTerminator::Bell => b"\x07",
+            StringTerminator::StringTerminator => b"\x1b\\",
+        };
+    }
+}
+
+impl std::fmt::Display for ControlString<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{}{}",
+            String::from_utf8_lossy(self.payload),
+            String::from_utf8_lossy(self.terminator.as_bytes())
+        )
+    }
+}
@@ -1,5 +1,11 @@
 // Taken from ansi_parse and modify
 
+use crate::parse_ansi_text::raw_ansi_parse::control_sequence::{
+    graphics_mode_parameters_to_string, ControlSequence, GraphicsModeParameter,
+};
+use crate::parse_ansi_text::raw_ansi_parse::control_string::ControlString;
+use crate::parse_ansi_text::raw_ansi_parse::operating_system_command::OperatingSystemCommand;
+
 ///The following are the implemented ANSI escape sequences. More to be added.
 #[derive(Debug, PartialEq, Clone)]
 pub enum AnsiSequence<'a> {
@@ -14,7 +20,7 @@ pub enum AnsiSequence<'a> {
     CursorRestore,
     EraseDisplay,
     EraseLine,
-    SetGraphicsMode(heapless::Vec<u8, 5>),
+    SetGraphicsMode(Vec<GraphicsModeParameter>),
     SetMode(u8),
     ResetMode(u8),
     HideCursor,
@@ -53,6 +59,24 @@ pub enum AnsiSequence<'a> {
     SetSingleShift2,
     SetSingleShift3,
     SetTopAndBottom(u32, u32),
+
+    // Any control sequence that does not have a specific variant
+    ControlSequence(ControlSequence<'a>),
+
+    // OSC - e.g. hyperlink (`\x1b]8;;https://example.com\x1b\\`)
+    OperatingSystemCommand(OperatingSystemCommand<'a>),
+
+    // DCS - e.g. sixel images (`\x1bPq...\x1b\\`)
+    DeviceControlString(ControlString<'a>),
+
+    // SOS - `\x1bX...\x1b\\`
+    StartOfString(ControlString<'a>),
+
+    // PM - `\x1b^...\x1b\\`
+    PrivacyMessage(ControlString<'a>),
+
+    // APC - `\x1b_...\x1b\\`
+    ApplicationProgramCommand(ControlString<'a>),
 }
 
 use core::fmt::{Display, Formatter, Result as DisplayResult};
@@ -62,19 +86,14 @@ impl Display for AnsiSequence<'_> {
 
         use AnsiSequence::*;
         match self {
-            Text(text) => write!(formatter, "{}", String::from_utf8(text.to_vec()).unwrap()),
-            SetGraphicsMode(vec) => match vec.len() {
-                0 => write!(formatter, "[m"),
-                1 => write!(formatter, "[{}m", vec[0]),
-                2 => write!(formatter, "[{};{}m", vec[0], vec[1]),
-                3 => write!(formatter, "[{};{};{}m", vec[0], vec[1], vec[2]),
-                5 => write!(
-                    formatter,
-                    "[{};{};{};{};{}m",
-                    vec[0], vec[1], vec[2], vec[3], vec[4]
-                ),
-                _ => unreachable!(),
-            },
+            Text(text) => write!(formatter, "{}", String::from_utf8_lossy(text)),
+            SetGraphicsMode(vec) => write!(formatter, "[{}m", graphics_mode_parameters_to_string(vec)),
+            ControlSequence(sequence) => write!(formatter, "{}", sequence),
+            OperatingSystemCommand(command) => write!(formatter, "{}", command),
+            DeviceControlString(control_string) => write!(formatter, "P{}", control_string),
+            StartOfString(control_string) => write!(formatter, "X{}", control_string),
+            PrivacyMessage(control_string) => write!(formatter, "^{}", control_string),
+            ApplicationProgramCommand(control_string) => write!(formatter, "_{}", control_string),
             SetMode(mode) => write!(formatter, "[={}h", mode),
             ResetMode(mode) => write!(formatter, "[={}l", mode),
             _ => write!(formatter, "<other>"),
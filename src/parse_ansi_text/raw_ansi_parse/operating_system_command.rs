@@ -0,0 +1,259 @@
+use atoi::atoi;
+
+use crate::parse_ansi_text::raw_ansi_parse::control_string::StringTerminator;
+
+// Operating System Command (OSC) as defined in ECMA-48 section 8.3.89
+// The format is:
+// ESC ] <payload> <terminator>
+//
+// The payload usually start with the command number followed by `;` and the command arguments,
+// e.g. `8;;https://example.com` for `\x1b]8;;https://example.com\x1b\\`
+#[derive(Debug, PartialEq, Clone)]
+pub struct OperatingSystemCommand<'a> {
+    pub payload: &'a [u8],
+
+    pub terminator: StringTerminator,
+}
+
+// OSC 8 - hyperlink
+// `\x1b]8;<params>;<uri>\x1b\\` start a link and `\x1b]8;;\x1b\\` end it
+// params are `:` separated `key=value` pairs, e.g. `id=1`
+// Reference: https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feda
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HyperlinkCommand<'a> {
+    pub params: &'a [u8],
+
+    // Empty uri is the end of the link
+    pub uri: &'a [u8],
+}
+
+// The known commands, the payload is the command arguments
+// Reference: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands
+#[derive(Debug, PartialEq, Clone)]
+pub enum OperatingSystemCommandType<'a> {
+    Unknown,
+
+    // OSC 0 - \x1b]0;<title>\x07
+    SetIconNameAndWindowTitle(&'a [u8]),
+
+    // OSC 1 - \x1b]1;<icon-name>\x07
+    SetIconName(&'a [u8]),
+
+    // OSC 2 - \x1b]2;<title>\x07
+    SetWindowTitle(&'a [u8]),
+
+    // OSC 8
+    Hyperlink(HyperlinkCommand<'a>),
+
+    // OSC 9 (\x1b]9;<message>\x07) and OSC 777 (\x1b]777;notify;<title>;<body>\x07)
+    Notification(&'a [u8]),
+
+    // OSC 133 - shell integration marks, e.g. \x1b]133;A\x07 for prompt start
+    ShellIntegration(&'a [u8]),
+
+    // OSC 1337 - iTerm2 proprietary commands, e.g. inline images
+    ITermProprietary(&'a [u8]),
+}
+
+// OSC 133 - shell integration marks (also known as semantic prompts)
+// Reference: https://gitlab.freedesktop.org/Per_Bothner/specifications/blob/master/proposals/semantic-prompts.md
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShellIntegrationMark {
+    // \x1b]133;A\x07
+    PromptStart,
+
+    // \x1b]133;B\x07 - end of the prompt and start of the command the user type
+    CommandStart,
+
+    // \x1b]133;C\x07 - the command was executed, and it's output start
+    CommandExecuted,
+
+    // \x1b]133;D;<exit-code>\x07 - the exit code is optional
+    CommandFinished(Option<i32>),
+}
+
+impl HyperlinkCommand<'_> {
+    pub fn is_end(&self) -> bool {
+        return self.uri.is_empty();
+    }
+}
+
+impl<'a> OperatingSystemCommand<'a> {
+    // The command number is everything before the first `;`
+    // returns None if it's not a number
+    pub fn command(&self) -> Option<u32> {
+        let command = match self.payload.iter().position(|byte| *byte == b';') {
+            Some(index) => &self.payload[..index],
+            None => self.payload,
+        };
+
+        if command.is_empty() || !command.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+
+        return atoi::<u32>(command);
+    }
+
+    // Everything after the first `;`
+    pub fn arguments(&self) -> &'a [u8] {
+        return match self.payload.iter().position(|byte| *byte == b';') {
+            Some(index) => &self.payload[index + 1..],
+            None => b"",
+        };
+    }
+
+    pub fn command_type(&self) -> OperatingSystemCommandType<'a> {
+        let arguments = self.arguments();
+
+        return match self.command() {
+            Some(0) => OperatingSystemCommandType::SetIconNameAndWindowTitle(arguments),
+            Some(1) => OperatingSystemCommandType::SetIconName(arguments),
+            Some(2) => OperatingSystemCommandType::SetWindowTitle(arguments),
+            Some(8) => match self.hyperlink() {
+                Some(hyperlink) => OperatingSystemCommandType::Hyperlink(hyperlink),
+                None => OperatingSystemCommandType::Unknown,
+            },
+            Some(9) | Some(777) => OperatingSystemCommandType::Notification(arguments),
+            Some(133) => OperatingSystemCommandType::ShellIntegration(arguments),
+            Some(1337) => OperatingSystemCommandType::ITermProprietary(arguments),
+            _ => OperatingSystemCommandType::Unknown,
+        };
+    }
+
+    pub fn shell_integration_mark(&self) -> Option<ShellIntegrationMark> {
+        if self.command() != Some(133) {
+            return None;
+        }
+
+        // Additional arguments are `;` separated, e.g. `D;0;aid=1`
+        let mut arguments = self.arguments().split(|byte| *byte == b';');
+
+        return match arguments.next()? {
+            b"A" => Some(ShellIntegrationMark::PromptStart),
+            b"B" => Some(ShellIntegrationMark::CommandStart),
+            b"C" => Some(ShellIntegrationMark::CommandExecuted),
+            b"D" => Some(ShellIntegrationMark::CommandFinished(
+                arguments.next().and_then(atoi::<i32>),
+            )),
+            _ => None,
+        };
+    }
+
+    pub fn hyperlink(&self) -> Option<HyperlinkCommand<'a>> {
+        if self.command() != Some(8) {
+            return None;
+        }
+
+        let arguments = self.arguments();
+
+        // Missing the params separator
+        let separator_index = arguments.iter().position(|byte| *byte == b';')?;
+
+        return Some(HyperlinkCommand {
+            params: &arguments[..separator_index],
+            uri: &arguments[separator_index + 1..],
+        });
+    }
+}
+
+impl std::fmt::Display for OperatingSystemCommand<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "]{}{}",
+            String::from_utf8_lossy(self.payload),
+            String::from_utf8_lossy(self.terminator.as_bytes())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn create(payload: &[u8]) -> OperatingSystemCommand {
+        OperatingSystemCommand {
+            payload,
+            terminator: StringTerminator::StringTerminator,
+        }
+    }
+
+    #[test]
+    fn should_get_command_number_and_arguments() {
+        let command = create(b"0;Window title; with separator");
+
+        assert_eq!(command.command(), Some(0));
+        assert_eq!(command.arguments(), b"Window title; with separator");
+    }
+
+    #[test]
+    fn command_that_is_not_a_number_should_return_none() {
+        assert_eq!(create(b"abc;1").command(), None);
+        assert_eq!(create(b"").command(), None);
+    }
+
+    #[test]
+    fn should_get_hyperlink_params_and_uri() {
+        assert_eq!(
+            create(b"8;id=1;https://example.com/a;b").hyperlink(),
+            Some(HyperlinkCommand {
+                params: b"id=1",
+                uri: b"https://example.com/a;b",
+            })
+        );
+    }
+
+    #[test]
+    fn hyperlink_with_empty_uri_should_be_the_end_of_the_link() {
+        assert!(create(b"8;;").hyperlink().unwrap().is_end());
+    }
+
+    #[test]
+    fn should_get_the_command_type() {
+        assert_eq!(
+            create(b"0;Build").command_type(),
+            OperatingSystemCommandType::SetIconNameAndWindowTitle(b"Build")
+        );
+        assert_eq!(
+            create(b"2;Build").command_type(),
+            OperatingSystemCommandType::SetWindowTitle(b"Build")
+        );
+        assert_eq!(
+            create(b"133;D;0").command_type(),
+            OperatingSystemCommandType::ShellIntegration(b"D;0")
+        );
+        assert_eq!(
+            create(b"777;notify;Done;Tests passed").command_type(),
+            OperatingSystemCommandType::Notification(b"notify;Done;Tests passed")
+        );
+        assert_eq!(
+            create(b"1337;File=inline=1:AAAA").command_type(),
+            OperatingSystemCommandType::ITermProprietary(b"File=inline=1:AAAA")
+        );
+        assert_eq!(create(b"52;c;AAAA").command_type(), OperatingSystemCommandType::Unknown);
+    }
+
+    #[test]
+    fn should_get_shell_integration_mark() {
+        assert_eq!(create(b"133;A").shell_integration_mark(), Some(ShellIntegrationMark::PromptStart));
+        assert_eq!(create(b"133;B").shell_integration_mark(), Some(ShellIntegrationMark::CommandStart));
+        assert_eq!(create(b"133;C").shell_integration_mark(), Some(ShellIntegrationMark::CommandExecuted));
+        assert_eq!(
+            create(b"133;D;127;aid=1").shell_integration_mark(),
+            Some(ShellIntegrationMark::CommandFinished(Some(127)))
+        );
+        assert_eq!(
+            create(b"133;D").shell_integration_mark(),
+            Some(ShellIntegrationMark::CommandFinished(None))
+        );
+        assert_eq!(create(b"133;P;k=i").shell_integration_mark(), None);
+        assert_eq!(create(b"2;A").shell_integration_mark(), None);
+    }
+
+    #[test]
+    fn hyperlink_without_params_separator_should_return_none() {
+        assert_eq!(create(b"8;https://example.com").hyperlink(), None);
+    }
+}
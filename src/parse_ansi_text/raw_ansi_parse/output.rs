@@ -20,11 +20,7 @@ impl<'a> Display for Output<'a> {
     fn fmt(&self, formatter: &mut Formatter) -> DisplayResult {
         use Output::*;
         match self {
-            TextBlock(txt) => write!(
-                formatter,
-                "{}",
-                String::from_utf8(txt.text.to_vec()).unwrap()
-            ),
+            TextBlock(txt) => write!(formatter, "{}", String::from_utf8_lossy(txt.text)),
             Escape(seq) => write!(formatter, "{}", seq),
         }
     }
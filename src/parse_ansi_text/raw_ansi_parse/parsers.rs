@@ -1,19 +1,27 @@
 // Taken from ansi_parse and modify
 
 use atoi::atoi;
-use heapless::Vec;
-use memchr::memchr;
+use memchr::{memchr, memchr2};
 use nom::{error, IResult};
 use nom::branch::alt;
-use nom::bytes::streaming::{tag, take};
+use nom::bytes::streaming::{tag, take, take_while};
 use nom::character::streaming::{digit0, digit1};
-use nom::combinator::{map, map_res, opt, value};
+use nom::combinator::{map, map_opt, map_res, opt, value, verify};
 use nom::error::ErrorKind;
 use nom::sequence::{delimited, preceded, tuple};
 
+use crate::parse_ansi_text::raw_ansi_parse::control_sequence::{
+    ControlSequence, is_final_byte, is_intermediate_byte, is_parameter_byte, is_private_marker,
+};
+use crate::parse_ansi_text::raw_ansi_parse::control_string::{ControlString, StringTerminator};
 use crate::parse_ansi_text::raw_ansi_parse::enums::AnsiSequence;
+use crate::parse_ansi_text::raw_ansi_parse::operating_system_command::OperatingSystemCommand;
 
 pub const ESCAPE_AS_BYTES: &[u8] = b"\x1b";
+
+// Longest payload of a control string (OSC, DCS, SOS, PM and APC), without a limit a missing terminator
+// would make the parser wait for the rest of the input and buffer all of it
+pub const MAX_CONTROL_STRING_PAYLOAD_LENGTH: usize = 64 * 1024;
 const EMPTY_AS_BYTES: &[u8] = b"";
 
 macro_rules! tag_parser {
@@ -25,8 +33,11 @@ macro_rules! tag_parser {
 }
 
 fn parse_u32(input: &[u8]) -> IResult<&[u8], u32, error::Error<&[u8]>> {
-    map_res(digit1, |s: &[u8]| -> Result<u32, error::Error<&[u8]>> {
-        return Ok(u32::from_be_bytes(s.try_into().unwrap()));
+    map_res(digit1, |s: &[u8]| {
+        return atoi::<u32>(s).ok_or(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Digit,
+        )));
     })(input)
 }
 
@@ -98,79 +109,101 @@ fn cursor_backward(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
     )(input)
 }
 
-fn graphics_mode1(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
-    map(delimited(tag(b"\x1b["), parse_u8, tag(b"m")), |val| {
-        let mode =
-            Vec::from_slice(&[val]).expect("Vec::from_slice should allocate sufficient size");
-        AnsiSequence::SetGraphicsMode(mode)
-    })(input)
-}
-
-fn graphics_mode2(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
-    map(
-        tuple((tag(b"\x1b["), parse_u8, tag(b";"), parse_u8, tag(b"m"))),
-        |(_, val1, _, val2, _)| {
-            let mode = Vec::from_slice(&[val1, val2])
-                .expect("Vec::from_slice should allocate sufficient size");
-            AnsiSequence::SetGraphicsMode(mode)
-        },
-    )(input)
-}
-
-fn graphics_mode3(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+fn control_sequence(input: &[u8]) -> IResult<&[u8], ControlSequence> {
     map(
         tuple((
             tag(b"\x1b["),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b"m"),
+            opt(verify(take(1usize), |byte: &[u8]| is_private_marker(byte[0]))),
+            take_while(is_parameter_byte),
+            take_while(is_intermediate_byte),
+            verify(take(1usize), |byte: &[u8]| is_final_byte(byte[0])),
         )),
-        |(_, val1, _, val2, _, val3, _)| {
-            let mode = Vec::from_slice(&[val1, val2, val3])
-                .expect("Vec::from_slice should allocate sufficient size");
-            AnsiSequence::SetGraphicsMode(mode)
+        |(_, private_marker, parameters, intermediates, final_byte)| ControlSequence {
+            private_marker: private_marker.map(|marker: &[u8]| marker[0]),
+            parameters,
+            intermediates,
+            final_byte: final_byte[0],
         },
     )(input)
 }
 
-fn graphics_mode4(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
-    value(AnsiSequence::SetGraphicsMode(Vec::new()), tag(b"\x1b[m"))(input)
+fn graphics_mode(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map_opt(control_sequence, |sequence| {
+        if !sequence.is_select_graphic_rendition() {
+            return None;
+        }
+
+        let mode = sequence.graphics_mode_parameters()?;
+
+        Some(AnsiSequence::SetGraphicsMode(mode))
+    })(input)
 }
 
-fn graphics_mode5(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+// Fallback for control sequences that we don't have specific parser for,
+// so they won't be treated as text
+fn unknown_control_sequence(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_sequence, AnsiSequence::ControlSequence)(input)
+}
+
+fn string_terminator(input: &[u8]) -> IResult<&[u8], StringTerminator> {
+    alt((
+        value(StringTerminator::Bell, tag(b"\x07")),
+        value(StringTerminator::StringTerminator, tag(b"\x1b\\")),
+    ))(input)
+}
+
+// The payload ends at the BEL or ESC of the terminator, a payload that is longer than the limit is not a control string
+fn control_string_payload(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let searched = &input[..input.len().min(MAX_CONTROL_STRING_PAYLOAD_LENGTH + 1)];
+
+    return match memchr2(b'\x07', b'\x1b', searched) {
+        Some(end) => Ok((&input[end..], &input[..end])),
+        None if input.len() > MAX_CONTROL_STRING_PAYLOAD_LENGTH => {
+            Err(nom::Err::Error(error::Error::new(input, ErrorKind::Tag)))
+        }
+        None => Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+    };
+}
+
+// The payload end at the terminator, either BEL or ST (`ESC \`)
+// while the payload is not terminated we wait for more input, so it works across chunks,
+// until the payload is too long and then the introducer is treated as a malformed escape
+fn control_string<'a>(
+    introducer: &'static [u8],
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], ControlString<'a>> {
     map(
         tuple((
-            tag(b"\x1b["),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b";"),
-            parse_u8,
-            tag(b"m"),
+            tag(introducer),
+            control_string_payload,
+            string_terminator,
         )),
-        |(_, val1, _, val2, _, val3, _, val4, _, val5, _)| {
-            let mode = Vec::from_slice(&[val1, val2, val3, val4, val5])
-                .expect("Vec::from_slice should allocate sufficient size");
-            AnsiSequence::SetGraphicsMode(mode)
-        },
-    )(input)
+        |(_, payload, terminator)| ControlString { payload, terminator },
+    )
 }
 
-fn graphics_mode(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
-    alt((
-        graphics_mode1,
-        graphics_mode2,
-        graphics_mode3,
-        graphics_mode4,
-        graphics_mode5,
-    ))(input)
+fn operating_system_command(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_string(b"\x1b]"), |control_string| {
+        AnsiSequence::OperatingSystemCommand(OperatingSystemCommand {
+            payload: control_string.payload,
+            terminator: control_string.terminator,
+        })
+    })(input)
+}
+
+fn device_control_string(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_string(b"\x1bP"), AnsiSequence::DeviceControlString)(input)
+}
+
+fn start_of_string(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_string(b"\x1bX"), AnsiSequence::StartOfString)(input)
+}
+
+fn privacy_message(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_string(b"\x1b^"), AnsiSequence::PrivacyMessage)(input)
+}
+
+fn application_program_command(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
+    map(control_string(b"\x1b_"), AnsiSequence::ApplicationProgramCommand)(input)
 }
 
 fn set_mode(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
@@ -326,6 +359,13 @@ fn combined(input: &[u8]) -> IResult<&[u8], AnsiSequence> {
             set_us_g1,
             set_g0_special,
         )),
+        // Must be after all the specific control sequences parsers
+        unknown_control_sequence,
+        operating_system_command,
+        device_control_string,
+        start_of_string,
+        privacy_message,
+        application_program_command,
         set_g1_special,
         set_g0_alternate,
         set_g1_alternate,
@@ -388,6 +428,7 @@ pub fn parse_escape(input: &[u8], complete_string: bool) -> IResult<&[u8], AnsiS
                 nom::Err::Error(sub_error) => {
                     // If fail to match than we have escape code in the first char
                     // we check in fail to match and not incomplete as we might get more text that might be escape code
+                    // The malformed escape is returned as text, the caller decide what to do with it based on the parse mode
                     if matches!(sub_error.code, ErrorKind::Tag) {
                         let single_res = take_single(input);
 
@@ -413,6 +454,9 @@ mod tests {
     use crate::parse_ansi_text::ansi::colors::*;
     use crate::parse_ansi_text::ansi::constants::RESET_CODE;
 
+    use crate::parse_ansi_text::raw_ansi_parse::GraphicsModeParameter;
+    use test_case::test_case;
+
     use super::*;
 
     #[test]
@@ -421,15 +465,251 @@ mod tests {
             parse_escape(RED_BACKGROUND_CODE.as_bytes(), true),
             Ok((
                 EMPTY_AS_BYTES,
-                AnsiSequence::SetGraphicsMode(Vec::from_slice(&[41]).unwrap())
+                AnsiSequence::SetGraphicsMode(vec![41.into()])
             ))
         );
         assert_eq!(
             parse_escape(RESET_CODE.as_bytes(), true),
             Ok((
                 EMPTY_AS_BYTES,
-                AnsiSequence::SetGraphicsMode(Vec::from_slice(&[0]).unwrap())
+                AnsiSequence::SetGraphicsMode(vec![0.into()])
+            ))
+        );
+    }
+
+    #[test]
+    fn should_parse_graphics_mode_with_any_number_of_parameters() {
+        assert_eq!(
+            parse_escape(b"\x1b[1;4;31;42m", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::SetGraphicsMode(vec![1.into(), 4.into(), 31.into(), 42.into()])
+            ))
+        );
+        assert_eq!(
+            parse_escape(b"\x1b[1;38;2;10;20;30m", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::SetGraphicsMode(vec![
+                    1.into(),
+                    38.into(),
+                    2.into(),
+                    10.into(),
+                    20.into(),
+                    30.into()
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn should_treat_empty_graphics_mode_parameters_as_zero() {
+        assert_eq!(
+            parse_escape(b"\x1b[;1mHello", true),
+            Ok((
+                b"Hello".as_slice(),
+                AnsiSequence::SetGraphicsMode(vec![0.into(), 1.into()])
+            ))
+        );
+    }
+
+    #[test]
+    fn should_parse_control_sequence_with_private_marker_and_intermediate_bytes() {
+        assert_eq!(
+            parse_escape(b"\x1b[?1049h", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::ControlSequence(ControlSequence {
+                    private_marker: Some(b'?'),
+                    parameters: b"1049",
+                    intermediates: b"",
+                    final_byte: b'h',
+                })
             ))
         );
+        assert_eq!(
+            parse_escape(b"\x1b[>4;2 q", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::ControlSequence(ControlSequence {
+                    private_marker: Some(b'>'),
+                    parameters: b"4;2",
+                    intermediates: b" ",
+                    final_byte: b'q',
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn graphics_mode_with_parameter_that_does_not_fit_should_not_be_treated_as_text() {
+        assert_eq!(
+            parse_escape(b"\x1b[300m", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::ControlSequence(ControlSequence {
+                    private_marker: None,
+                    parameters: b"300",
+                    intermediates: b"",
+                    final_byte: b'm',
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn should_wait_for_more_input_when_control_sequence_is_not_finished() {
+        assert!(matches!(
+            parse_escape(b"\x1b[1;4;31", true),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn should_treat_escape_as_text_when_control_sequence_is_malformed() {
+        assert_eq!(
+            parse_escape(b"\x1b[1\x07", true),
+            Ok((b"[1\x07".as_slice(), AnsiSequence::Text(b"\x1b")))
+        );
+    }
+
+    #[test]
+    fn should_parse_set_top_and_bottom() {
+        assert_eq!(
+            parse_escape(b"\x1b[1;24r", true),
+            Ok((EMPTY_AS_BYTES, AnsiSequence::SetTopAndBottom(1, 24)))
+        );
+    }
+
+    #[test]
+    fn should_parse_graphics_mode_with_colon_separated_sub_parameters() {
+        assert_eq!(
+            parse_escape(b"\x1b[38:2::255:0:0;4:3m", true),
+            Ok((
+                EMPTY_AS_BYTES,
+                AnsiSequence::SetGraphicsMode(vec![
+                    GraphicsModeParameter::new(38).with_sub_parameters(vec![
+                        Some(2),
+                        None,
+                        Some(255),
+                        Some(0),
+                        Some(0)
+                    ]),
+                    GraphicsModeParameter::new(4).with_sub_parameters(vec![Some(3)]),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn should_parse_operating_system_command_with_any_terminator() {
+        assert_eq!(
+            parse_escape(b"\x1b]8;;https://example.com\x1b\\Link", true),
+            Ok((
+                b"Link".as_slice(),
+                AnsiSequence::OperatingSystemCommand(OperatingSystemCommand {
+                    payload: b"8;;https://example.com",
+                    terminator: StringTerminator::StringTerminator,
+                })
+            ))
+        );
+        assert_eq!(
+            parse_escape(b"\x1b]8;;https://example.com\x07Link", true),
+            Ok((
+                b"Link".as_slice(),
+                AnsiSequence::OperatingSystemCommand(OperatingSystemCommand {
+                    payload: b"8;;https://example.com",
+                    terminator: StringTerminator::Bell,
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn should_wait_for_more_input_when_operating_system_command_is_not_terminated() {
+        assert!(matches!(
+            parse_escape(b"\x1b]8;;https://exam", true),
+            Err(nom::Err::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse_escape(b"\x1b]8;;https://example.com\x1b", true),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn should_treat_escape_as_text_when_operating_system_command_is_malformed() {
+        assert_eq!(
+            parse_escape(b"\x1b]8;;a\x1b[1m", true),
+            Ok((b"]8;;a\x1b[1m".as_slice(), AnsiSequence::Text(b"\x1b")))
+        );
+    }
+
+    #[test_case(b"\x1bPq#0;2;0;0;0#1!6~-\x1b\\Hello", AnsiSequence::DeviceControlString(ControlString {
+        payload: b"q#0;2;0;0;0#1!6~-",
+        terminator: StringTerminator::StringTerminator,
+    }); "device control string")]
+    #[test_case(b"\x1bXpayload\x07Hello", AnsiSequence::StartOfString(ControlString {
+        payload: b"payload",
+        terminator: StringTerminator::Bell,
+    }); "start of string")]
+    #[test_case(b"\x1b^payload\x1b\\Hello", AnsiSequence::PrivacyMessage(ControlString {
+        payload: b"payload",
+        terminator: StringTerminator::StringTerminator,
+    }); "privacy message")]
+    #[test_case(b"\x1b_Gf=100;AAAA\x1b\\Hello", AnsiSequence::ApplicationProgramCommand(ControlString {
+        payload: b"Gf=100;AAAA",
+        terminator: StringTerminator::StringTerminator,
+    }); "application program command")]
+    #[test_case(b"\x1b]0;Window title\x07Hello", AnsiSequence::OperatingSystemCommand(OperatingSystemCommand {
+        payload: b"0;Window title",
+        terminator: StringTerminator::Bell,
+    }); "operating system command")]
+    fn should_parse_control_strings(input: &[u8], expected: AnsiSequence) {
+        assert_eq!(parse_escape(input, true), Ok((b"Hello".as_slice(), expected)));
+    }
+
+    #[test_case(b"\x1bPq#0;2;0"; "device control string")]
+    #[test_case(b"\x1b_Gf=100;AA"; "application program command")]
+    #[test_case(b"\x1b]133;A\x1b"; "operating system command with partial terminator")]
+    fn should_wait_for_more_input_when_control_string_is_not_terminated(input: &[u8]) {
+        assert!(matches!(parse_escape(input, true), Err(nom::Err::Incomplete(_))));
+    }
+
+    #[test_case(b"\x1b]0;"; "operating system command")]
+    #[test_case(b"\x1bP"; "device control string")]
+    #[test_case(b"\x1b_"; "application program command")]
+    fn should_treat_escape_as_text_when_control_string_is_too_long(introducer: &[u8]) {
+        let input = [introducer, &vec![b'a'; MAX_CONTROL_STRING_PAYLOAD_LENGTH + 1]].concat();
+
+        assert_eq!(
+            parse_escape(&input, true),
+            Ok((&input[1..], AnsiSequence::Text(b"\x1b")))
+        );
+    }
+
+    #[test]
+    fn should_parse_control_string_with_payload_of_max_length() {
+        let payload = vec![b'a'; MAX_CONTROL_STRING_PAYLOAD_LENGTH];
+        let input = [b"\x1b_".as_slice(), &payload, b"\x1b\\"].concat();
+
+        assert_eq!(
+            parse_escape(&input, true),
+            Ok((
+                b"".as_slice(),
+                AnsiSequence::ApplicationProgramCommand(ControlString {
+                    payload: &payload,
+                    terminator: StringTerminator::StringTerminator,
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn control_string_display_should_be_the_same_as_the_input() {
+        let input = b"\x1bPq#0;2;0;0;0\x1b\\";
+        let (_, sequence) = parse_escape(input, true).unwrap();
+
+        assert_eq!(sequence.to_string().as_bytes(), input);
     }
 }
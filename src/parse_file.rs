@@ -1,7 +1,10 @@
+pub mod file_to_command_blocks;
 pub mod file_to_lines_of_spans;
 pub mod file_to_spans;
 pub mod from_middle_of_file;
 mod tests;
 pub mod types;
 pub mod text_to_spans;
+pub mod text_to_span_refs;
 pub mod text_to_lines_of_spans;
+pub mod text_to_lines_of_span_refs;
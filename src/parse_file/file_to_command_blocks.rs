@@ -0,0 +1,136 @@
+use std::io::Read;
+
+use genawaiter::sync::{Co, Gen};
+
+use crate::error::Error;
+use crate::parse_ansi_text::raw_ansi_parse::ShellIntegrationMark;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_file::file_to_lines_of_spans::{read_ansi_file_to_lines_and_marks, read_ansi_to_lines_and_marks, LineOrMark};
+use crate::parse_file::types::ReadAnsiFileOptions;
+use crate::types::{CommandBlock, CommandBlockPart};
+
+enum Zone {
+    Prompt,
+    Command,
+    Output,
+}
+
+fn lines_and_marks_to_command_block_parts(
+    lines_and_marks: impl Iterator<Item=Result<LineOrMark, Error>>,
+) -> impl Iterator<Item=Result<CommandBlockPart, Error>> {
+    // Text before the first prompt is treated as output of unknown command
+    let mut current_zone = Zone::Output;
+
+    return lines_and_marks.filter_map(move |item| {
+        let item = match item {
+            Ok(item) => item,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let part = match item {
+            LineOrMark::Line(line) => match current_zone {
+                Zone::Prompt => CommandBlockPart::Prompt(line),
+                Zone::Command => CommandBlockPart::Command(line),
+                Zone::Output => CommandBlockPart::Output(line),
+            },
+            LineOrMark::ShellIntegrationMark(mark) => match mark {
+                ShellIntegrationMark::PromptStart => {
+                    current_zone = Zone::Prompt;
+
+                    CommandBlockPart::Start
+                }
+                ShellIntegrationMark::CommandStart => {
+                    current_zone = Zone::Command;
+
+                    return None;
+                }
+                ShellIntegrationMark::CommandExecuted => {
+                    current_zone = Zone::Output;
+
+                    return None;
+                }
+                ShellIntegrationMark::CommandFinished(exit_code) => {
+                    // Text after the command finished and before the next prompt is still part of the output
+                    current_zone = Zone::Output;
+
+                    CommandBlockPart::Finished(exit_code)
+                }
+            },
+        };
+
+        return Some(Ok(part));
+    });
+}
+
+// Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
+async fn read_ansi_file_to_command_blocks_producer(
+    parts: impl Iterator<Item=Result<CommandBlockPart, Error>>,
+    co: Co<Result<CommandBlock, Error>>,
+) {
+    let mut current_block = CommandBlock::empty();
+
+    for part in parts {
+        let part = match part {
+            Ok(part) => part,
+            Err(error) => {
+                // The error stops the parsing, the current block is not complete so it's dropped
+                co.yield_(Err(error)).await;
+                return;
+            }
+        };
+
+        match part {
+            CommandBlockPart::Start => {
+                if !current_block.is_empty() {
+                    co.yield_(Ok(current_block)).await;
+                }
+
+                current_block = CommandBlock::empty();
+            }
+            CommandBlockPart::Prompt(line) => current_block.prompt.push(line),
+            CommandBlockPart::Command(line) => current_block.command.push(line),
+            CommandBlockPart::Output(line) => current_block.output.push(line),
+            CommandBlockPart::Finished(exit_code) => current_block.exit_code = exit_code,
+        }
+    }
+
+    if !current_block.is_empty() {
+        co.yield_(Ok(current_block)).await;
+    }
+}
+
+// The lines of each command block are returned as they are parsed, so nothing is kept in memory,
+// use it instead of `read_ansi_file_to_command_blocks` when a command can have a lot of output
+pub fn read_ansi_file_to_command_block_parts(
+    options: ReadAnsiFileOptions,
+) -> Result<impl Iterator<Item=Result<CommandBlockPart, Error>>, Error> {
+    return Ok(lines_and_marks_to_command_block_parts(read_ansi_file_to_lines_and_marks(options)?));
+}
+
+// Same as `read_ansi_file_to_command_block_parts` but for any reader (e.g. stdin)
+pub fn read_ansi_to_command_block_parts(
+    reader: impl Read,
+    options: ParseOptions,
+) -> impl Iterator<Item=Result<CommandBlockPart, Error>> {
+    return lines_and_marks_to_command_block_parts(read_ansi_to_lines_and_marks(reader, options));
+}
+
+// Each command block is returned once it's complete, so all of its lines (including the whole output of the command) are kept in memory,
+// see `read_ansi_file_to_command_block_parts` for getting the lines as they are parsed
+pub fn read_ansi_file_to_command_blocks(
+    options: ReadAnsiFileOptions,
+) -> Result<impl Iterator<Item=Result<CommandBlock, Error>>, Error> {
+    let parts = read_ansi_file_to_command_block_parts(options)?;
+
+    return Ok(Gen::new(|co| read_ansi_file_to_command_blocks_producer(parts, co)).into_iter());
+}
+
+// Same as `read_ansi_file_to_command_blocks` but for any reader (e.g. stdin)
+pub fn read_ansi_to_command_blocks(
+    reader: impl Read,
+    options: ParseOptions,
+) -> impl Iterator<Item=Result<CommandBlock, Error>> {
+    let parts = read_ansi_to_command_block_parts(reader, options);
+
+    return Gen::new(|co| read_ansi_file_to_command_blocks_producer(parts, co)).into_iter();
+}
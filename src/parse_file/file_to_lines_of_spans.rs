@@ -1,24 +1,61 @@
+use std::io::Read;
+
 use genawaiter::sync::{Co, Gen};
-use crate::files::file_reader::FileReader;
+use itertools::Either;
+
+use crate::error::Error;
+use crate::files::chunk_reader::{ChunkReader, DEFAULT_CHUNK_SIZE_IN_BYTES};
+use crate::files::follow_reader::{FollowEnd, FollowOptions, FollowReader};
+use crate::files::mapped_file::{open_file_input, FileInput, MappedFile};
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_line_of_spans::{
-    convert_ansi_output_lines_of_spans_continues, ResultType,
+    convert_ansi_output_lines_of_spans_continues, remove_empty_last_span, ResultType,
 };
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
-use crate::parse_ansi_text::raw_ansi_parse::Output;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_ansi_text::raw_ansi_parse::{AnsiSequence, Output, ShellIntegrationMark};
 use crate::parse_ansi_text::raw_ansi_parse::Text;
+use crate::parse_file::text_to_lines_of_spans::buffer_to_lines_producer;
 use crate::parse_file::types::ReadAnsiFileOptions;
 use crate::types::Line;
 
+pub enum LineOrMark {
+    Line(Line),
+
+    // The line is split at the mark, so the lines before it end at the mark and the lines after it start at the mark
+    ShellIntegrationMark(ShellIntegrationMark),
+}
+
+fn get_shell_integration_mark(output: &Output) -> Option<ShellIntegrationMark> {
+    return match output {
+        Output::Escape(AnsiSequence::OperatingSystemCommand(command)) => command.shell_integration_mark(),
+        _ => None,
+    };
+}
+
 // Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
-async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<Line>) {
-    let file_reader = FileReader::new(options.file_options);
+async fn read_ansi_file_to_lines_producer(
+    chunks: impl Iterator<Item=Result<Vec<u8>, Error>>,
+    options: ParseOptions,
+    split_at_shell_integration_marks: bool,
+    co: Co<Result<LineOrMark, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_read_ansi_file_to_lines(chunks, options, split_at_shell_integration_marks, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
 
+async fn try_read_ansi_file_to_lines(
+    chunks: impl Iterator<Item=Result<Vec<u8>, Error>>,
+    options: ParseOptions,
+    split_at_shell_integration_marks: bool,
+    co: &Co<Result<LineOrMark, Error>>,
+) -> Result<(), Error> {
     let mut end_of_line_index: usize = 0;
 
     let current_span: Span = options
-        .parse_options
         .initial_span
         .clone()
         .replace_default_color_with_none();
@@ -28,8 +65,8 @@ async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<L
     };
     let mut pending_string: Vec<u8> = vec![];
 
-    for item in file_reader {
-        let mut value = item;
+    for item in chunks {
+        let mut value = item?;
 
         if pending_string.is_empty() {
             pending_string = value;
@@ -41,23 +78,62 @@ async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<L
         let mut result: ParseAnsiResult = parse_ansi_continues(pending);
 
         while let Some(ready_output) = result.output {
+            // The location of the output in the text
+            let location = end_of_line_index;
             end_of_line_index += result.size;
-            let mut lines_result = convert_ansi_output_lines_of_spans_continues(
-                Some(ready_output),
-                &mut current_line,
-                end_of_line_index,
-            );
 
-            while let ResultType::Parse(next_line) = lines_result {
-                co.yield_(current_line).await;
+            if result.malformed_escape {
+                options.handle_anomaly(Error::MalformedEscape, location)?;
+            }
+
+            let mark = if split_at_shell_integration_marks {
+                get_shell_integration_mark(&ready_output)
+            } else {
+                None
+            };
+
+            if let Some(mark) = mark {
+                // The rest of the line start after the mark with the same style
+                let next_line = Line {
+                    location_in_file: end_of_line_index,
+                    spans: vec![current_line
+                        .spans
+                        .last()
+                        .map(Span::clone_without_text)
+                        .unwrap_or_else(Span::empty)],
+                };
+
+                let mut line_before_mark = std::mem::replace(&mut current_line, next_line);
+                line_before_mark.spans.retain(|span| !span.text.is_empty());
 
-                current_line = next_line;
+                // Nothing before the mark (e.g. mark at the start of the line)
+                if !line_before_mark.spans.is_empty() {
+                    co.yield_(Ok(LineOrMark::Line(line_before_mark))).await;
+                }
 
-                lines_result = convert_ansi_output_lines_of_spans_continues(
-                    None,
+                co.yield_(Ok(LineOrMark::ShellIntegrationMark(mark))).await;
+            } else {
+                let mut lines_result = convert_ansi_output_lines_of_spans_continues(
+                    Some(ready_output),
                     &mut current_line,
                     end_of_line_index,
-                );
+                    location,
+                    &options,
+                )?;
+
+                while let ResultType::Parse(next_line) = lines_result {
+                    co.yield_(Ok(LineOrMark::Line(current_line))).await;
+
+                    current_line = next_line;
+
+                    lines_result = convert_ansi_output_lines_of_spans_continues(
+                        None,
+                        &mut current_line,
+                        end_of_line_index,
+                        location,
+                        &options,
+                    )?;
+                }
             }
 
             pending = result.pending_string;
@@ -67,6 +143,13 @@ async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<L
         pending_string = result.pending_string.to_vec();
     }
 
+    // Unfinished escape code at the end is treated as text
+    let location = end_of_line_index;
+
+    if !pending_string.is_empty() {
+        options.handle_anomaly(Error::UnterminatedEscape, location)?;
+    }
+
     let ready_output = Output::TextBlock(Text {
         text: pending_string.as_slice(),
     });
@@ -77,10 +160,12 @@ async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<L
         Some(ready_output),
         &mut current_line,
         end_of_line_index,
-    );
+        location,
+        &options,
+    )?;
 
     while let ResultType::Parse(next_line) = lines_result {
-        co.yield_(current_line).await;
+        co.yield_(Ok(LineOrMark::Line(current_line))).await;
 
         current_line = next_line;
 
@@ -88,21 +173,157 @@ async fn read_ansi_file_to_lines_producer(options: ReadAnsiFileOptions, co: Co<L
             None,
             &mut current_line,
             end_of_line_index,
-        );
+            location,
+            &options,
+        )?;
+    }
+
+    remove_empty_last_span(&mut current_line.spans, &options);
+
+    // Yielding the last line
+    // when splitting at marks, empty last line is just the end of the text after the last mark or new line
+    if !split_at_shell_integration_marks || !current_line.spans.is_empty() {
+        co.yield_(Ok(LineOrMark::Line(current_line))).await;
     }
 
-    let last_span = current_line.spans.last();
+    return Ok(());
+}
+
+// The mapped file is owned by the producer so it lives as long as the lines are parsed from it
+async fn read_mapped_file_to_lines_producer(
+    mapped_file: MappedFile,
+    options: ParseOptions,
+    co: Co<Result<Line, Error>>,
+) {
+    buffer_to_lines_producer(mapped_file.as_bytes(), options, co).await;
+}
+
+// Only the chunks parser splits the lines at the shell integration marks,
+// so the mapped file is parsed in chunks that are copied from it instead of read from the file
+async fn read_mapped_file_to_lines_and_marks_producer(
+    mapped_file: MappedFile,
+    chunk_size_in_bytes: usize,
+    options: ParseOptions,
+    co: Co<Result<LineOrMark, Error>>,
+) {
+    let chunks = mapped_file.as_bytes().chunks(chunk_size_in_bytes).map(|chunk| Ok(chunk.to_vec()));
 
-    if let Some(last_span) = last_span {
-        if last_span.text.is_empty() {
-            current_line.spans.pop();
+    read_ansi_file_to_lines_producer(chunks, options, true, co).await;
+}
+
+pub fn read_ansi_file_to_lines(options: ReadAnsiFileOptions) -> Result<impl Iterator<Item=Result<Line, Error>>, Error> {
+    let parse_options = options.parse_options;
+
+    return match open_file_input(options.file_options)? {
+        FileInput::Chunks(file_reader) => Ok(Either::Left(only_lines(
+            Gen::new(|co| read_ansi_file_to_lines_producer(file_reader, parse_options, false, co)).into_iter(),
+        ))),
+        FileInput::Mapped(mapped_file) => Ok(Either::Right(
+            Gen::new(|co| read_mapped_file_to_lines_producer(mapped_file, parse_options, co)).into_iter(),
+        )),
+    };
+}
+
+// Same as `read_ansi_file_to_lines` but for any reader (e.g. stdin)
+pub fn read_ansi_to_lines(reader: impl Read, options: ParseOptions) -> impl Iterator<Item=Result<Line, Error>> {
+    let chunks = ChunkReader::new(reader);
+
+    return only_lines(Gen::new(|co| read_ansi_file_to_lines_producer(chunks, options, false, co)).into_iter());
+}
+
+// Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
+async fn follow_ansi_file_to_lines_producer(
+    follow_reader: FollowReader,
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+    co: Co<Result<LineOrMark, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_follow_ansi_file_to_lines(follow_reader, options, follow_options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
+
+async fn try_follow_ansi_file_to_lines(
+    mut follow_reader: FollowReader,
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+    co: &Co<Result<LineOrMark, Error>>,
+) -> Result<(), Error> {
+    let mut parse_options = options.parse_options;
+
+    loop {
+        // The parser state (current style and unfinished escape code) is kept while waiting for more bytes
+        try_read_ansi_file_to_lines(&mut follow_reader, parse_options.clone(), false, co).await?;
+
+        if follow_reader.end() != Some(FollowEnd::Restart) {
+            return Ok(());
         }
+
+        // The new file has nothing from the old one, so start from the beginning without any style
+        follow_reader = FollowReader::new(
+            options.file_options.file_path.clone(),
+            0,
+            options.file_options.chunk_size_in_bytes,
+            follow_options.clone(),
+        )?;
+        parse_options = parse_options.with_initial_span(Span::empty());
     }
+}
 
-    // Yielding the last line
-    co.yield_(current_line).await;
+// Same as `read_ansi_file_to_lines` but like `tail -f`, lines are returned as they are written to the file
+// and the file is read again from the start when it's truncated or replaced (e.g. log rotation),
+// `to_bytes` is ignored and the file must not be compressed
+pub fn follow_ansi_file_to_lines(
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+) -> Result<impl Iterator<Item=Result<Line, Error>>, Error> {
+    let follow_reader = FollowReader::new(
+        options.file_options.file_path.clone(),
+        options.file_options.from_bytes.unwrap_or(0),
+        options.file_options.chunk_size_in_bytes,
+        follow_options.clone(),
+    )?;
+
+    return Ok(only_lines(
+        Gen::new(|co| follow_ansi_file_to_lines_producer(follow_reader, options, follow_options, co)).into_iter(),
+    ));
 }
 
-pub fn read_ansi_file_to_lines(options: ReadAnsiFileOptions) -> impl Iterator<Item=Line> {
-    return Gen::new(|co| read_ansi_file_to_lines_producer(options, co)).into_iter();
+fn only_lines(
+    lines_and_marks: impl Iterator<Item=Result<LineOrMark, Error>>,
+) -> impl Iterator<Item=Result<Line, Error>> {
+    return lines_and_marks.filter_map(|item| match item {
+        Ok(LineOrMark::Line(line)) => Some(Ok(line)),
+        Ok(LineOrMark::ShellIntegrationMark(_)) => None,
+        Err(error) => Some(Err(error)),
+    });
+}
+
+// Same as `read_ansi_file_to_lines` but lines are split at shell integration marks (OSC 133) and the marks are returned as well
+pub fn read_ansi_file_to_lines_and_marks(
+    options: ReadAnsiFileOptions,
+) -> Result<impl Iterator<Item=Result<LineOrMark, Error>>, Error> {
+    let parse_options = options.parse_options;
+    let chunk_size_in_bytes = options.file_options.chunk_size_in_bytes.unwrap_or(DEFAULT_CHUNK_SIZE_IN_BYTES).max(1);
+
+    return match open_file_input(options.file_options)? {
+        FileInput::Chunks(file_reader) => Ok(Either::Left(
+            Gen::new(|co| read_ansi_file_to_lines_producer(file_reader, parse_options, true, co)).into_iter(),
+        )),
+        FileInput::Mapped(mapped_file) => Ok(Either::Right(
+            Gen::new(|co| read_mapped_file_to_lines_and_marks_producer(mapped_file, chunk_size_in_bytes, parse_options, co))
+                .into_iter(),
+        )),
+    };
+}
+
+// Same as `read_ansi_file_to_lines_and_marks` but for any reader (e.g. stdin)
+pub fn read_ansi_to_lines_and_marks(
+    reader: impl Read,
+    options: ParseOptions,
+) -> impl Iterator<Item=Result<LineOrMark, Error>> {
+    let chunks = ChunkReader::new(reader);
+
+    return Gen::new(|co| read_ansi_file_to_lines_producer(chunks, options, true, co)).into_iter();
 }
@@ -1,30 +1,52 @@
+use std::io::Read;
+
 use genawaiter::sync::{Co, Gen};
+use itertools::Either;
 
-use crate::files::file_reader::FileReader;
+use crate::error::Error;
+use crate::files::chunk_reader::ChunkReader;
+use crate::files::follow_reader::{FollowEnd, FollowOptions, FollowReader};
+use crate::files::mapped_file::{open_file_input, FileInput, MappedFile};
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_spans::convert_ansi_output_to_spans_continues;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_spans::ResultType;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::Output;
 use crate::parse_ansi_text::raw_ansi_parse::Text;
+use crate::parse_file::text_to_spans::buffer_to_spans_producer;
 use crate::parse_file::types::ReadAnsiFileOptions;
 
-
 // Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
-async fn read_ansi_file_to_spans_producer(options: ReadAnsiFileOptions, co: Co<Span>) {
-    let file_reader = FileReader::new(options.file_options);
+async fn read_ansi_file_to_spans_producer(
+    chunks: impl Iterator<Item=Result<Vec<u8>, Error>>,
+    options: ParseOptions,
+    co: Co<Result<Span, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_read_ansi_file_to_spans(chunks, options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
 
+async fn try_read_ansi_file_to_spans(
+    chunks: impl Iterator<Item=Result<Vec<u8>, Error>>,
+    options: ParseOptions,
+    co: &Co<Result<Span, Error>>,
+) -> Result<(), Error> {
     let mut pending_string: Vec<u8> = vec![];
 
+    // The location of the pending string in the text
+    let mut location: usize = 0;
+
     let mut current_span: Span = options
-        .parse_options
         .initial_span
         .clone()
         .replace_default_color_with_none();
 
-    for item in file_reader {
-        let mut value = item;
+    for item in chunks {
+        let mut value = item?;
 
         if pending_string.is_empty() {
             pending_string = value;
@@ -36,12 +58,16 @@ async fn read_ansi_file_to_spans_producer(options: ReadAnsiFileOptions, co: Co<S
         let mut result: ParseAnsiResult = parse_ansi_continues(pending);
 
         while let Some(ready_output) = result.output {
+            if result.malformed_escape {
+                options.handle_anomaly(Error::MalformedEscape, location)?;
+            }
+
             let span_result =
-                convert_ansi_output_to_spans_continues(ready_output, &mut current_span);
+                convert_ansi_output_to_spans_continues(ready_output, &mut current_span, location, &options)?;
 
             match span_result {
                 ResultType::Parse(next_span) => {
-                    co.yield_(current_span).await;
+                    co.yield_(Ok(current_span)).await;
 
                     current_span = next_span;
                 }
@@ -53,6 +79,7 @@ async fn read_ansi_file_to_spans_producer(options: ReadAnsiFileOptions, co: Co<S
                 }
             }
 
+            location += result.size;
             pending = result.pending_string;
             result = parse_ansi_continues(pending);
         }
@@ -60,18 +87,107 @@ async fn read_ansi_file_to_spans_producer(options: ReadAnsiFileOptions, co: Co<S
         pending_string = result.pending_string.to_vec();
     }
 
+    // Unfinished escape code at the end is treated as text
+    if !pending_string.is_empty() {
+        options.handle_anomaly(Error::UnterminatedEscape, location)?;
+    }
+
     // Add last span if it has text
     if !current_span.text.is_empty() {
         let ready_output = Output::TextBlock(Text {
             text: pending_string.as_slice(),
         });
 
-        convert_ansi_output_to_spans_continues(ready_output, &mut current_span);
+        convert_ansi_output_to_spans_continues(ready_output, &mut current_span, location, &options)?;
+
+        co.yield_(Ok(current_span)).await;
+    }
+
+    return Ok(());
+}
+
+// The mapped file is owned by the producer so it lives as long as the spans are parsed from it
+async fn read_mapped_file_to_spans_producer(
+    mapped_file: MappedFile,
+    options: ParseOptions,
+    co: Co<Result<Span, Error>>,
+) {
+    buffer_to_spans_producer(mapped_file.as_bytes(), options, co).await;
+}
+
+pub fn read_ansi_file_to_spans(options: ReadAnsiFileOptions) -> Result<impl Iterator<Item=Result<Span, Error>>, Error> {
+    let parse_options = options.parse_options;
+
+    return match open_file_input(options.file_options)? {
+        FileInput::Chunks(file_reader) => Ok(Either::Left(
+            Gen::new(|co| read_ansi_file_to_spans_producer(file_reader, parse_options, co)).into_iter(),
+        )),
+        FileInput::Mapped(mapped_file) => Ok(Either::Right(
+            Gen::new(|co| read_mapped_file_to_spans_producer(mapped_file, parse_options, co)).into_iter(),
+        )),
+    };
+}
+
+// Same as `read_ansi_file_to_spans` but for any reader (e.g. stdin)
+pub fn read_ansi_to_spans(reader: impl Read, options: ParseOptions) -> impl Iterator<Item=Result<Span, Error>> {
+    let chunks = ChunkReader::new(reader);
+
+    return Gen::new(|co| read_ansi_file_to_spans_producer(chunks, options, co)).into_iter();
+}
+
+// Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
+async fn follow_ansi_file_to_spans_producer(
+    follow_reader: FollowReader,
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+    co: Co<Result<Span, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_follow_ansi_file_to_spans(follow_reader, options, follow_options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
+
+async fn try_follow_ansi_file_to_spans(
+    mut follow_reader: FollowReader,
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+    co: &Co<Result<Span, Error>>,
+) -> Result<(), Error> {
+    let mut parse_options = options.parse_options;
+
+    loop {
+        // The parser state (current style and unfinished escape code) is kept while waiting for more bytes
+        try_read_ansi_file_to_spans(&mut follow_reader, parse_options.clone(), co).await?;
+
+        if follow_reader.end() != Some(FollowEnd::Restart) {
+            return Ok(());
+        }
 
-        co.yield_(current_span).await;
+        // The new file has nothing from the old one, so start from the beginning without any style
+        follow_reader = FollowReader::new(
+            options.file_options.file_path.clone(),
+            0,
+            options.file_options.chunk_size_in_bytes,
+            follow_options.clone(),
+        )?;
+        parse_options = parse_options.with_initial_span(Span::empty());
     }
 }
 
-pub fn read_ansi_file_to_spans(options: ReadAnsiFileOptions) -> impl Iterator<Item=Span> {
-    return Gen::new(|co| read_ansi_file_to_spans_producer(options, co)).into_iter();
+// Same as `read_ansi_file_to_spans` but like `tail -f`, spans are returned as they are written to the file
+// and the file is read again from the start when it's truncated or replaced (e.g. log rotation),
+// `to_bytes` is ignored and the file must not be compressed
+pub fn follow_ansi_file_to_spans(
+    options: ReadAnsiFileOptions,
+    follow_options: FollowOptions,
+) -> Result<impl Iterator<Item=Result<Span, Error>>, Error> {
+    let follow_reader = FollowReader::new(
+        options.file_options.file_path.clone(),
+        options.file_options.from_bytes.unwrap_or(0),
+        options.file_options.chunk_size_in_bytes,
+        follow_options.clone(),
+    )?;
+
+    return Ok(Gen::new(|co| follow_ansi_file_to_spans_producer(follow_reader, options, follow_options, co)).into_iter());
 }
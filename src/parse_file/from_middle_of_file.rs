@@ -1,11 +1,12 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
+use crate::error::Error;
+use crate::files::compression::{find_seek_point, SeekPoint};
 use crate::files::file_reader::FileReaderOptions;
-use crate::files::file_size::get_file_size;
 
-use crate::mapping_file::read::{get_line_metadata_from_file, get_mapping_file_ready_to_read};
+use crate::mapping_file::create::get_mapping_parse_options;
+use crate::mapping_file::read::MappingFileReader;
 use crate::parse_ansi_text::ansi::types::Span;
-use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_file::file_to_lines_of_spans::read_ansi_file_to_lines;
 use crate::parse_file::types::ReadAnsiFileOptions;
 use crate::types::Line;
@@ -15,6 +16,9 @@ pub struct FromMiddleOfFile {
     pub from_bytes: Option<usize>,
     pub to_bytes: Option<usize>,
     pub initial_span: Option<Span>,
+
+    // Where to start decompressing from when the input file is compressed
+    pub seek_point: Option<SeekPoint>,
 }
 
 // Avoid using when mapping file is not provided and the file is big and the from_line is not the first line
@@ -24,77 +28,81 @@ pub fn get_from_middle_of_the_file_info(
     from_line: Option<usize>,
     to_line: Option<usize>,
     mapping_file: Option<String>,
-) -> FromMiddleOfFile {
+) -> Result<FromMiddleOfFile, Error> {
     if mapping_file.is_none() {
         return get_from_middle_of_the_file_info_without_mapping(input_file, from_line, to_line);
     }
 
     let mapping_file_path = PathBuf::from(OsString::from(mapping_file.clone().unwrap()));
 
-    let (mut file, content_start_offset, line_length) =
-        get_mapping_file_ready_to_read(mapping_file_path).expect("Failed to read mapping file");
+    let mut mapping_file_reader = MappingFileReader::open(mapping_file_path, input_file.clone())?;
+    let seek_points = mapping_file_reader.header().seek_points.clone();
 
     let mut from_bytes: Option<usize> = None;
     let mut to_bytes: Option<usize> = None;
     let mut initial_span: Option<Span> = None;
+    let mut seek_point: Option<SeekPoint> = None;
 
     if from_line.is_some() {
-        let from = get_line_metadata_from_file(
-            &mut file,
-            from_line.unwrap(),
-            content_start_offset,
-            line_length,
-        )
-            .expect("from should exists");
+        let from = mapping_file_reader
+            .get_line_metadata(from_line.unwrap())?
+            .ok_or(Error::LineNotFound(from_line.unwrap()))?;
 
         from_bytes = Some(from.location_in_original_file);
         initial_span = Some(from.initial_span);
+
+        if !seek_points.is_empty() {
+            seek_point = Some(find_seek_point(&seek_points, from.location_in_original_file));
+        }
     }
 
     if to_line.is_some() {
-        let to = get_line_metadata_from_file(
-            &mut file,
-            // + 1 to get the line after the last line to have the to bytes
-            to_line.unwrap() + 1,
-            content_start_offset,
-            line_length,
-        );
-
-        if to.is_none() {
-            // File size if the line does not exist or it's the last line in the file
-            to_bytes = Some(get_file_size(input_file));
-        } else {
-            to_bytes = Some(to.unwrap().location_in_original_file - 1);
+        // + 1 to get the line after the last line to have the to bytes
+        let to = mapping_file_reader.get_line_metadata(to_line.unwrap() + 1)?;
+
+        // Read until the end of the file if the line does not exist or it's the last line in the file
+        if let Some(to) = to {
+            to_bytes = Some(to.location_in_original_file - 1);
         }
     };
 
-    return FromMiddleOfFile {
+    return Ok(FromMiddleOfFile {
         from_bytes,
         to_bytes,
         initial_span,
-    };
+        seek_point,
+    });
 }
 
 pub fn get_from_middle_of_the_file_info_without_mapping(
     input_file: PathBuf,
     from_line: Option<usize>,
     to_line: Option<usize>,
-) -> FromMiddleOfFile {
+) -> Result<FromMiddleOfFile, Error> {
     if from_line.is_none() && to_line.is_none() {
-        return FromMiddleOfFile {
+        return Ok(FromMiddleOfFile {
             from_bytes: None,
             to_bytes: None,
             initial_span: None,
-        };
+            seek_point: None,
+        });
     }
 
+    let file_path = match input_file.to_str() {
+        Some(file_path) => file_path.to_string(),
+        None => return Err(Error::InvalidPath(input_file)),
+    };
+
     let file_reader_options = FileReaderOptions {
-        file_path: input_file.clone().into_os_string().into_string().expect("Failed to convert path"),
+        file_path,
         from_bytes: None,
         chunk_size_in_bytes: Some(1024 * 1024 * 10), // 10MB
         to_bytes: None,
+        seek_point: None,
+        backend: None,
     };
-    let parse_options = ParseOptions::default();
+    // Keep the style of empty lines so starting from one does not lose it
+    let parse_options = get_mapping_parse_options();
 
     let options = ReadAnsiFileOptions {
         file_options: file_reader_options,
@@ -102,7 +110,7 @@ pub fn get_from_middle_of_the_file_info_without_mapping(
     };
 
 
-    let mut lines_iterator: Box<dyn Iterator<Item = Line>> = Box::new(read_ansi_file_to_lines(options));
+    let mut lines_iterator: Box<dyn Iterator<Item = Result<Line, Error>>> = Box::new(read_ansi_file_to_lines(options)?);
 
     if from_line.is_some() {
         lines_iterator = Box::new(lines_iterator.skip(from_line.unwrap()));
@@ -115,7 +123,7 @@ pub fn get_from_middle_of_the_file_info_without_mapping(
         lines_iterator = Box::new(lines_iterator.take(number_of_lines + 1));
     }
 
-    let lines = lines_iterator.collect::<Vec<Line>>();
+    let lines = lines_iterator.collect::<Result<Vec<Line>, Error>>()?;
 
     let from = lines.first();
 
@@ -134,18 +142,17 @@ pub fn get_from_middle_of_the_file_info_without_mapping(
     if to_line.is_some() {
         let got_all_lines = lines.len() == to_line.unwrap() - from_line.unwrap_or(0) + 1;
 
+        // Read until the end of the file if the line does not exist or it's the last line in the file
         if got_all_lines {
             // the line after requested line - 1 to get the end of the requested line
             to_bytes = Some(lines.last().unwrap().location_in_file - 1);
-        } else {
-            // File size if the line does not exist or it's the last line in the file
-            to_bytes = Some(get_file_size(input_file));
         }
     }
 
-    return FromMiddleOfFile {
+    return Ok(FromMiddleOfFile {
         from_bytes,
         to_bytes,
         initial_span,
-    };
+        seek_point: None,
+    });
 }
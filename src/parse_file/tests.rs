@@ -1,19 +1,43 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
     use pretty_assertions::assert_eq;
     use tempfile::NamedTempFile;
     use test_case::test_case;
 
-    use crate::files::file_reader::FileReaderOptions;
+    use crate::error::Error;
+    use crate::files::compression::{compress, Compression};
+    use crate::files::file_reader::{FileReaderBackend, FileReaderOptions};
+    use crate::files::follow_reader::FollowOptions;
+    use crate::mapping_file::create::{create_mapping_file_from_input_path, create_mapping_file_with_layout, create_sparse_mapping_file};
+    use crate::mapping_file::header::MappingLayout;
+    use crate::output::html::{span_to_html, HtmlOptions, HtmlStyles};
     use crate::parse_ansi_text::ansi::colors::*;
     use crate::parse_ansi_text::ansi::constants::*;
+    use crate::parse_ansi_text::ansi::hyperlink::Hyperlink;
     use crate::parse_ansi_text::ansi::style::*;
+    use crate::parse_ansi_text::ansi::theme::Theme;
     use crate::parse_ansi_text::ansi::types::*;
-    use crate::parse_ansi_text::parse_options::ParseOptions;
-    use crate::parse_file::file_to_lines_of_spans::{read_ansi_file_to_lines};
-    use crate::parse_file::file_to_spans::read_ansi_file_to_spans;
+    use crate::parse_ansi_text::parse_options::{DiagnosticsCollector, ParseMode, ParseOptions};
+    use crate::parse_ansi_text::parse_text_matching_single_span::parse_text_matching_single_span;
+    use crate::parse_ansi_text::raw_ansi_parse::parsers::MAX_CONTROL_STRING_PAYLOAD_LENGTH;
+    use crate::parse_file::file_to_command_blocks::{
+        read_ansi_file_to_command_block_parts, read_ansi_file_to_command_blocks, read_ansi_to_command_block_parts,
+        read_ansi_to_command_blocks,
+    };
+    use crate::parse_file::file_to_lines_of_spans::{follow_ansi_file_to_lines, read_ansi_file_to_lines, read_ansi_to_lines};
+    use crate::parse_file::file_to_spans::{follow_ansi_file_to_spans, read_ansi_file_to_spans, read_ansi_to_spans};
+    use crate::parse_file::from_middle_of_file::get_from_middle_of_the_file_info;
+    use crate::parse_file::text_to_lines_of_span_refs::buffer_to_line_refs;
+    use crate::parse_file::text_to_lines_of_spans::buffer_to_lines;
+    use crate::parse_file::text_to_span_refs::buffer_to_span_refs;
+    use crate::parse_file::text_to_spans::buffer_to_spans;
     use crate::parse_file::types::ReadAnsiFileOptions;
-    use crate::types::Line;
+    use crate::types::{CommandBlock, CommandBlockPart, Line};
 
     // TODO - run all tests here on the buffer parse as well
 
@@ -44,10 +68,13 @@ mod tests {
                 chunk_size_in_bytes: None,
                 from_bytes: None,
                 to_bytes: None,
+                seek_point: None,
+                backend: None,
             },
-        });
+        })
+        .expect("read file failed");
 
-        return spans_iterator.collect();
+        return spans_iterator.collect::<Result<Vec<Span>, Error>>().expect("parse failed");
     }
 
     fn parse_ansi_text(input: &str) -> Vec<Span> {
@@ -67,10 +94,13 @@ mod tests {
                 chunk_size_in_bytes: None,
                 from_bytes: None,
                 to_bytes: None,
+                seek_point: None,
+                backend: None,
             },
-        });
+        })
+        .expect("read file failed");
 
-        return lines_iterator.collect();
+        return lines_iterator.collect::<Result<Vec<Line>, Error>>().expect("parse failed");
     }
 
     #[test]
@@ -200,6 +230,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             text_style: TextStyle::None,
         }];
@@ -217,6 +249,8 @@ mod tests {
             .join("");
         let expected = vec![Span {
             bg_color: Color::Red,
+            underline_color: Color::None,
+            link: None,
 
             color: Color::None,
             text: "Hello, world!".to_string().into_bytes(),
@@ -234,6 +268,8 @@ mod tests {
 
             color: Color::None,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text: "Hello, world!".to_string().into_bytes(),
             text_style: TextStyle::None,
         }];
@@ -259,6 +295,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             text_style: TextStyle::None,
         }];
@@ -277,6 +315,8 @@ mod tests {
             .join("");
         let expected = vec![Span {
             bg_color: Color::Red,
+            underline_color: Color::None,
+            link: None,
 
             color: Color::None,
             text: "Hello, world!".to_string().into_bytes(),
@@ -294,6 +334,8 @@ mod tests {
 
             color: Color::None,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text: "Hello, world!".to_string().into_bytes(),
             text_style: TextStyle::None,
         }];
@@ -315,6 +357,8 @@ mod tests {
 
             color: Color::None,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             text: "Hello, world!".to_string().into_bytes(),
         }];
@@ -341,6 +385,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -349,6 +395,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -372,6 +420,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -380,6 +430,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -400,6 +452,8 @@ mod tests {
         let expected = vec![
             Span {
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
 
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
@@ -408,6 +462,8 @@ mod tests {
             },
             Span {
                 bg_color: Color::Red,
+                underline_color: Color::None,
+                link: None,
 
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
@@ -431,6 +487,8 @@ mod tests {
         let expected = vec![
             Span {
                 bg_color: Color::Rgb(188, 29, 68),
+                underline_color: Color::None,
+                link: None,
 
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
@@ -439,6 +497,8 @@ mod tests {
             },
             Span {
                 bg_color: Color::Rgb(255, 19, 94),
+                underline_color: Color::None,
+                link: None,
 
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
@@ -466,6 +526,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
             },
             Span {
@@ -474,6 +536,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
             },
         ];
@@ -496,6 +560,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 color: Color::None,
             },
@@ -504,6 +570,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 color: Color::None,
             },
@@ -534,6 +602,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::White,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
                 text_style: TextStyle::Italic,
             },
@@ -542,6 +612,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::White,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
                 text_style: TextStyle::Italic,
             },
@@ -565,6 +637,8 @@ mod tests {
         let expected = vec![
             Span {
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
 
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::White,
@@ -573,6 +647,8 @@ mod tests {
             },
             Span {
                 bg_color: Color::Red,
+                underline_color: Color::None,
+                link: None,
 
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::White,
@@ -603,6 +679,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::White,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::Italic,
             },
             Span {
@@ -611,6 +689,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::White,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::Italic,
             },
         ];
@@ -637,6 +717,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::White,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
             },
             Span {
@@ -645,6 +727,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::White,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
             },
         ];
@@ -670,6 +754,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -678,6 +764,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -697,6 +785,8 @@ mod tests {
         let expected = vec![
             Span {
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
 
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
@@ -705,6 +795,8 @@ mod tests {
             },
             Span {
                 bg_color: Color::Red,
+                underline_color: Color::None,
+                link: None,
 
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
@@ -725,6 +817,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
             },
             Span {
@@ -733,6 +827,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::None,
             },
         ];
@@ -748,6 +844,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 color: Color::None,
             },
@@ -756,6 +854,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 color: Color::None,
             },
@@ -785,6 +885,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
                 text_style: TextStyle::Italic,
             },
@@ -793,6 +895,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Bold,
                 text_style: TextStyle::Italic,
             },
@@ -815,6 +919,8 @@ mod tests {
         let expected = vec![
             Span {
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
 
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::Red,
@@ -823,6 +929,8 @@ mod tests {
             },
             Span {
                 bg_color: Color::Red,
+                underline_color: Color::None,
+                link: None,
 
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::Red,
@@ -852,6 +960,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::Red,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::Italic,
             },
             Span {
@@ -860,6 +970,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::Red,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 text_style: TextStyle::Italic,
             },
         ];
@@ -885,6 +997,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::Red,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Dim,
             },
             Span {
@@ -893,6 +1007,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::Red,
                 bg_color: Color::Black,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::Dim,
             },
         ];
@@ -911,6 +1027,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             text_style: TextStyle::None,
         }];
@@ -922,6 +1040,8 @@ mod tests {
         let input = ["Hello, world!", RED_BACKGROUND_CODE].join("");
         let expected = vec![Span {
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
 
             text: "Hello, world!".to_string().into_bytes(),
             color: Color::None,
@@ -940,6 +1060,8 @@ mod tests {
             text: "Hello, world!".to_string().into_bytes(),
             color: Color::None,
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             text_style: TextStyle::None,
         }];
         assert_eq!(parse_ansi_text(&input), expected);
@@ -953,6 +1075,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             color: Color::None,
         }];
@@ -982,6 +1106,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             color: Color::None,
             brightness: Brightness::None,
         }];
@@ -1011,6 +1137,8 @@ mod tests {
                 text: "Hello, world!".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
             },
             Span {
@@ -1019,6 +1147,8 @@ mod tests {
                 text: "How are you?".to_string().into_bytes(),
                 color: Color::None,
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
             },
         ];
@@ -1050,6 +1180,8 @@ mod tests {
             text: "Hello, world!How are you?".to_string().into_bytes(),
             color: Color::Red,
             bg_color: Color::Green,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::Dim,
         }];
         assert_eq!(parse_ansi_text(&input), expected);
@@ -1067,6 +1199,8 @@ mod tests {
 
             text: "Hello, world!".to_string().into_bytes(),
             bg_color: Color::None,
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::None,
             text_style: TextStyle::None,
         }];
@@ -1089,6 +1223,8 @@ mod tests {
 
                 text: "Hello, world!".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -1097,6 +1233,8 @@ mod tests {
 
                 text: "How are you?".to_string().into_bytes(),
                 bg_color: Color::None,
+                underline_color: Color::None,
+                link: None,
                 brightness: Brightness::None,
                 text_style: TextStyle::None,
             },
@@ -1133,6 +1271,8 @@ mod tests {
                     text: "Hello, world!".to_string().into_bytes(),
                     color: Color::Rgb(255, 19, 94),
                     bg_color: Color::Rgb(188, 29, 68),
+                    underline_color: Color::None,
+                    link: None,
                     brightness: Brightness::Bold,
                     text_style: TextStyle::Italic | TextStyle::Underline,
                 }],
@@ -1143,6 +1283,8 @@ mod tests {
                     text: "How are you?".to_string().into_bytes(),
                     color: Color::Rgb(255, 19, 94),
                     bg_color: Color::Rgb(188, 29, 68),
+                    underline_color: Color::None,
+                    link: None,
                     brightness: Brightness::Bold,
                     text_style: TextStyle::Italic | TextStyle::Underline,
                 }],
@@ -1173,12 +1315,1537 @@ mod tests {
             text: "Hello, world!".to_string().into_bytes(),
             color: Color::Rgb(255, 19, 94),
             bg_color: Color::Rgb(188, 29, 68),
+            underline_color: Color::None,
+            link: None,
             brightness: Brightness::Bold,
             text_style: TextStyle::Italic | TextStyle::Underline,
         }];
         assert_eq!(parse_ansi_text(&input), expected);
     }
 
+    #[test_case("\x1b[1;4;31;42m"; "graphics mode with 4 parameters")]
+    #[test_case("\x1b[1;38;2;10;20;30m"; "graphics mode with 6 parameters")]
+    #[test_case("\x1b[?1049h"; "private mode")]
+    #[test_case("\x1b[>4;2 q"; "intermediate bytes")]
+    fn control_sequences_should_not_be_part_of_the_text(control_sequence: &str) {
+        let input = [control_sequence, "Hello, world!", RESET_CODE].join("");
+
+        let texts: Vec<Vec<u8>> = parse_ansi_text(&input)
+            .into_iter()
+            .map(|span| span.text)
+            .collect();
+
+        assert_eq!(texts, vec!["Hello, world!".to_string().into_bytes()]);
+    }
+
+    #[test_case("\x1b[38:2::255:0:10m", &RGB_FOREGROUND_CODE(255, 0, 10); "RGB foreground with empty color space")]
+    #[test_case("\x1b[38:2:0:255:0:10m", &RGB_FOREGROUND_CODE(255, 0, 10); "RGB foreground with color space")]
+    #[test_case("\x1b[38:2:255:0:10m", &RGB_FOREGROUND_CODE(255, 0, 10); "RGB foreground without color space")]
+    #[test_case("\x1b[48:2::255:0:10m", &RGB_BACKGROUND_CODE(255, 0, 10); "RGB background")]
+    #[test_case("\x1b[38:5:196m", "\x1b[38;5;196m"; "8 bit foreground")]
+    #[test_case("\x1b[48:5:196m", "\x1b[48;5;196m"; "8 bit background")]
+    #[test_case("\x1b[4:1m", UNDERLINE_CODE; "single underline")]
+    #[test_case("\x1b[4:2m", DOUBLE_UNDERLINE_CODE; "double underline")]
+    fn colon_separated_sub_parameters_should_have_same_style_as_semicolon_form(colon_form: &str, semicolon_form: &str) {
+        let colon_input = [colon_form, "Hello, world!", RESET_CODE].join("");
+        let semicolon_input = [semicolon_form, "Hello, world!", RESET_CODE].join("");
+
+        assert_eq!(parse_ansi_text(&colon_input), parse_ansi_text(&semicolon_input));
+    }
+
+    #[test]
+    fn eight_bit_theme_color_should_be_resolved_from_the_theme_in_html() {
+        let input = ["\x1b[38;5;1m", "Hello", RESET_CODE].join("");
+        let spans = parse_ansi_text(&input);
+        assert_eq!(spans, vec![Span::empty().with_color(Color::EightBit(1)).with_text(b"Hello".to_vec())]);
+
+        let theme = Theme::from_name("solarized");
+        let inline = HtmlOptions {
+            theme,
+            ..HtmlOptions::default()
+        };
+        assert_eq!(span_to_html(&spans[0], &inline), "<span style=\"color: rgb(220, 50, 47);\">Hello</span>");
+
+        let classes = HtmlOptions {
+            styles: Some(HtmlStyles::Classes),
+            theme,
+            ..HtmlOptions::default()
+        };
+        assert_eq!(span_to_html(&spans[0], &classes), "<span class=\"ansi-fg-red\">Hello</span>");
+    }
+
+
+    // ----------------------------------
+    // Multiple attributes in single sequence
+    // ----------------------------------
+
+    #[test]
+    fn should_apply_all_attributes_in_single_sequence() {
+        let input = ["\x1b[1;4;31;42m", "Hello, world!", RESET_CODE].join("");
+        let expected = vec![Span::empty()
+            .with_brightness(Brightness::Bold)
+            .with_text_style(TextStyle::Underline)
+            .with_color(Color::Red)
+            .with_bg_color(Color::Green)
+            .with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn should_skip_extended_color_parameters_when_applying_attributes_in_single_sequence() {
+        let input = ["\x1b[1;38;2;10;20;30;48;5;4;4m", "Hello, world!", RESET_CODE].join("");
+        let expected = vec![Span::empty()
+            .with_brightness(Brightness::Bold)
+            .with_color(Color::Rgb(10, 20, 30))
+            .with_bg_color(Color::EightBit(4))
+            .with_text_style(TextStyle::Underline)
+            .with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn should_apply_attributes_after_colon_separated_extended_color() {
+        let input = ["\x1b[38:2::10:20:30;1m", "Hello, world!", RESET_CODE].join("");
+        let expected = vec![Span::empty()
+            .with_color(Color::Rgb(10, 20, 30))
+            .with_brightness(Brightness::Bold)
+            .with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn attributes_after_reset_in_the_same_sequence_should_apply_to_the_next_span() {
+        let input = [BOLD_CODE, "Hello, ", "\x1b[0;32m", "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_brightness(Brightness::Bold)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_color(Color::Green)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn graphics_mode_without_parameters_should_reset() {
+        let input = [RED_FOREGROUND_CODE, "Hello, ", "\x1b[m", "world!"].join("");
+        let expected = vec![
+            Span::empty()
+                .with_color(Color::Red)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty().with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn should_apply_all_attributes_in_single_sequence_when_split_by_lines() {
+        let input = [BOLD_CODE, "\x1b[0;4;31m", "Hello, world!\nHow are you?", RESET_CODE].join("");
+        let expected_span = Span::empty()
+            .with_text_style(TextStyle::Underline)
+            .with_color(Color::Red);
+        let expected = vec![
+            Line {
+                spans: vec![expected_span.clone().with_text("Hello, world!".to_string().into_bytes())],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![expected_span.clone().with_text("How are you?".to_string().into_bytes())],
+                location_in_file: input.find("How").unwrap(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Turning off single attribute
+    // ----------------------------------
+
+    #[test_case(TextStyle::Italic, ITALIC_CODE, NOT_ITALIC_CODE; "Italic text")]
+    #[test_case(TextStyle::Underline, UNDERLINE_CODE, NOT_UNDERLINED_CODE; "Underline text")]
+    #[test_case(TextStyle::Underline, UNDERLINE_CODE, "\x1b[4:0m"; "Underline text with no underline style")]
+    #[test_case(TextStyle::Inverse, INVERSE_CODE, NOT_INVERSE_CODE; "Inverse text")]
+    #[test_case(TextStyle::Strikethrough, STRIKETHROUGH_CODE, NOT_STRIKETHROUGH_CODE; "Strikethrough text")]
+    fn turning_off_text_style_after_text_should_create_new_span_without_the_text_style(
+        text_style: TextStyle,
+        text_style_code: &str,
+        turn_off_code: &str,
+    ) {
+        let input = [text_style_code, "Hello, ", turn_off_code, "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_text_style(text_style)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty().with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test_case(Brightness::Bold, BOLD_CODE; "Bold text")]
+    #[test_case(Brightness::Dim, DIM_CODE; "Dim text")]
+    fn normal_intensity_after_text_should_create_new_span_without_brightness(
+        brightness: Brightness,
+        brightness_code: &str,
+    ) {
+        let input = [brightness_code, "Hello, ", NORMAL_INTENSITY_CODE, "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_brightness(brightness)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty().with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn turning_off_text_style_should_keep_the_rest_of_the_style() {
+        let input = [
+            RED_FOREGROUND_CODE,
+            BOLD_CODE,
+            ITALIC_CODE,
+            UNDERLINE_CODE,
+            "Hello, ",
+            NOT_ITALIC_CODE,
+            "world!",
+            RESET_CODE,
+        ]
+            .join("");
+        let expected = vec![
+            Span::empty()
+                .with_color(Color::Red)
+                .with_brightness(Brightness::Bold)
+                .with_text_style(TextStyle::Italic | TextStyle::Underline)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_color(Color::Red)
+                .with_brightness(Brightness::Bold)
+                .with_text_style(TextStyle::Underline)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn turning_off_text_style_that_is_not_applied_should_not_create_new_span() {
+        let input = [ITALIC_CODE, "Hello, ", NOT_UNDERLINED_CODE, "world!", RESET_CODE].join("");
+        let expected = vec![Span::empty()
+            .with_text_style(TextStyle::Italic)
+            .with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn turning_off_text_style_should_apply_when_split_by_lines() {
+        let input = [ITALIC_CODE, "Hello, ", NOT_ITALIC_CODE, "world!\nHow are you?", RESET_CODE].join("");
+        let expected = vec![
+            Line {
+                spans: vec![
+                    Span::empty()
+                        .with_text_style(TextStyle::Italic)
+                        .with_text("Hello, ".to_string().into_bytes()),
+                    Span::empty().with_text("world!".to_string().into_bytes()),
+                ],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![Span::empty().with_text("How are you?".to_string().into_bytes())],
+                location_in_file: input.find("How").unwrap(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Extended text attributes
+    // ----------------------------------
+
+    #[test_case(TextStyle::SlowBlink, SLOW_BLINK_CODE, NOT_BLINKING_CODE; "Slow blink text")]
+    #[test_case(TextStyle::RapidBlink, RAPID_BLINK_CODE, NOT_BLINKING_CODE; "Rapid blink text")]
+    #[test_case(TextStyle::Hidden, HIDDEN_CODE, NOT_HIDDEN_CODE; "Hidden text")]
+    #[test_case(TextStyle::Overline, OVERLINE_CODE, NOT_OVERLINED_CODE; "Overline text")]
+    #[test_case(TextStyle::Superscript, SUPERSCRIPT_CODE, NOT_SUPERSCRIPT_OR_SUBSCRIPT_CODE; "Superscript text")]
+    #[test_case(TextStyle::Subscript, SUBSCRIPT_CODE, NOT_SUPERSCRIPT_OR_SUBSCRIPT_CODE; "Subscript text")]
+    #[test_case(TextStyle::DoubleUnderline, DOUBLE_UNDERLINE_CODE, NOT_UNDERLINED_CODE; "Double underline text")]
+    #[test_case(TextStyle::DoubleUnderline, "\x1b[4:2m", NOT_UNDERLINED_CODE; "Double underline text using sub parameter")]
+    #[test_case(TextStyle::CurlyUnderline, CURLY_UNDERLINE_CODE, NOT_UNDERLINED_CODE; "Curly underline text")]
+    #[test_case(TextStyle::DottedUnderline, DOTTED_UNDERLINE_CODE, NOT_UNDERLINED_CODE; "Dotted underline text")]
+    #[test_case(TextStyle::DashedUnderline, DASHED_UNDERLINE_CODE, "\x1b[4:0m"; "Dashed underline text")]
+    fn extended_text_style_should_be_applied_until_turned_off(
+        text_style: TextStyle,
+        text_style_code: &str,
+        turn_off_code: &str,
+    ) {
+        let input = [text_style_code, "Hello, ", turn_off_code, "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_text_style(text_style)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty().with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn new_underline_style_should_replace_the_previous_underline_style() {
+        let input = [ITALIC_CODE, UNDERLINE_CODE, "Hello, ", CURLY_UNDERLINE_CODE, "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_text_style(TextStyle::Italic | TextStyle::Underline)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_text_style(TextStyle::Italic | TextStyle::CurlyUnderline)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test_case(SLOW_BLINK_CODE, RAPID_BLINK_CODE, TextStyle::SlowBlink, TextStyle::RapidBlink; "rapid blink replace slow blink")]
+    #[test_case(RAPID_BLINK_CODE, SLOW_BLINK_CODE, TextStyle::RapidBlink, TextStyle::SlowBlink; "slow blink replace rapid blink")]
+    #[test_case(SUPERSCRIPT_CODE, SUBSCRIPT_CODE, TextStyle::Superscript, TextStyle::Subscript; "subscript replace superscript")]
+    #[test_case(SUBSCRIPT_CODE, SUPERSCRIPT_CODE, TextStyle::Subscript, TextStyle::Superscript; "superscript replace subscript")]
+    fn exclusive_text_style_should_replace_the_previous_one(
+        first_code: &str,
+        second_code: &str,
+        first_text_style: TextStyle,
+        second_text_style: TextStyle,
+    ) {
+        let input = [ITALIC_CODE, first_code, "Hello, ", second_code, "world!", RESET_CODE].join("");
+        let expected = vec![
+            Span::empty()
+                .with_text_style(TextStyle::Italic | first_text_style)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_text_style(TextStyle::Italic | second_text_style)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+
+        let lines = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(lines[0].spans, expected);
+
+        let span_refs = buffer_to_span_refs(input.as_bytes(), &ParseOptions::default())
+            .map(|span| span.map(|span| span.to_span()))
+            .collect::<Result<Vec<Span>, Error>>()
+            .expect("parse failed");
+        assert_eq!(merge_spans_with_same_style(span_refs), expected);
+
+        // The mapping file has the style of the line start in the same codes
+        let style = [first_code, second_code].join("");
+        assert_eq!(
+            parse_text_matching_single_span(&style).text_style,
+            second_text_style
+        );
+    }
+
+    #[test]
+    fn extended_text_style_should_apply_when_split_by_lines() {
+        let input = [OVERLINE_CODE, DOTTED_UNDERLINE_CODE, "Hello\nworld!", RESET_CODE].join("");
+        let expected = vec![
+            Line {
+                spans: vec![Span::empty()
+                    .with_text_style(TextStyle::Overline | TextStyle::DottedUnderline)
+                    .with_text("Hello".to_string().into_bytes())],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![Span::empty()
+                    .with_text_style(TextStyle::Overline | TextStyle::DottedUnderline)
+                    .with_text("world!".to_string().into_bytes())],
+                location_in_file: input.find("world").unwrap(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Underline color
+    // ----------------------------------
+
+    #[test_case("\x1b[58;2;255;0;10m", Color::Rgb(255, 0, 10); "RGB underline color")]
+    #[test_case("\x1b[58:2::255:0:10m", Color::Rgb(255, 0, 10); "RGB underline color in colon form")]
+    #[test_case("\x1b[58;5;196m", Color::EightBit(196); "8 bit underline color")]
+    #[test_case("\x1b[58:5:196m", Color::EightBit(196); "8 bit underline color in colon form")]
+    fn underline_color_should_be_applied_until_reset_to_default(underline_color_code: &str, underline_color: Color) {
+        let input = [
+            UNDERLINE_CODE,
+            underline_color_code,
+            "Hello, ",
+            DEFAULT_UNDERLINE_COLOR_CODE,
+            "world!",
+            RESET_CODE,
+        ]
+            .join("");
+        let expected = vec![
+            Span::empty()
+                .with_text_style(TextStyle::Underline)
+                .with_underline_color(underline_color)
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_text_style(TextStyle::Underline)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn underline_color_should_not_affect_the_foreground_and_background_colors() {
+        let input = [RED_FOREGROUND_CODE, "\x1b[4;58;2;0;255;0;42m", "Hello, world!", RESET_CODE].join("");
+        let expected = vec![Span::empty()
+            .with_color(Color::Red)
+            .with_bg_color(Color::Green)
+            .with_text_style(TextStyle::Underline)
+            .with_underline_color(Color::Rgb(0, 255, 0))
+            .with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn underline_color_should_apply_when_split_by_lines() {
+        let input = [UNDERLINE_CODE, "\x1b[58;5;196m", "Hello\nworld!", RESET_CODE].join("");
+        let expected = vec![
+            Line {
+                spans: vec![Span::empty()
+                    .with_text_style(TextStyle::Underline)
+                    .with_underline_color(Color::EightBit(196))
+                    .with_text("Hello".to_string().into_bytes())],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![Span::empty()
+                    .with_text_style(TextStyle::Underline)
+                    .with_underline_color(Color::EightBit(196))
+                    .with_text("world!".to_string().into_bytes())],
+                location_in_file: input.find("world").unwrap(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Hyperlinks
+    // ----------------------------------
+
+    #[test_case("\x1b\\"; "ST terminator")]
+    #[test_case("\x07"; "BEL terminator")]
+    fn hyperlink_should_split_spans_at_link_boundaries(terminator: &str) {
+        let input = [
+            "See ",
+            "\x1b]8;;https://example.com", terminator,
+            "the docs",
+            "\x1b]8;;", terminator,
+            " for more",
+        ]
+            .join("");
+        let expected = vec![
+            Span::empty().with_text("See ".to_string().into_bytes()),
+            Span::empty()
+                .with_link(Some(Hyperlink::new(b"https://example.com".to_vec())))
+                .with_text("the docs".to_string().into_bytes()),
+            Span::empty().with_text(" for more".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn hyperlink_should_keep_its_params() {
+        let input = ["\x1b]8;id=1;https://example.com\x1b\\", "Hello", "\x1b]8;;\x1b\\"].join("");
+        let expected = vec![Span::empty()
+            .with_link(Some(Hyperlink::new(b"https://example.com".to_vec()).with_params(b"id=1".to_vec())))
+            .with_text("Hello".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn hyperlink_should_survive_serializing_back_to_ansi() {
+        let input = [
+            "See ",
+            "\x1b]8;id=1;https://example.com\x07",
+            BOLD_CODE,
+            "the docs",
+            "\x1b]8;;\x07",
+            " for more",
+        ]
+            .join("");
+        let spans = parse_ansi_text(&input);
+
+        let serialized = spans
+            .clone()
+            .into_iter()
+            .map(|span| span.serialize_to_ansi_string())
+            .collect::<Vec<Vec<u8>>>()
+            .concat();
+
+        assert_eq!(parse_ansi_text(&String::from_utf8(serialized).unwrap()), spans);
+    }
+
+    #[test]
+    fn reset_should_not_end_hyperlink() {
+        let input = [
+            "\x1b]8;;https://example.com\x1b\\",
+            RED_FOREGROUND_CODE,
+            "Hello, ",
+            RESET_CODE,
+            "world!",
+            "\x1b]8;;\x1b\\",
+        ]
+            .join("");
+        let link = Some(Hyperlink::new(b"https://example.com".to_vec()));
+        let expected = vec![
+            Span::empty()
+                .with_color(Color::Red)
+                .with_link(link.clone())
+                .with_text("Hello, ".to_string().into_bytes()),
+            Span::empty()
+                .with_link(link)
+                .with_text("world!".to_string().into_bytes()),
+        ];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test]
+    fn hyperlink_should_apply_when_split_by_lines() {
+        let input = ["\x1b]8;;https://example.com\x1b\\", "Hello\nworld!", "\x1b]8;;\x1b\\", "\nBye"].join("");
+        let link = Some(Hyperlink::new(b"https://example.com".to_vec()));
+        let expected = vec![
+            Line {
+                spans: vec![Span::empty()
+                    .with_link(link.clone())
+                    .with_text("Hello".to_string().into_bytes())],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![Span::empty()
+                    .with_link(link)
+                    .with_text("world!".to_string().into_bytes())],
+                location_in_file: input.find("world").unwrap(),
+            },
+            Line {
+                spans: vec![Span::empty().with_text("Bye".to_string().into_bytes())],
+                location_in_file: input.find("Bye").unwrap(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(&input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Control strings (OSC, DCS, APC, PM, SOS)
+    // ----------------------------------
+
+    #[test_case("\x1b]0;Running tests\x07"; "window title with BEL terminator")]
+    #[test_case("\x1b]2;Running tests\x1b\\"; "window title with ST terminator")]
+    #[test_case("\x1b]133;A\x07"; "shell integration mark")]
+    #[test_case("\x1b]777;notify;Done;Tests passed\x1b\\"; "notification")]
+    #[test_case("\x1b]1337;File=inline=1:AAAA\x07"; "iTerm image")]
+    #[test_case("\x1bPq#0;2;0;0;0#1!6~-\x1b\\"; "sixel device control string")]
+    #[test_case("\x1b_Gf=100;AAAA\x1b\\"; "application program command")]
+    #[test_case("\x1b^message\x1b\\"; "privacy message")]
+    #[test_case("\x1bXmessage\x1b\\"; "start of string")]
+    fn control_strings_should_not_be_part_of_the_text(control_string: &str) {
+        let input = ["Hello, ", control_string, "world!"].join("");
+        let expected = vec![Span::empty().with_text("Hello, world!".to_string().into_bytes())];
+        assert_eq!(parse_ansi_text(&input), expected);
+    }
+
+    #[test_case(1; "chunk of single byte")]
+    #[test_case(3; "chunk of 3 bytes")]
+    #[test_case(7; "chunk of 7 bytes")]
+    fn control_strings_should_be_parsed_across_chunk_boundaries(chunk_size: usize) {
+        let input = [
+            "\x1b]2;Running tests\x1b\\",
+            RED_FOREGROUND_CODE,
+            "Hello, ",
+            "\x1bPq#0;2;0;0;0#1!6~-\x1b\\",
+            "world!",
+            "\x1b]133;D;0\x07",
+            RESET_CODE,
+        ]
+            .join("");
+        let expected = vec![Span::empty()
+            .with_color(Color::Red)
+            .with_text("Hello, world!".to_string().into_bytes())];
+
+        let tmp_file_path = create_tmp_file(input);
+        let actual: Vec<Span> = read_ansi_file_to_spans(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes: Some(chunk_size),
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect::<Result<Vec<Span>, Error>>()
+        .expect("parse failed");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unterminated_control_string_should_be_text_after_max_length() {
+        let text = "a".repeat(MAX_CONTROL_STRING_PAYLOAD_LENGTH * 4);
+        let input = ["\x1b]0;", &text, "\n", RED_FOREGROUND_CODE, "Hello", RESET_CODE].join("");
+        let collector = DiagnosticsCollector::new();
+
+        let lines = read_ansi_to_lines(
+            input.as_bytes(),
+            ParseOptions::default().with_mode(ParseMode::Collect(collector.clone())),
+        )
+        .collect::<Result<Vec<Line>, Error>>()
+        .expect("parse failed");
+
+        assert_eq!(
+            lines.iter().map(|line| line.spans.clone()).collect::<Vec<Vec<Span>>>(),
+            vec![
+                vec![Span::empty().with_text(["\x1b]0;", &text].join("").into_bytes())],
+                vec![Span::empty().with_color(Color::Red).with_text(b"Hello".to_vec())],
+            ]
+        );
+        assert_eq!(
+            diagnostics_to_strings(&collector),
+            vec![(0, "Escape character is not followed by a valid sequence".to_string())]
+        );
+    }
+
+    // ----------------------------------
+    // Command blocks (OSC 133)
+    // ----------------------------------
+
+    fn parse_ansi_text_to_command_blocks(input: &str, chunk_size_in_bytes: Option<usize>) -> Vec<CommandBlock> {
+        return parse_ansi_text_to_command_blocks_with_backend(input, chunk_size_in_bytes, None);
+    }
+
+    fn parse_ansi_text_to_command_blocks_with_backend(
+        input: &str,
+        chunk_size_in_bytes: Option<usize>,
+        backend: Option<FileReaderBackend>,
+    ) -> Vec<CommandBlock> {
+        let tmp_file_path = create_tmp_file(input.to_string());
+        let blocks_iterator = read_ansi_file_to_command_blocks(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend,
+            },
+        })
+        .expect("read file failed");
+
+        return blocks_iterator.collect::<Result<Vec<CommandBlock>, Error>>().expect("parse failed");
+    }
+
+    fn create_line(text: &str, location_in_file: usize) -> Line {
+        Line {
+            spans: vec![Span::empty().with_text(text.to_string().into_bytes())],
+            location_in_file,
+        }
+    }
+
+    #[test_case(None; "single chunk")]
+    #[test_case(Some(5); "multiple chunks")]
+    fn shell_integration_marks_should_split_the_session_to_command_blocks(chunk_size_in_bytes: Option<usize>) {
+        let input = [
+            "Script started\n",
+            "\x1b]133;A\x07$ \x1b]133;B\x07echo hi\n\x1b]133;C\x07hi\n\x1b]133;D;0\x07",
+            "\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\false\n\x1b]133;C\x1b\\\x1b]133;D;1\x1b\\",
+        ]
+            .join("");
+
+        let first_prompt = input.find("\x1b]133;A").unwrap() + "\x1b]133;A\x07".len();
+        let second_prompt = input.rfind("\x1b]133;A").unwrap() + "\x1b]133;A\x1b\\".len();
+
+        let expected = vec![
+            CommandBlock {
+                prompt: vec![],
+                command: vec![],
+                output: vec![create_line("Script started", 0)],
+                exit_code: None,
+            },
+            CommandBlock {
+                prompt: vec![create_line("$ ", first_prompt)],
+                command: vec![create_line("echo hi", input.find("echo").unwrap())],
+                output: vec![create_line("hi", input.find("\x1b]133;C").unwrap() + "\x1b]133;C\x07".len())],
+                exit_code: Some(0),
+            },
+            CommandBlock {
+                prompt: vec![create_line("$ ", second_prompt)],
+                command: vec![create_line("false", input.find("false").unwrap())],
+                output: vec![],
+                exit_code: Some(1),
+            },
+        ];
+
+        assert_eq!(parse_ansi_text_to_command_blocks(&input, chunk_size_in_bytes), expected);
+    }
+
+    #[test_case(None; "single chunk")]
+    #[test_case(Some(5); "multiple chunks")]
+    fn mapped_file_should_have_the_same_command_blocks_as_reading_in_chunks(chunk_size_in_bytes: Option<usize>) {
+        let input = "Script started\n\x1b]133;A\x07$ \x1b]133;B\x07ls\n\x1b]133;C\x07a.txt\nb.txt\n\x1b]133;D;0\x07\x1b]133;A\x07$ ";
+
+        assert_eq!(
+            parse_ansi_text_to_command_blocks_with_backend(input, chunk_size_in_bytes, Some(FileReaderBackend::Mmap)),
+            parse_ansi_text_to_command_blocks(input, chunk_size_in_bytes)
+        );
+    }
+
+    #[test]
+    fn command_block_parts_should_return_the_output_lines_before_the_command_finishes() {
+        let input = "\x1b]133;A\x07$ \x1b]133;B\x07tail -f log\n\x1b]133;C\x07first\nsecond\n";
+        let tmp_file_path = create_tmp_file(input.to_string());
+
+        let parts = read_ansi_file_to_command_block_parts(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes: None,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect::<Result<Vec<CommandBlockPart>, Error>>()
+        .expect("parse failed");
+
+        let output_start = input.find("first").unwrap();
+
+        assert_eq!(
+            parts,
+            vec![
+                CommandBlockPart::Start,
+                CommandBlockPart::Prompt(create_line("$ ", input.find("$ ").unwrap())),
+                CommandBlockPart::Command(create_line("tail -f log", input.find("tail").unwrap())),
+                CommandBlockPart::Output(create_line("first", output_start)),
+                CommandBlockPart::Output(create_line("second", output_start + "first\n".len())),
+            ]
+        );
+    }
+
+    #[test]
+    fn command_block_should_keep_the_style_across_the_marks() {
+        let input = [
+            "\x1b]133;A\x07",
+            GREEN_FOREGROUND_CODE,
+            "$ ",
+            "\x1b]133;B\x07",
+            "make\n",
+            "\x1b]133;C\x07",
+            "error\n",
+            RESET_CODE,
+            "\x1b]133;D;2\x07",
+        ]
+            .join("");
+
+        let blocks = parse_ansi_text_to_command_blocks(&input, None);
+
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks[0].is_failed());
+        assert_eq!(
+            blocks[0].command[0].spans,
+            vec![Span::empty()
+                .with_color(Color::Green)
+                .with_text("make".to_string().into_bytes())]
+        );
+        assert_eq!(
+            blocks[0].output[0].spans,
+            vec![Span::empty()
+                .with_color(Color::Green)
+                .with_text("error".to_string().into_bytes())]
+        );
+    }
+
+    #[test]
+    fn shell_integration_marks_should_not_split_lines_when_reading_lines() {
+        let input = "\x1b]133;A\x07$ \x1b]133;B\x07ls\n";
+        let expected = vec![
+            Line {
+                spans: vec![Span::empty().with_text("$ ls".to_string().into_bytes())],
+                location_in_file: 0,
+            },
+            Line {
+                spans: vec![],
+                location_in_file: input.len(),
+            },
+        ];
+
+        let actual = parse_ansi_text_split_by_lines_with_options(input, ParseOptions::default());
+        assert_eq!(actual, expected);
+    }
+
+    // ----------------------------------
+    // Malformed and unsupported sequences
+    // ----------------------------------
+
+    fn parse_ansi_text_with_diagnostics(input: &str) -> (Vec<Span>, Vec<String>) {
+        let diagnostics = Arc::new(Mutex::new(vec![]));
+        let diagnostics_for_sink = diagnostics.clone();
+
+        let spans = parse_ansi_text_with_options(
+            input,
+            ParseOptions::default().with_diagnostics_sink(move |diagnostic| {
+                diagnostics_for_sink.lock().unwrap().push(diagnostic.error.to_string());
+            }),
+        );
+
+        let diagnostics = diagnostics.lock().unwrap().clone();
+
+        return (spans, diagnostics);
+    }
+
+    #[test_case("\x1b[38;7mHello", vec![Span::empty().with_text_style(TextStyle::Inverse).with_text(b"Hello".to_vec())], "Invalid color: 38"; "extended color with unknown color space")]
+    #[test_case("\x1b[38mHello", vec![Span::empty().with_text(b"Hello".to_vec())], "Invalid color: 38"; "extended color without arguments")]
+    #[test_case("\x1b[48;2;1;2mHello", vec![Span::empty().with_text(b"Hello".to_vec())], "Invalid color: 48;2;1;2"; "rgb color with missing arguments")]
+    #[test_case("\x1b[98mHello", vec![Span::empty().with_text(b"Hello".to_vec())], "Unsupported graphics mode: 98"; "unknown 4 bit color")]
+    #[test_case("\x1b[1;108mHello", vec![Span::empty().with_brightness(Brightness::Bold).with_text(b"Hello".to_vec())], "Unsupported graphics mode: 108"; "unknown 4 bit background color after valid parameter")]
+    fn malformed_sequence_should_be_reported_and_skipped(input: &str, expected: Vec<Span>, expected_diagnostic: &str) {
+        let (spans, diagnostics) = parse_ansi_text_with_diagnostics(input);
+
+        assert_eq!(spans, expected);
+        assert_eq!(diagnostics, vec![expected_diagnostic.to_string()]);
+    }
+
+    #[test]
+    fn unsupported_sequence_should_be_reported() {
+        let (spans, diagnostics) = parse_ansi_text_with_diagnostics("Hello\x1b[2A world");
+
+        assert_eq!(spans, vec![Span::empty().with_text(b"Hello world".to_vec())]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("Unsupported ANSI sequence: CursorUp"));
+    }
+
+    #[test]
+    fn diagnostics_should_be_reported_when_splitting_to_lines() {
+        let diagnostics = Arc::new(Mutex::new(vec![]));
+        let diagnostics_for_sink = diagnostics.clone();
+
+        let lines = parse_ansi_text_split_by_lines_with_options(
+            "\x1b[38mHello\n\x1b[99mworld",
+            ParseOptions::default().with_diagnostics_sink(move |diagnostic| {
+                diagnostics_for_sink.lock().unwrap().push(diagnostic.error.to_string());
+            }),
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                Line {
+                    spans: vec![Span::empty().with_text(b"Hello".to_vec())],
+                    location_in_file: 0,
+                },
+                Line {
+                    spans: vec![Span::empty().with_text(b"world".to_vec())],
+                    location_in_file: 11,
+                },
+            ]
+        );
+        assert_eq!(
+            *diagnostics.lock().unwrap(),
+            vec!["Invalid color: 38".to_string(), "Unsupported graphics mode: 99".to_string()]
+        );
+    }
+
+    #[test]
+    fn missing_file_should_return_error() {
+        let result = read_ansi_file_to_spans(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: "/non-existing-file.ansi".to_string(),
+
+                chunk_size_in_bytes: None,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        });
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    // ----------------------------------
+    // Parse modes
+    // ----------------------------------
+
+    fn try_parse_ansi_text_with_options(input: &str, parse_options: ParseOptions) -> Vec<Result<Span, Error>> {
+        let tmp_file_path = create_tmp_file(input.to_string());
+
+        return read_ansi_file_to_spans(ReadAnsiFileOptions {
+            parse_options,
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes: None,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect();
+    }
+
+    fn diagnostics_to_strings(collector: &DiagnosticsCollector) -> Vec<(usize, String)> {
+        return collector
+            .take()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.location, diagnostic.error.to_string()))
+            .collect();
+    }
+
+    #[test_case("Hi\x1b[38mHello", "Invalid color: 38 at byte 2"; "invalid color")]
+    #[test_case("Hi\x1b[1\x07Hello", "Escape character is not followed by a valid sequence at byte 2"; "malformed escape")]
+    #[test_case("Hi\x1b[1", "Escape sequence is not terminated at byte 2"; "unterminated escape")]
+    fn strict_mode_should_stop_at_first_malformed_sequence(input: &str, expected_error: &str) {
+        let result = try_parse_ansi_text_with_options(input, ParseOptions::default().with_mode(ParseMode::Strict));
+
+        let error = result.last().expect("should have result").as_ref().expect_err("should fail");
+
+        assert!(matches!(error, Error::MalformedSequence { location: 2, .. }));
+        assert_eq!(error.to_string(), expected_error);
+    }
+
+    #[test]
+    fn strict_mode_should_return_spans_completed_before_the_malformed_sequence() {
+        let result = try_parse_ansi_text_with_options(
+            "\x1b[31mHello\x1b[32m world\x1b[38m!",
+            ParseOptions::default().with_mode(ParseMode::Strict),
+        );
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].as_ref().expect("first span"), &Span::empty().with_color(Color::Red).with_text(b"Hello".to_vec()));
+        assert!(matches!(result[1], Err(Error::MalformedSequence { location: 21, .. })));
+    }
+
+    #[test]
+    fn strict_mode_should_not_fail_on_unsupported_sequence() {
+        let spans = parse_ansi_text_with_options("Hello\x1b[2A world", ParseOptions::default().with_mode(ParseMode::Strict));
+
+        assert_eq!(spans, vec![Span::empty().with_text(b"Hello world".to_vec())]);
+    }
+
+    #[test]
+    fn only_options_with_sink_or_collector_should_report_anomalies() {
+        assert!(!ParseOptions::default().reports_anomalies());
+        assert!(!ParseOptions::default().with_mode(ParseMode::Strict).reports_anomalies());
+        assert!(ParseOptions::default().with_mode(ParseMode::Collect(DiagnosticsCollector::new())).reports_anomalies());
+        assert!(ParseOptions::default().with_diagnostics_sink(|_| {}).reports_anomalies());
+    }
+
+    #[test]
+    fn lenient_mode_should_treat_malformed_escape_as_text() {
+        let spans = parse_ansi_text("Hi\x1b[1\x07Hello\x1b[1");
+
+        assert_eq!(spans, vec![Span::empty().with_text(b"Hi\x1b[1\x07Hello\x1b[1".to_vec())]);
+    }
+
+    #[test]
+    fn collect_mode_should_record_every_anomaly_with_location() {
+        let collector = DiagnosticsCollector::new();
+        let input = "\x1b[38mHi\x1b[2A\x1b[1\x07\x1b[1";
+
+        let spans = parse_ansi_text_with_options(input, ParseOptions::default().with_mode(ParseMode::Collect(collector.clone())));
+
+        assert_eq!(spans, parse_ansi_text(input));
+        assert_eq!(
+            diagnostics_to_strings(&collector),
+            vec![
+                (0, "Invalid color: 38".to_string()),
+                (7, "Unsupported ANSI sequence: CursorUp(2)".to_string()),
+                (11, "Escape character is not followed by a valid sequence".to_string()),
+                (15, "Escape sequence is not terminated".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_mode_should_have_the_same_locations_in_all_parsers() {
+        let input = "\x1b[38mHi\nthere\x1b[1\x07\n\x1b[99mBye\x1b[1";
+        let collector = DiagnosticsCollector::new();
+        let options = ParseOptions::default().with_mode(ParseMode::Collect(collector.clone()));
+
+        parse_ansi_text_with_options(input, options.clone());
+        let expected = diagnostics_to_strings(&collector);
+
+        assert_eq!(expected.len(), 4);
+
+        parse_ansi_text_split_by_lines_with_options(input, options.clone());
+        assert_eq!(diagnostics_to_strings(&collector), expected, "file to lines");
+
+        buffer_to_spans(input.as_bytes(), options.clone()).for_each(drop);
+        assert_eq!(diagnostics_to_strings(&collector), expected, "buffer to spans");
+
+        buffer_to_lines(input.as_bytes(), options.clone()).for_each(drop);
+        assert_eq!(diagnostics_to_strings(&collector), expected, "buffer to lines");
+
+        buffer_to_span_refs(input.as_bytes(), &options).for_each(drop);
+        assert_eq!(diagnostics_to_strings(&collector), expected, "buffer to span refs");
+
+        buffer_to_line_refs(input.as_bytes(), &options).for_each(drop);
+        assert_eq!(diagnostics_to_strings(&collector), expected, "buffer to line refs");
+    }
+
+    // ----------------------------------
+    // Readers
+    // ----------------------------------
+
+    // Return a single byte on every read, like a slow pipe, so escape codes are split between chunks
+    struct SingleByteReader<'a> {
+        data: &'a [u8],
+    }
+
+    impl Read for SingleByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.data.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+
+            buf[0] = self.data[0];
+            self.data = &self.data[1..];
+
+            return Ok(1);
+        }
+    }
+
+    #[test_case("Hello, world!"; "no escape codes")]
+    #[test_case("\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye"; "style across lines")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ after\n"; "hyperlink")]
+    #[test_case("\x1b[31mHello, \x1b[2Aworld!\nBye\x1b[1"; "unfinished escape code at the end")]
+    fn reader_should_have_the_same_spans_and_lines_as_file(input: &str) {
+        let spans = read_ansi_to_spans(SingleByteReader { data: input.as_bytes() }, ParseOptions::default())
+            .collect::<Result<Vec<Span>, Error>>()
+            .expect("parse failed");
+        let lines = read_ansi_to_lines(SingleByteReader { data: input.as_bytes() }, ParseOptions::default())
+            .collect::<Result<Vec<Line>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(spans, parse_ansi_text(input));
+        assert_eq!(lines, parse_ansi_text_split_by_lines_with_options(input, ParseOptions::default()));
+    }
+
+    #[test]
+    fn reader_should_have_the_same_command_block_parts_as_file() {
+        let input = "out\n\x1b]133;A\x07$ \x1b]133;B\x07ls\n\x1b]133;C\x07a.txt\n\x1b]133;D;0\x07";
+        let tmp_file_path = create_tmp_file(input.to_string());
+
+        let file_parts = read_ansi_file_to_command_block_parts(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes: None,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect::<Result<Vec<CommandBlockPart>, Error>>()
+        .expect("parse failed");
+
+        let reader_parts = read_ansi_to_command_block_parts(input.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<CommandBlockPart>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(reader_parts, file_parts);
+        assert_eq!(reader_parts.last(), Some(&CommandBlockPart::Finished(Some(0))));
+    }
+
+    #[test]
+    fn reader_should_have_the_same_command_blocks_as_file() {
+        let input = "\x1b]133;A\x07$ \x1b]133;B\x07ls\n\x1b]133;C\x07a.txt\nb.txt\n\x1b]133;D;0\x07\x1b]133;A\x07$ ";
+
+        let blocks = read_ansi_to_command_blocks(input.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<CommandBlock>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(blocks, parse_ansi_text_to_command_blocks(input, None));
+    }
+
+    // ----------------------------------
+    // Compressed files
+    // ----------------------------------
+
+    fn read_lines_from_middle_of_file(file_path: &str, mapping_file_path: &str, from_line: usize, to_line: usize) -> Vec<Line> {
+        let from_middle_of_file = get_from_middle_of_the_file_info(
+            PathBuf::from(file_path),
+            Some(from_line),
+            Some(to_line),
+            Some(mapping_file_path.to_string()),
+        )
+        .expect("read mapping file failed");
+
+        return read_ansi_file_to_lines(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default()
+                .with_initial_span(from_middle_of_file.initial_span.unwrap_or(Span::empty())),
+            file_options: FileReaderOptions {
+                file_path: file_path.to_string(),
+
+                chunk_size_in_bytes: None,
+                from_bytes: from_middle_of_file.from_bytes,
+                to_bytes: from_middle_of_file.to_bytes,
+                seek_point: from_middle_of_file.seek_point,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect::<Result<Vec<Line>, Error>>()
+        .expect("parse failed");
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn compressed_file_should_have_the_same_lines_as_uncompressed_file(compression: Compression) {
+        let input = "\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye";
+
+        let tmp_file_path = get_tmp_file_path();
+        std::fs::write(tmp_file_path.clone(), compress(compression, input.as_bytes())).expect("Failed to write to file");
+
+        let lines = read_ansi_file_to_lines(ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: tmp_file_path,
+
+                chunk_size_in_bytes: Some(3),
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        })
+        .expect("read file failed")
+        .collect::<Result<Vec<Line>, Error>>()
+        .expect("parse failed");
+
+        assert_eq!(lines, parse_ansi_text_split_by_lines_with_options(input, ParseOptions::default()));
+    }
+
+    #[test_case(Compression::Gzip; "gzip")]
+    #[test_case(Compression::Zstd; "zstd")]
+    #[test_case(Compression::Xz; "xz")]
+    fn reading_from_middle_of_compressed_file_should_have_the_same_lines_as_uncompressed_file(compression: Compression) {
+        let members = ["\x1b[31mHello\nworld\n", "how are \x1b[1myou\n", "I'm fine\x1b[0m\nGreat\nBye"];
+
+        let tmp_file_path = create_tmp_file(members.concat());
+        let tmp_compressed_file_path = get_tmp_file_path();
+        std::fs::write(
+            tmp_compressed_file_path.clone(),
+            members.map(|member| compress(compression, member.as_bytes())).concat(),
+        )
+        .expect("Failed to write to file");
+
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_compressed_mapping_file_path = get_tmp_file_path();
+        create_mapping_file_from_input_path(PathBuf::from(&tmp_mapping_file_path), PathBuf::from(&tmp_file_path))
+            .expect("create mapping file failed");
+        create_mapping_file_from_input_path(PathBuf::from(&tmp_compressed_mapping_file_path), PathBuf::from(&tmp_compressed_file_path))
+            .expect("create mapping file failed");
+
+        for (from_line, to_line) in [(1, 2), (3, 4), (4, 6), (5, 6)] {
+            assert_eq!(
+                read_lines_from_middle_of_file(&tmp_compressed_file_path, &tmp_compressed_mapping_file_path, from_line, to_line),
+                read_lines_from_middle_of_file(&tmp_file_path, &tmp_mapping_file_path, from_line, to_line),
+                "from line {} to line {}",
+                from_line,
+                to_line
+            );
+        }
+    }
+
+    #[test]
+    fn reading_from_middle_of_file_with_compact_mapping_file_should_have_the_same_lines_as_fixed_mapping_file() {
+        let tmp_file_path = create_tmp_file("\x1b[31mHello\nworld\nhow are \x1b[1myou\nI'm fine\x1b[0m\nGreat\nBye".to_string());
+
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_compact_mapping_file_path = get_tmp_file_path();
+        create_mapping_file_from_input_path(PathBuf::from(&tmp_mapping_file_path), PathBuf::from(&tmp_file_path))
+            .expect("create mapping file failed");
+        create_mapping_file_with_layout(PathBuf::from(&tmp_compact_mapping_file_path), PathBuf::from(&tmp_file_path), MappingLayout::Compact)
+            .expect("create mapping file failed");
+
+        for (from_line, to_line) in [(1, 2), (3, 4), (4, 6), (5, 6)] {
+            assert_eq!(
+                read_lines_from_middle_of_file(&tmp_file_path, &tmp_compact_mapping_file_path, from_line, to_line),
+                read_lines_from_middle_of_file(&tmp_file_path, &tmp_mapping_file_path, from_line, to_line),
+                "from line {} to line {}",
+                from_line,
+                to_line
+            );
+        }
+    }
+
+    #[test_case("\x1b[31mA\n\nB\nC"; "style before the empty line")]
+    #[test_case("A\x1b[31m\n\nB\nC"; "style at the end of the line before the empty line")]
+    #[test_case("A\n\x1b[31m\n\nB\nC"; "line with only style before the empty line")]
+    fn reading_from_empty_line_in_middle_of_file_should_keep_the_style(input: &str) {
+        let tmp_file_path = create_tmp_file(input.to_string());
+        let all_lines = parse_ansi_text_split_by_lines_with_options(input, ParseOptions::default());
+
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_compact_mapping_file_path = get_tmp_file_path();
+        let tmp_sparse_mapping_file_path = get_tmp_file_path();
+        create_mapping_file_from_input_path(PathBuf::from(&tmp_mapping_file_path), PathBuf::from(&tmp_file_path))
+            .expect("create mapping file failed");
+        create_mapping_file_with_layout(PathBuf::from(&tmp_compact_mapping_file_path), PathBuf::from(&tmp_file_path), MappingLayout::Compact)
+            .expect("create mapping file failed");
+        create_sparse_mapping_file(PathBuf::from(&tmp_sparse_mapping_file_path), PathBuf::from(&tmp_file_path), MappingLayout::Fixed, 2)
+            .expect("create mapping file failed");
+
+        for mapping_file_path in [&tmp_mapping_file_path, &tmp_compact_mapping_file_path, &tmp_sparse_mapping_file_path] {
+            for from_line in 2..=all_lines.len() {
+                // The locations are relative to where the reading started
+                assert_eq!(
+                    read_lines_from_middle_of_file(&tmp_file_path, mapping_file_path, from_line, all_lines.len())
+                        .into_iter()
+                        .map(|line| line.spans)
+                        .collect::<Vec<Vec<Span>>>(),
+                    all_lines[from_line - 1..].iter().map(|line| line.spans.clone()).collect::<Vec<Vec<Span>>>(),
+                    "from line {}",
+                    from_line
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reading_from_middle_of_file_with_sparse_mapping_file_should_have_the_same_lines_as_mapping_file_with_every_line() {
+        let tmp_file_path = create_tmp_file("\x1b[31mHello\nworld\nhow are \x1b[1myou\nI'm fine\x1b[0m\nGreat\nBye".to_string());
+
+        let tmp_mapping_file_path = get_tmp_file_path();
+        let tmp_sparse_mapping_file_path = get_tmp_file_path();
+        create_mapping_file_from_input_path(PathBuf::from(&tmp_mapping_file_path), PathBuf::from(&tmp_file_path))
+            .expect("create mapping file failed");
+        create_sparse_mapping_file(PathBuf::from(&tmp_sparse_mapping_file_path), PathBuf::from(&tmp_file_path), MappingLayout::Fixed, 4)
+            .expect("create mapping file failed");
+
+        for (from_line, to_line) in [(1, 2), (3, 4), (4, 6), (5, 6), (2, 5)] {
+            assert_eq!(
+                read_lines_from_middle_of_file(&tmp_file_path, &tmp_sparse_mapping_file_path, from_line, to_line),
+                read_lines_from_middle_of_file(&tmp_file_path, &tmp_mapping_file_path, from_line, to_line),
+                "from line {} to line {}",
+                from_line,
+                to_line
+            );
+        }
+    }
+
+    // ----------------------------------
+    // Memory mapped files
+    // ----------------------------------
+
+    fn read_file_options(file_path: &str, from_bytes: Option<usize>, to_bytes: Option<usize>, backend: FileReaderBackend) -> ReadAnsiFileOptions {
+        return ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: file_path.to_string(),
+
+                // Small chunks so escape codes are split between chunks when not mapped
+                chunk_size_in_bytes: Some(2),
+                from_bytes,
+                to_bytes,
+                seek_point: None,
+                backend: Some(backend),
+            },
+        };
+    }
+
+    #[test_case("", None, None; "empty file")]
+    #[test_case("Hello, world!", None, None; "no escape codes")]
+    #[test_case("\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye\n", None, None; "style across lines")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ after\n", None, None; "hyperlink")]
+    #[test_case("\x1b[31mHello, \x1b[2Aworld!\nBye\x1b[1", None, None; "unfinished escape code at the end")]
+    #[test_case("\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye\n", Some(11), Some(27); "range")]
+    #[test_case("\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye\n", Some(12), None; "from the middle until the end")]
+    fn mapped_file_should_have_the_same_spans_and_lines_as_chunks(input: &str, from_bytes: Option<usize>, to_bytes: Option<usize>) {
+        let tmp_file_path = create_tmp_file(input.to_string());
+
+        let read_spans = |backend| {
+            return read_ansi_file_to_spans(read_file_options(&tmp_file_path, from_bytes, to_bytes, backend))
+                .expect("read file failed")
+                .collect::<Result<Vec<Span>, Error>>()
+                .expect("parse failed");
+        };
+        let read_lines = |backend| {
+            return read_ansi_file_to_lines(read_file_options(&tmp_file_path, from_bytes, to_bytes, backend))
+                .expect("read file failed")
+                .collect::<Result<Vec<Line>, Error>>()
+                .expect("parse failed");
+        };
+
+        assert_eq!(read_spans(FileReaderBackend::Mmap), read_spans(FileReaderBackend::Chunks));
+        assert_eq!(read_lines(FileReaderBackend::Mmap), read_lines(FileReaderBackend::Chunks));
+    }
+
+    #[test]
+    fn mapped_file_should_stop_at_the_first_malformed_sequence_in_strict_mode() {
+        let tmp_file_path = create_tmp_file("Hello \x1b[31mworld\x1b[38;7m!".to_string());
+
+        let read_spans = |backend| {
+            let mut options = read_file_options(&tmp_file_path, None, None, backend);
+            options.parse_options = ParseOptions::default().with_mode(ParseMode::Strict);
+
+            return read_ansi_file_to_spans(options)
+                .expect("read file failed")
+                .map(|span| span.map_err(|error| error.to_string()))
+                .collect::<Vec<Result<Span, String>>>();
+        };
+
+        assert_eq!(read_spans(FileReaderBackend::Mmap), read_spans(FileReaderBackend::Chunks));
+    }
+
+    // ----------------------------------
+    // Follow
+    // ----------------------------------
+
+    fn follow_file_options(file_path: &str) -> ReadAnsiFileOptions {
+        return ReadAnsiFileOptions {
+            parse_options: ParseOptions::default(),
+            file_options: FileReaderOptions {
+                file_path: file_path.to_string(),
+
+                chunk_size_in_bytes: None,
+                from_bytes: None,
+                to_bytes: None,
+                seek_point: None,
+                backend: None,
+            },
+        };
+    }
+
+    // Each wait at the end of the file runs the next write instead of sleeping, and following stops when there are no more writes
+    fn follow_options(writes: Vec<Box<dyn FnOnce() + Send>>) -> FollowOptions {
+        let writes = Mutex::new(VecDeque::from(writes));
+
+        return FollowOptions {
+            poll_interval: None,
+            stop_when_idle_for: None,
+            wait: Some(Arc::new(move |_| match writes.lock().unwrap().pop_front() {
+                Some(write) => {
+                    write();
+                    true
+                }
+                None => false,
+            })),
+        };
+    }
+
+    fn append_to_file(file_path: &str, text: &str) {
+        let mut file = std::fs::OpenOptions::new().append(true).open(file_path).expect("open file");
+
+        file.write_all(text.as_bytes()).expect("write to file");
+    }
+
+    fn appending_writes(file_path: &str, texts: &[&'static str]) -> Vec<Box<dyn FnOnce() + Send>> {
+        return texts
+            .iter()
+            .map(|&text| {
+                let file_path = file_path.to_string();
+
+                Box::new(move || append_to_file(&file_path, text)) as Box<dyn FnOnce() + Send>
+            })
+            .collect();
+    }
+
+    const FOLLOWED_WRITES: [&str; 5] =
+        ["\x1b[31mHello", "\nwor", "ld\x1b[", "1m!\n\x1b]8;;https://example.com\x1b\\li", "nk\x1b]8;;\x1b\\\nBye"];
+
+    #[test]
+    fn follow_should_keep_the_style_and_unfinished_escape_code_between_writes() {
+        let tmp_file_path = create_tmp_file(FOLLOWED_WRITES[0].to_string());
+
+        let lines = follow_ansi_file_to_lines(
+            follow_file_options(&tmp_file_path),
+            follow_options(appending_writes(&tmp_file_path, &FOLLOWED_WRITES[1..])),
+        )
+        .expect("follow file failed")
+        .collect::<Result<Vec<Line>, Error>>()
+        .expect("parse failed");
+
+        assert_eq!(lines, parse_ansi_text_split_by_lines_with_options(&FOLLOWED_WRITES.concat(), ParseOptions::default()));
+    }
+
+    #[test]
+    fn follow_spans_should_keep_the_style_and_unfinished_escape_code_between_writes() {
+        let tmp_file_path = create_tmp_file(FOLLOWED_WRITES[0].to_string());
+
+        let spans = follow_ansi_file_to_spans(
+            follow_file_options(&tmp_file_path),
+            follow_options(appending_writes(&tmp_file_path, &FOLLOWED_WRITES[1..])),
+        )
+        .expect("follow file failed")
+        .collect::<Result<Vec<Span>, Error>>()
+        .expect("parse failed");
+
+        assert_eq!(spans, parse_ansi_text(&FOLLOWED_WRITES.concat()));
+    }
+
+    #[test]
+    fn follow_should_start_from_the_beginning_without_style_when_file_is_truncated() {
+        let old_text = "\x1b[31mold line\nold";
+        let new_text = "new\n";
+
+        let tmp_file_path = create_tmp_file(old_text.to_string());
+        let writer_file_path = tmp_file_path.clone();
+
+        let lines = follow_ansi_file_to_lines(
+            follow_file_options(&tmp_file_path),
+            follow_options(vec![Box::new(move || std::fs::write(&writer_file_path, new_text).expect("truncate file"))]),
+        )
+        .expect("follow file failed")
+        .collect::<Result<Vec<Line>, Error>>()
+        .expect("parse failed");
+
+        let mut expected = parse_ansi_text_split_by_lines_with_options(old_text, ParseOptions::default());
+        expected.extend(parse_ansi_text_split_by_lines_with_options(new_text, ParseOptions::default()));
+
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn follow_spans_should_start_from_the_beginning_without_style_when_file_is_truncated() {
+        let old_text = "\x1b[31mold line\nold";
+        let new_text = "new\n";
+
+        let tmp_file_path = create_tmp_file(old_text.to_string());
+        let writer_file_path = tmp_file_path.clone();
+
+        let spans = follow_ansi_file_to_spans(
+            follow_file_options(&tmp_file_path),
+            follow_options(vec![Box::new(move || std::fs::write(&writer_file_path, new_text).expect("truncate file"))]),
+        )
+        .expect("follow file failed")
+        .collect::<Result<Vec<Span>, Error>>()
+        .expect("parse failed");
+
+        let mut expected = parse_ansi_text(old_text);
+        expected.extend(parse_ansi_text(new_text));
+
+        assert_eq!(spans, expected);
+    }
+
+    // ----------------------------------
+    // Borrowed spans
+    // ----------------------------------
+
+    // Span refs and spans split text differently when escape code does not change the style
+    fn merge_spans_with_same_style(spans: Vec<Span>) -> Vec<Span> {
+        let mut merged: Vec<Span> = vec![];
+
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if Span::clone_without_text(last) == Span::clone_without_text(&span) => {
+                    last.text.extend_from_slice(&span.text);
+                }
+                _ => merged.push(span),
+            }
+        }
+
+        return merged;
+    }
+
+    #[test_case("Hello, world!"; "no escape codes")]
+    #[test_case("\x1b[31mHello, \x1b[1;4mworld!\x1b[0m Bye"; "style changes")]
+    #[test_case("\x1b[31mHello, \x1b[2Aworld!\x1b[31m Bye"; "escape codes that does not change the style")]
+    #[test_case("\x1b]8;;https://example.com\x1b\\link\x1b[0m text\x1b]8;;\x1b\\ after"; "hyperlink")]
+    #[test_case("\x1b[58;2;1;2;3m\x1b[4:3mHello\x1b[24m world"; "extended attributes")]
+    fn span_refs_should_have_the_same_spans_as_owned_spans(input: &str) {
+        let spans = buffer_to_spans(input.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<Span>, Error>>()
+            .expect("parse failed");
+        let span_refs = buffer_to_span_refs(input.as_bytes(), &ParseOptions::default())
+            .map(|span| span.map(|span| span.to_span()))
+            .collect::<Result<Vec<Span>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(merge_spans_with_same_style(span_refs), merge_spans_with_same_style(spans));
+    }
+
+    #[test]
+    fn span_refs_text_should_be_borrowed_from_the_buffer() {
+        let input = b"\x1b[31mHello, \x1b[32mworld!";
+        let buffer_range = input.as_ptr_range();
+
+        let options = ParseOptions::default();
+
+        let span_refs = buffer_to_span_refs(input, &options)
+            .collect::<Result<Vec<SpanRef>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(span_refs.len(), 2);
+        for span in span_refs {
+            assert!(buffer_range.contains(&span.text.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn span_refs_should_not_split_text_without_escape_codes_in_between() {
+        let input = b"\x1b[31mHello, world!\x1b[0m";
+
+        let options = ParseOptions::default();
+
+        let span_refs = buffer_to_span_refs(input, &options)
+            .collect::<Result<Vec<SpanRef>, Error>>()
+            .expect("parse failed");
+
+        assert_eq!(
+            span_refs,
+            vec![SpanRef {
+                color: Color::Red,
+                ..SpanRef::empty().with_text(b"Hello, world!")
+            }]
+        );
+    }
+
+    #[test_case("Hello\nworld!\n"; "no escape codes")]
+    #[test_case("\x1b[31mHello\n\n\x1b[1mworld!\x1b[0m\nBye"; "style across lines")]
+    #[test_case("\x1b[31mHello, \x1b[2Aworld!\nBye\x1b[1"; "unfinished escape code at the end")]
+    fn line_refs_should_have_the_same_lines_as_owned_lines(input: &str) {
+        let lines = buffer_to_lines(input.as_bytes(), ParseOptions::default())
+            .collect::<Result<Vec<Line>, Error>>()
+            .expect("parse failed");
+        let line_refs = buffer_to_line_refs(input.as_bytes(), &ParseOptions::default())
+            .map(|line| {
+                let line = line.expect("parse failed").to_line();
+
+                Line {
+                    spans: merge_spans_with_same_style(line.spans),
+                    location_in_file: line.location_in_file,
+                }
+            })
+            .collect::<Vec<Line>>();
+        let lines = lines
+            .into_iter()
+            .map(|line| Line {
+                spans: merge_spans_with_same_style(line.spans),
+                location_in_file: line.location_in_file,
+            })
+            .collect::<Vec<Line>>();
+
+        assert_eq!(line_refs, lines);
+    }
 
     // ----------------------------------
     // Lines location
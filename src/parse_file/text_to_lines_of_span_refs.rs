@@ -0,0 +1,134 @@
+use genawaiter::sync::{Co, Gen};
+use memchr::memchr_iter;
+
+use crate::error::Error;
+use crate::parse_ansi_text::ansi::types::SpanRef;
+use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_span_refs::{
+    apply_ansi_sequence_to_span_ref_style, SpanRefBuilder,
+};
+use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
+use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_ansi_text::raw_ansi_parse::Output;
+use crate::types::LineRef;
+
+fn append_text_to_line<'a>(
+    current_line: &mut LineRef<'a>,
+    span_builder: &mut SpanRefBuilder<'a>,
+    style: &SpanRef<'a>,
+    start: usize,
+    end: usize,
+) {
+    if let Some(span) = span_builder.append_text(style, start, end) {
+        current_line.spans.push(span);
+    }
+}
+
+// Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
+async fn buffer_to_line_refs_producer<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+    co: Co<Result<LineRef<'a>, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_buffer_to_line_refs(buffer, options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
+
+async fn try_buffer_to_line_refs<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+    co: &Co<Result<LineRef<'a>, Error>>,
+) -> Result<(), Error> {
+    let mut style: SpanRef = SpanRef::from_style(&options.initial_span);
+    let mut span_builder = SpanRefBuilder::new(buffer);
+
+    let mut current_line = LineRef {
+        location_in_file: 0,
+        spans: vec![],
+    };
+
+    // The location in the buffer of the current output
+    let mut location: usize = 0;
+
+    let mut result: ParseAnsiResult = parse_ansi_continues(buffer);
+
+    // Unfinished escape code at the end is treated as text
+    loop {
+        let (text_start, text_end) = match &result.output {
+            Some(Output::TextBlock(text)) => {
+                if result.malformed_escape {
+                    options.handle_anomaly(Error::MalformedEscape, location)?;
+                }
+
+                (location, location + text.text.len())
+            }
+            Some(Output::Escape(seq)) => {
+                apply_ansi_sequence_to_span_ref_style(seq, &mut style, location, options)?;
+
+                location += result.size;
+                result = parse_ansi_continues(result.pending_string);
+
+                continue;
+            }
+            None => {
+                if location < buffer.len() {
+                    options.handle_anomaly(Error::UnterminatedEscape, location)?;
+                }
+
+                (location, buffer.len())
+            }
+        };
+
+        let mut line_text_start = text_start;
+
+        for new_line_index in memchr_iter(b'\n', &buffer[text_start..text_end]) {
+            let new_line_location = text_start + new_line_index;
+
+            append_text_to_line(&mut current_line, &mut span_builder, &style, line_text_start, new_line_location);
+
+            if let Some(span) = span_builder.take() {
+                current_line.spans.push(span);
+            }
+
+            let next_line = LineRef {
+                location_in_file: new_line_location + 1,
+                spans: vec![],
+            };
+
+            co.yield_(Ok(std::mem::replace(&mut current_line, next_line))).await;
+
+            line_text_start = new_line_location + 1;
+        }
+
+        append_text_to_line(&mut current_line, &mut span_builder, &style, line_text_start, text_end);
+
+        if result.output.is_none() {
+            break;
+        }
+
+        location += result.size;
+        result = parse_ansi_continues(result.pending_string);
+    }
+
+    if let Some(span) = span_builder.take() {
+        current_line.spans.push(span);
+    }
+
+    // Yielding the last line
+    co.yield_(Ok(current_line)).await;
+
+    return Ok(());
+}
+
+// Same as `buffer_to_lines` but without copying the text,
+// text that is separated by escape codes that does not change the style (e.g. cursor movement) is returned as separate spans
+// The options are borrowed as well, as the initial span link is borrowed from them
+// Only the text is not copied, escape codes that change the style still allocate while they are parsed
+pub fn buffer_to_line_refs<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+) -> impl Iterator<Item=Result<LineRef<'a>, Error>> + 'a {
+    return Gen::new(|co| buffer_to_line_refs_producer(buffer, options, co)).into_iter();
+}
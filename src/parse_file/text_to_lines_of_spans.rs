@@ -1,19 +1,26 @@
 use genawaiter::sync::{Co, Gen};
-use crate::files::file_reader::FileReader;
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_line_of_spans::{
-    convert_ansi_output_lines_of_spans_continues, ResultType,
+    convert_ansi_output_lines_of_spans_continues, remove_empty_last_span, ResultType,
 };
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::Output;
 use crate::parse_ansi_text::raw_ansi_parse::Text;
-use crate::parse_file::types::ReadAnsiFileOptions;
 use crate::types::Line;
 
 // Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
-async fn buffer_to_lines_producer(mut buffer: &[u8], co: Co<Line>) {
-    let current_span: Span = Span::empty();
+pub(crate) async fn buffer_to_lines_producer(buffer: &[u8], options: ParseOptions, co: Co<Result<Line, Error>>) {
+    // The error stops the parsing
+    if let Err(error) = try_buffer_to_lines(buffer, options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
+
+async fn try_buffer_to_lines(mut buffer: &[u8], options: ParseOptions, co: &Co<Result<Line, Error>>) -> Result<(), Error> {
+    let current_span: Span = options.initial_span.clone().replace_default_color_with_none();
     let mut end_of_line_index: usize = 0;
 
     let mut current_line = Line {
@@ -21,19 +28,27 @@ async fn buffer_to_lines_producer(mut buffer: &[u8], co: Co<Line>) {
         spans: vec![current_span],
     };
 
-
     let mut result: ParseAnsiResult = parse_ansi_continues(buffer);
 
     while let Some(ready_output) = result.output {
+        // The location of the output in the buffer
+        let location = end_of_line_index;
         end_of_line_index += result.size;
+
+        if result.malformed_escape {
+            options.handle_anomaly(Error::MalformedEscape, location)?;
+        }
+
         let mut lines_result = convert_ansi_output_lines_of_spans_continues(
             Some(ready_output),
             &mut current_line,
             end_of_line_index,
-        );
+            location,
+            &options,
+        )?;
 
         while let ResultType::Parse(next_line) = lines_result {
-            co.yield_(current_line).await;
+            co.yield_(Ok(current_line)).await;
 
             current_line = next_line;
 
@@ -41,13 +56,22 @@ async fn buffer_to_lines_producer(mut buffer: &[u8], co: Co<Line>) {
                 None,
                 &mut current_line,
                 end_of_line_index,
-            );
+                location,
+                &options,
+            )?;
         }
 
         buffer = result.pending_string;
         result = parse_ansi_continues(buffer);
     }
 
+    // Unfinished escape code at the end is treated as text
+    let location = end_of_line_index;
+
+    if !result.pending_string.is_empty() {
+        options.handle_anomaly(Error::UnterminatedEscape, location)?;
+    }
+
     let ready_output = Output::TextBlock(Text {
         text: result.pending_string,
     });
@@ -58,10 +82,12 @@ async fn buffer_to_lines_producer(mut buffer: &[u8], co: Co<Line>) {
         Some(ready_output),
         &mut current_line,
         end_of_line_index,
-    );
+        location,
+        &options,
+    )?;
 
     while let ResultType::Parse(next_line) = lines_result {
-        co.yield_(current_line).await;
+        co.yield_(Ok(current_line)).await;
 
         current_line = next_line;
 
@@ -69,21 +95,19 @@ async fn buffer_to_lines_producer(mut buffer: &[u8], co: Co<Line>) {
             None,
             &mut current_line,
             end_of_line_index,
-        );
+            location,
+            &options,
+        )?;
     }
 
-    let last_span = current_line.spans.last();
-
-    if let Some(last_span) = last_span {
-        if last_span.text.is_empty() {
-            current_line.spans.pop();
-        }
-    }
+    remove_empty_last_span(&mut current_line.spans, &options);
 
     // Yielding the last line
-    co.yield_(current_line).await;
+    co.yield_(Ok(current_line)).await;
+
+    return Ok(());
 }
 
-pub fn buffer_to_lines(buffer: &[u8]) -> impl Iterator<Item=Line> + '_ {
-    return Gen::new(|co| buffer_to_lines_producer(buffer, co)).into_iter();
+pub fn buffer_to_lines(buffer: &[u8], options: ParseOptions) -> impl Iterator<Item=Result<Line, Error>> + '_ {
+    return Gen::new(|co| buffer_to_lines_producer(buffer, options, co)).into_iter();
 }
@@ -0,0 +1,84 @@
+use genawaiter::sync::{Co, Gen};
+
+use crate::error::Error;
+use crate::parse_ansi_text::ansi::types::SpanRef;
+use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_span_refs::{
+    apply_ansi_sequence_to_span_ref_style, SpanRefBuilder,
+};
+use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
+use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
+use crate::parse_ansi_text::parse_options::ParseOptions;
+use crate::parse_ansi_text::raw_ansi_parse::Output;
+
+// Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
+async fn buffer_to_span_refs_producer<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+    co: Co<Result<SpanRef<'a>, Error>>,
+) {
+    // The error stops the parsing
+    if let Err(error) = try_buffer_to_span_refs(buffer, options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
+
+async fn try_buffer_to_span_refs<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+    co: &Co<Result<SpanRef<'a>, Error>>,
+) -> Result<(), Error> {
+    let mut style: SpanRef = SpanRef::from_style(&options.initial_span);
+    let mut span_builder = SpanRefBuilder::new(buffer);
+
+    // The location in the buffer of the current output
+    let mut location: usize = 0;
+
+    let mut result: ParseAnsiResult = parse_ansi_continues(buffer);
+
+    while let Some(ready_output) = result.output {
+        match ready_output {
+            Output::TextBlock(text) => {
+                if result.malformed_escape {
+                    options.handle_anomaly(Error::MalformedEscape, location)?;
+                }
+
+                if let Some(span) = span_builder.append_text(&style, location, location + text.text.len()) {
+                    co.yield_(Ok(span)).await;
+                }
+            }
+            Output::Escape(seq) => {
+                apply_ansi_sequence_to_span_ref_style(&seq, &mut style, location, options)?;
+            }
+        }
+
+        location += result.size;
+        result = parse_ansi_continues(result.pending_string);
+    }
+
+    // Unfinished escape code is treated as text
+    if location < buffer.len() {
+        options.handle_anomaly(Error::UnterminatedEscape, location)?;
+    }
+
+    if let Some(span) = span_builder.append_text(&style, location, buffer.len()) {
+        co.yield_(Ok(span)).await;
+    }
+
+    // Add last span if it has text
+    if let Some(span) = span_builder.take() {
+        co.yield_(Ok(span)).await;
+    }
+
+    return Ok(());
+}
+
+// Same as `buffer_to_spans` but without copying the text,
+// text that is separated by escape codes that does not change the style (e.g. cursor movement) is returned as separate spans
+// The options are borrowed as well, as the initial span link is borrowed from them
+// Only the text is not copied, escape codes that change the style still allocate while they are parsed
+pub fn buffer_to_span_refs<'a>(
+    buffer: &'a [u8],
+    options: &'a ParseOptions,
+) -> impl Iterator<Item=Result<SpanRef<'a>, Error>> + 'a {
+    return Gen::new(|co| buffer_to_span_refs_producer(buffer, options, co)).into_iter();
+}
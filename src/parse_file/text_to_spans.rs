@@ -1,30 +1,42 @@
 use genawaiter::sync::{Co, Gen};
 
-use crate::files::file_reader::FileReader;
+use crate::error::Error;
 use crate::parse_ansi_text::ansi::types::Span;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_spans::convert_ansi_output_to_spans_continues;
 use crate::parse_ansi_text::ansi_output_to_spans::parse_ansi_as_spans::ResultType;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::parse_ansi_continues;
 use crate::parse_ansi_text::ansi_text_to_output::str_part_parse::ParseAnsiResult;
+use crate::parse_ansi_text::parse_options::ParseOptions;
 use crate::parse_ansi_text::raw_ansi_parse::Output;
 use crate::parse_ansi_text::raw_ansi_parse::Text;
-use crate::parse_file::types::ReadAnsiFileOptions;
-
 
 // Using the low level API instead of relying on the gen! or producer! macros for better editor support and debugging
-async fn buffer_to_spans_producer(mut buffer: &[u8], co: Co<Span>) {
+pub(crate) async fn buffer_to_spans_producer(buffer: &[u8], options: ParseOptions, co: Co<Result<Span, Error>>) {
+    // The error stops the parsing
+    if let Err(error) = try_buffer_to_spans(buffer, options, &co).await {
+        co.yield_(Err(error)).await;
+    }
+}
 
-    let mut current_span: Span = Span::empty();
+async fn try_buffer_to_spans(mut buffer: &[u8], options: ParseOptions, co: &Co<Result<Span, Error>>) -> Result<(), Error> {
+    // The location in the buffer of the current output
+    let mut location: usize = 0;
+
+    let mut current_span: Span = options.initial_span.clone().replace_default_color_with_none();
 
     let mut result: ParseAnsiResult = parse_ansi_continues(buffer);
 
     while let Some(ready_output) = result.output {
+        if result.malformed_escape {
+            options.handle_anomaly(Error::MalformedEscape, location)?;
+        }
+
         let span_result =
-            convert_ansi_output_to_spans_continues(ready_output, &mut current_span);
+            convert_ansi_output_to_spans_continues(ready_output, &mut current_span, location, &options)?;
 
         match span_result {
             ResultType::Parse(next_span) => {
-                co.yield_(current_span).await;
+                co.yield_(Ok(current_span)).await;
 
                 current_span = next_span;
             }
@@ -36,22 +48,30 @@ async fn buffer_to_spans_producer(mut buffer: &[u8], co: Co<Span>) {
             }
         }
 
+        location += result.size;
         buffer = result.pending_string;
         result = parse_ansi_continues(buffer);
     }
 
+    // Unfinished escape code at the end is treated as text
+    if !result.pending_string.is_empty() {
+        options.handle_anomaly(Error::UnterminatedEscape, location)?;
+    }
+
     // Add last span if it has text
     if !current_span.text.is_empty() {
         let ready_output = Output::TextBlock(Text {
             text: result.pending_string,
         });
 
-        convert_ansi_output_to_spans_continues(ready_output, &mut current_span);
+        convert_ansi_output_to_spans_continues(ready_output, &mut current_span, location, &options)?;
 
-        co.yield_(current_span).await;
+        co.yield_(Ok(current_span)).await;
     }
+
+    return Ok(());
 }
 
-pub fn buffer_to_spans(buffer: &[u8]) -> impl Iterator<Item=Span> + '_ {
-    return Gen::new(|co| buffer_to_spans_producer(buffer, co)).into_iter();
+pub fn buffer_to_spans(buffer: &[u8], options: ParseOptions) -> impl Iterator<Item=Result<Span, Error>> + '_ {
+    return Gen::new(|co| buffer_to_spans_producer(buffer, options, co)).into_iter();
 }
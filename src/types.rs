@@ -1,7 +1,68 @@
-use crate::parse_ansi_text::ansi::types::Span;
+use crate::parse_ansi_text::ansi::types::{Span, SpanRef};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Line {
     pub spans: Vec<Span>,
     pub location_in_file: usize,
 }
+
+// Same as `Line` but the spans borrow the text from the parsed buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineRef<'a> {
+    pub spans: Vec<SpanRef<'a>>,
+    pub location_in_file: usize,
+}
+
+impl LineRef<'_> {
+    pub fn to_line(&self) -> Line {
+        Line {
+            spans: self.spans.iter().map(SpanRef::to_span).collect(),
+            location_in_file: self.location_in_file,
+        }
+    }
+}
+
+// Command in recorded terminal session, split by the shell integration marks (OSC 133)
+// Prompt and command are usually in the same line in the file, so they are split to different lines at the mark
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandBlock {
+    pub prompt: Vec<Line>,
+    pub command: Vec<Line>,
+    pub output: Vec<Line>,
+
+    // None when the command did not finish or the shell did not report the exit code
+    pub exit_code: Option<i32>,
+}
+
+impl CommandBlock {
+    pub fn empty() -> CommandBlock {
+        CommandBlock {
+            prompt: vec![],
+            command: vec![],
+            output: vec![],
+            exit_code: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.prompt.is_empty() && self.command.is_empty() && self.output.is_empty() && self.exit_code.is_none();
+    }
+
+    pub fn is_failed(&self) -> bool {
+        return matches!(self.exit_code, Some(exit_code) if exit_code != 0);
+    }
+}
+
+// Part of a command block as it's parsed, so the output of a long running command is not kept in memory
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandBlockPart {
+    // A new command block starts at the prompt, the parts before the first prompt are of a command block without a prompt
+    Start,
+
+    Prompt(Line),
+    Command(Line),
+    Output(Line),
+
+    // The exit code is None when the shell did not report it, output can still come after it until the next prompt
+    Finished(Option<i32>),
+}